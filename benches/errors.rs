@@ -0,0 +1,52 @@
+// Benchmarks of creating and formatting errors. Run with `cargo bench --bench errors`, and again
+// with `--features disclose` to measure the bare macros when they disclose the location.
+
+use std::hint::black_box;
+use std::io;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use tyg_template::{result_err, result_err_bare, Error, ErrorFormat, Result};
+
+fn disclosed(value: u32) -> Result<()> {
+    result_err!("Failed at cycle {}", value)
+}
+
+fn bare(value: u32) -> Result<()> {
+    result_err_bare!("Failed at cycle {}", value)
+}
+
+fn io_error(value: u32) -> Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("Failed at cycle {}", value),
+    )
+    .into())
+}
+
+fn create(c: &mut Criterion) {
+    let mut group = c.benchmark_group("create");
+    group.bench_function("result_err", |b| b.iter(|| disclosed(black_box(5))));
+    group.bench_function("result_err_bare", |b| b.iter(|| bare(black_box(5))));
+    group.bench_function("io_error", |b| b.iter(|| io_error(black_box(5))));
+    group.finish();
+}
+
+fn format(c: &mut Criterion) {
+    let disclosed = disclosed(5).unwrap_err();
+    let bare = bare(5).unwrap_err();
+    let io_error: Error = io_error(5).unwrap_err();
+
+    let mut group = c.benchmark_group("format");
+    group.bench_function("disclosed", |b| {
+        b.iter(|| black_box(&disclosed).to_string())
+    });
+    group.bench_function("bare", |b| b.iter(|| black_box(&bare).to_string()));
+    group.bench_function("io_error", |b| b.iter(|| black_box(&io_error).to_string()));
+    group.bench_function("github", |b| {
+        b.iter(|| ErrorFormat::Github.error("bench", black_box(&disclosed)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, create, format);
+criterion_main!(benches);
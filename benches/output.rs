@@ -0,0 +1,48 @@
+// Benchmarks of the throughput of the output streams. Run with `cargo bench --bench output`.
+//
+// The lines are written to a temporary file, so that each unbuffered write is a system call as it
+// is when the standard output stream is redirected.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use tyg_template::Streams;
+
+const LINES: u64 = 10_000;
+
+fn write_lines(streams: &mut Streams) {
+    for n in 0..LINES {
+        writeln!(streams.out, "Cycle {}", n).unwrap();
+    }
+    streams.out.flush().unwrap();
+}
+
+fn bulk_output(c: &mut Criterion) {
+    let path = env::temp_dir().join(format!("{}-bench-output.txt", env!("CARGO_PKG_NAME")));
+    let create = || File::create(&path).unwrap();
+
+    let mut group = c.benchmark_group("bulk_output");
+    group.throughput(Throughput::Elements(LINES));
+    group.bench_function("unbuffered", |b| {
+        b.iter_batched(
+            || Streams::new(create(), Vec::new()),
+            |mut streams| write_lines(&mut streams),
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("buffered", |b| {
+        b.iter_batched(
+            || Streams::new(BufWriter::new(create()), Vec::new()),
+            |mut streams| write_lines(&mut streams),
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+
+    let _ = fs::remove_file(&path);
+}
+
+criterion_group!(benches, bulk_output);
+criterion_main!(benches);
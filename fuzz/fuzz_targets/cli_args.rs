@@ -0,0 +1,17 @@
+// Fuzz the command line parsing and dispatch. The input is split into arguments at each NUL byte
+// and processed in-process with the output captured, so any panic is reported as a failure.
+
+#![no_main]
+
+use std::ffi::OsString;
+
+use libfuzzer_sys::fuzz_target;
+use tyg_template::run_with_args;
+use tyg_template::testkit::{args_from_bytes, capture};
+
+fuzz_target!(|data: &[u8]| {
+    let mut args = vec![OsString::from("tyg_template")];
+    args.extend(args_from_bytes(data));
+
+    let _ = capture(|streams| run_with_args(args, streams));
+});
@@ -0,0 +1,17 @@
+// Fuzz the parsing of disclosed error locations and wildcard patterns used by the testkit. The
+// first line of the input is used as the pattern and the remainder as the text to match.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tyg_template::testkit::{matches_pattern, strip_location, strip_locations};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        assert!(strip_location(text).len() <= text.len());
+        assert!(strip_locations(text).len() <= text.len());
+
+        let (pattern, subject) = text.split_once('\n').unwrap_or((text, ""));
+        let _ = matches_pattern(subject, pattern);
+    }
+});
@@ -0,0 +1,51 @@
+//! The accessible mode, in which the output suits screen readers.
+//!
+//! Colors, animations and other control sequences are read out by screen readers, or garble what
+//! they read, as are symbols that stand in for words. The mode is turned on by the `--accessible`
+//! flag, or by setting the `TYG_ACCESSIBLE` environment variable to anything other than `0`, so
+//! that it can be set once for every run. In the accessible mode:
+//!
+//! - The help and the errors of the command line are shown without colors.
+//! - Errors are labelled in words, e.g. `tyg_template: error: No path specified`, and so is the
+//!   normal completion of the application, with `done:`.
+//! - The color-eyre handler, when the `eyre` feature is used, reports errors without colors.
+//!
+//! Output added to the application, such as progress reports, should check
+//! [`terminal::plain`](crate::terminal::plain), which is true in the accessible mode, and write plain
+//! status lines, one after another, in place of animations.
+
+use std::env;
+use std::ffi::OsString;
+
+use crate::globals;
+
+/// The environment variable that turns the accessible mode on when it is set, unless it is `0`.
+pub const ENV_VAR: &str = "TYG_ACCESSIBLE";
+
+/// Whether the accessible mode is turned on by the `--accessible` flag among the arguments, or by
+/// the environment variable. The first argument is taken to be the name of the binary.
+///
+/// # Examples
+/// ```
+/// use std::ffi::OsString;
+/// use tyg_template::accessibility;
+///
+/// let args: Vec<OsString> = ["bin", "--accessible", "fail"].iter().map(OsString::from).collect();
+///
+/// assert!(accessibility::requested(&args));
+/// assert!(!accessibility::requested(&args[..1]));
+/// ```
+pub fn requested(args: &[OsString]) -> bool {
+    let flag = args
+        .iter()
+        .skip(1)
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg == "--accessible");
+    flag || env::var_os(ENV_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Whether the accessible mode was turned on for the command line of the current run, as given
+/// by [`globals::current`].
+pub fn enabled() -> bool {
+    globals::current().accessible
+}
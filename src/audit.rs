@@ -0,0 +1,353 @@
+//! The audit log, a record of each run of the application on a shared machine.
+//!
+//! The log is opt-in: it is only written when the `TYG_AUDIT_LOG` environment variable is set to
+//! anything other than `0`. Each run adds a line to `audit.log` in the state directory of the
+//! application, which on Linux is `~/.local/state/<name>`, or `$XDG_STATE_HOME/<name>` when
+//! `XDG_STATE_HOME` is set. The line is a JSON object holding the time the run started, the user,
+//! the subcommand, the command line, the time taken and the exit code. The values of flags that
+//! hold secrets, such as `--token`, are replaced by `***` before the command line is recorded.
+//!
+//! A failure to write the log is reported as a warning and never changes the result of the
+//! command. The `history` subcommand shows the log.
+//!
+//! ```text
+//! {"args":["tyg_template","fail"],"duration_ms":3,"status":1,"subcommand":"fail","time":1704110400,"user":"alice"}
+//! ```
+
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::platform::{self, long_path};
+use crate::units::HumanDuration;
+use crate::{result_err_bare, ErrorFormat, Result};
+
+/// The environment variable that turns the audit log on when it is set, unless it is `0`.
+pub const ENV_VAR: &str = "TYG_AUDIT_LOG";
+
+// The words that mark a flag as holding a secret, whose value is not recorded
+const SECRET_WORDS: &[&str] = &["token", "password", "secret", "key"];
+
+/// A run of the application recorded in the audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The time the run started, in seconds since the Unix epoch.
+    pub time: u64,
+    /// The user who ran the application, or `unknown`.
+    pub user: String,
+    /// The subcommand run, or an empty string if there was none.
+    pub subcommand: String,
+    /// The command line, with the values of secret flags redacted.
+    pub args: Vec<String>,
+    /// The time taken, in milliseconds.
+    pub duration_ms: u64,
+    /// The exit code.
+    pub status: i32,
+}
+
+impl Entry {
+    /// The entry as a line of the log.
+    pub fn to_json(&self) -> String {
+        json!({
+            "time": self.time,
+            "user": self.user,
+            "subcommand": self.subcommand,
+            "args": self.args,
+            "duration_ms": self.duration_ms,
+            "status": self.status,
+        })
+        .to_string()
+    }
+
+    /// The entry in a line of the log, or `None` if the line is not an entry.
+    pub fn from_json(line: &str) -> Option<Entry> {
+        let value: Value = serde_json::from_str(line).ok()?;
+        Some(Entry {
+            time: value["time"].as_u64()?,
+            user: value["user"].as_str()?.to_string(),
+            subcommand: value["subcommand"].as_str()?.to_string(),
+            args: value["args"]
+                .as_array()?
+                .iter()
+                .map(|arg| arg.as_str().map(str::to_string))
+                .collect::<Option<_>>()?,
+            duration_ms: value["duration_ms"].as_u64()?,
+            status: i32::try_from(value["status"].as_i64()?).ok()?,
+        })
+    }
+}
+
+impl fmt::Display for Entry {
+    /// The entry as a line of the history, e.g.
+    /// `2024-01-01T12:00:00Z  alice  exit 1  3ms  tyg_template fail`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}  {}  exit {}  {}  {}",
+            utc(self.time),
+            self.user,
+            self.status,
+            HumanDuration::new(Duration::from_millis(self.duration_ms)),
+            self.args.join(" ")
+        )
+    }
+}
+
+/// Which entries of the log the `history` subcommand shows.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Only the runs of this subcommand.
+    pub subcommand: Option<String>,
+    /// Only the runs that failed.
+    pub failed: bool,
+    /// Only this number of the latest runs.
+    pub limit: Option<usize>,
+}
+
+impl Filter {
+    /// The entries that pass the filter, in the order given.
+    ///
+    /// # Examples
+    /// ```
+    /// use tyg_template::audit::{Entry, Filter};
+    ///
+    /// let entry = |subcommand: &str, status| Entry {
+    ///     time: 1_704_110_400,
+    ///     user: "alice".to_string(),
+    ///     subcommand: subcommand.to_string(),
+    ///     args: vec!["tyg_template".to_string(), subcommand.to_string()],
+    ///     duration_ms: 3,
+    ///     status,
+    /// };
+    /// let entries = [entry("fail", 1), entry("init", 0), entry("fail", 0), entry("fail", 1)];
+    ///
+    /// let filter = Filter { subcommand: Some("fail".to_string()), failed: true, limit: Some(1) };
+    /// let shown = filter.apply(&entries);
+    ///
+    /// assert_eq!(shown.len(), 1);
+    /// assert_eq!(shown[0].to_string(), "2024-01-01T12:00:00Z  alice  exit 1  3ms  tyg_template fail");
+    /// ```
+    pub fn apply<'a>(&self, entries: &'a [Entry]) -> Vec<&'a Entry> {
+        let mut shown: Vec<&Entry> = entries
+            .iter()
+            .filter(|entry| {
+                self.subcommand
+                    .as_ref()
+                    .is_none_or(|subcommand| entry.subcommand == *subcommand)
+            })
+            .filter(|entry| !self.failed || entry.status != 0)
+            .collect();
+        if let Some(limit) = self.limit {
+            shown.drain(..shown.len().saturating_sub(limit));
+        }
+        shown
+    }
+}
+
+/// Whether the audit log is turned on by the environment variable.
+pub fn enabled() -> bool {
+    env::var_os(ENV_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// The path of the audit log, or `None` if the state directory is unknown.
+pub fn path() -> Option<PathBuf> {
+    Some(platform::state_directory()?.join("audit.log"))
+}
+
+/// The command line with the values of the flags that hold secrets replaced by `***`, whether
+/// they are given after the flag or joined to it by `=`.
+///
+/// # Examples
+/// ```
+/// use std::ffi::OsString;
+/// use tyg_template::audit::redact;
+///
+/// let args: Vec<OsString> = ["bin", "config", "auth", "--token", "s3cr3t", "--api-key=abc"]
+///     .iter()
+///     .map(OsString::from)
+///     .collect();
+///
+/// assert_eq!(redact(&args), ["bin", "config", "auth", "--token", "***", "--api-key=***"]);
+/// ```
+pub fn redact(args: &[OsString]) -> Vec<String> {
+    let secret = |flag: &str| {
+        flag.starts_with("--")
+            && SECRET_WORDS
+                .iter()
+                .any(|word| flag.to_lowercase().contains(word))
+    };
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut hide_next = false;
+    for arg in args.iter().map(|arg| arg.to_string_lossy()) {
+        if hide_next {
+            redacted.push("***".to_string());
+            hide_next = false;
+        } else if let Some((flag, _)) = arg.split_once('=').filter(|(flag, _)| secret(flag)) {
+            redacted.push(format!("{}=***", flag));
+        } else {
+            hide_next = secret(&arg);
+            redacted.push(arg.into_owned());
+        }
+    }
+    redacted
+}
+
+/// Add a run to the audit log, if it is turned on. A failure is written to the error stream as a
+/// warning, so that it cannot mask the result of the command. The application records each run
+/// with its start read from the [clock](crate::clock).
+///
+/// # Examples
+/// ```
+/// use std::sync::Arc;
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use tyg_template::{audit, clock, run_with_args};
+/// use tyg_template::testkit::{capture, FakeClock, TempProject};
+///
+/// let _project = TempProject::builder().create().unwrap();
+/// std::env::set_var(audit::ENV_VAR, "1");
+/// let start = UNIX_EPOCH + Duration::from_secs(1_704_110_400);
+/// let _entered = clock::enter(Arc::new(FakeClock::new(start)));
+///
+/// capture(|streams| run_with_args(["tyg_template", "fail", "--bare"], streams));
+///
+/// let log = std::fs::read_to_string(audit::path().unwrap()).unwrap();
+/// assert!(log.contains(r#""time":1704110400"#));
+/// ```
+pub fn record(
+    args: &[OsString],
+    subcommand: &str,
+    started: SystemTime,
+    duration: Duration,
+    status: i32,
+    err: &mut dyn Write,
+) {
+    if !enabled() {
+        return;
+    }
+    let entry = Entry {
+        time: started
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs()),
+        user: env::var("USER")
+            .or_else(|_| env::var("USERNAME"))
+            .unwrap_or_else(|_| "unknown".to_string()),
+        subcommand: subcommand.to_string(),
+        args: redact(args),
+        duration_ms: u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+        status,
+    };
+    if let Err(e) = append(&entry) {
+        let message = format!("The run was not added to the audit log: {}", e);
+        let _ = writeln!(
+            err,
+            "{}",
+            ErrorFormat::selected().warning(env!("CARGO_PKG_NAME"), &message)
+        );
+    }
+}
+
+fn append(entry: &Entry) -> Result<()> {
+    let Some(path) = path() else {
+        return result_err_bare!("The state directory is unknown");
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(long_path(parent))
+            .or_else(|e| result_err_bare!("{}: {}", parent.display(), e))?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(long_path(&path))
+        .and_then(|mut file| writeln!(file, "{}", entry.to_json()))
+        .or_else(|e| result_err_bare!("{}: {}", path.display(), e))
+}
+
+/// Read the entries of the audit log, skipping any line that is not an entry. A log that does not
+/// exist yet has no entries.
+pub fn read() -> Result<Vec<Entry>> {
+    let Some(path) = path() else {
+        return result_err_bare!("The state directory is unknown");
+    };
+    match fs::read_to_string(long_path(&path)) {
+        Ok(text) => Ok(text.lines().filter_map(Entry::from_json).collect()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => result_err_bare!("{}: {}", path.display(), e),
+    }
+}
+
+/// The distinct subcommands recorded in the audit log, in the order they were first run, or none
+/// if the log cannot be read. They are the run time completions of `history --subcommand`.
+pub fn subcommands() -> Vec<String> {
+    let mut subcommands: Vec<String> = Vec::new();
+    for entry in read().unwrap_or_default() {
+        if !entry.subcommand.is_empty() && !subcommands.contains(&entry.subcommand) {
+            subcommands.push(entry.subcommand);
+        }
+    }
+    subcommands
+}
+
+/// The `history` subcommand, which shows the entries of the audit log that pass the filter.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use tyg_template::audit::{self, Filter};
+/// use tyg_template::testkit::TempProject;
+///
+/// let _project = TempProject::builder().create().unwrap();
+/// let path = audit::path().unwrap();
+/// fs::create_dir_all(path.parent().unwrap()).unwrap();
+/// fs::write(&path, concat!(
+///     r#"{"time":1704110400,"user":"alice","subcommand":"fail","args":["tyg_template","fail"],"#,
+///     r#""duration_ms":3,"status":1}"#, "\n",
+/// )).unwrap();
+///
+/// let mut out = Vec::new();
+/// audit::history(&Filter::default(), &mut out).unwrap();
+///
+/// assert_eq!(String::from_utf8(out).unwrap(),
+///            "2024-01-01T12:00:00Z  alice  exit 1  3ms  tyg_template fail\n");
+/// ```
+pub fn history(filter: &Filter, out: &mut dyn Write) -> Result<()> {
+    let entries = read()?;
+    for entry in filter.apply(&entries) {
+        writeln!(out, "{}", entry)?;
+    }
+    Ok(())
+}
+
+// The time in seconds since the Unix epoch as an RFC 3339 date and time in UTC
+fn utc(time: u64) -> String {
+    let (days, seconds) = (time / 86_400, time % 86_400);
+    // The civil date of a day count, from Howard Hinnant's days_from_civil algorithm in reverse
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
@@ -0,0 +1,251 @@
+//! A cache of the results of expensive lookups, such as the metadata of an API or a parsed index.
+//!
+//! Each result is stored under a key, as a JSON file in the cache directory of the application,
+//! which on Linux is `~/.cache/<name>`, or `$XDG_CACHE_HOME/<name>` when `XDG_CACHE_HOME` is set.
+//! [`get_or_compute`] returns the stored result while it is younger than the time to live given
+//! for it, and otherwise computes the result again and stores it. The cache is only an
+//! optimisation, so an entry that cannot be read is treated as missing, with a warning, and a
+//! result that cannot be stored is still returned. The `cache clear` subcommand removes every
+//! entry.
+//!
+//! ```text
+//! {"created":1704110400,"key":"releases","value":["1.0.0","1.1.0"]}
+//! ```
+
+use std::env;
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::clock;
+use crate::fsx::Journal;
+use crate::platform::long_path;
+use crate::{result_err_bare, ErrorFormat, Result};
+
+/// The cache directory of the application, or `None` if it is unknown.
+pub fn directory() -> Option<PathBuf> {
+    let cache = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| {
+            if cfg!(windows) {
+                env::var_os("LOCALAPPDATA").map(|local| PathBuf::from(local).join("cache"))
+            } else {
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache"))
+            }
+        })?;
+    Some(cache.join(env!("CARGO_PKG_NAME")))
+}
+
+/// The path of the file holding the entry for the key, or `None` if the cache directory is
+/// unknown. The file is named after a hash of the key, so that any key can be used.
+pub fn path(key: &str) -> Option<PathBuf> {
+    Some(directory()?.join(format!("{:016x}.json", fnv1a(key))))
+}
+
+/// The result stored under the key if it is younger than the time to live, or else the result of
+/// the function, which is then stored under the key. An error of the function is returned, and
+/// nothing is stored. Entries that cannot be read, and results that cannot be stored, are
+/// reported as warnings on the error stream.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use std::time::Duration;
+/// use tyg_template::cache;
+/// use tyg_template::testkit::TempProject;
+///
+/// let _project = TempProject::builder().create().unwrap();
+/// let day = Duration::from_secs(24 * 60 * 60);
+///
+/// let releases: Vec<String> =
+///     cache::get_or_compute("releases", day, || Ok(vec!["1.0.0".to_string()])).unwrap();
+/// assert_eq!(releases, ["1.0.0"]);
+///
+/// // The stored result is returned without calling the function
+/// let releases: Vec<String> =
+///     cache::get_or_compute("releases", day, || unreachable!()).unwrap();
+/// assert_eq!(releases, ["1.0.0"]);
+///
+/// // A result older than the time to live is computed again
+/// let releases: Vec<String> =
+///     cache::get_or_compute("releases", Duration::ZERO, || Ok(vec!["1.1.0".to_string()])).unwrap();
+/// assert_eq!(releases, ["1.1.0"]);
+///
+/// // An entry that cannot be read is a miss
+/// fs::write(cache::path("releases").unwrap(), "{not json").unwrap();
+/// let releases: Vec<String> =
+///     cache::get_or_compute("releases", day, || Ok(vec!["1.2.0".to_string()])).unwrap();
+/// assert_eq!(releases, ["1.2.0"]);
+/// ```
+pub fn get_or_compute<T, F>(key: &str, ttl: Duration, f: F) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Result<T>,
+{
+    let Some(path) = path(key) else {
+        return f();
+    };
+    match read(key, &path, ttl) {
+        Ok(Some(value)) => return Ok(value),
+        Ok(None) => {}
+        Err(e) => warn(&format!("The cached entry was ignored: {}", e)),
+    }
+    let value = f()?;
+    if let Err(e) = write(key, &path, &value) {
+        warn(&format!("The entry was not cached: {}", e));
+    }
+    Ok(value)
+}
+
+/// The result stored under the key if it is younger than the time to live, without computing it
+/// when it is not. An entry that cannot be read is reported as a warning and treated as missing.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use tyg_template::cache;
+/// use tyg_template::testkit::TempProject;
+///
+/// let _project = TempProject::builder().create().unwrap();
+/// assert_eq!(cache::get::<u32>("answer", Duration::MAX), None);
+///
+/// let _: u32 = cache::get_or_compute("answer", Duration::MAX, || Ok(42)).unwrap();
+/// assert_eq!(cache::get::<u32>("answer", Duration::MAX), Some(42));
+/// ```
+pub fn get<T: DeserializeOwned>(key: &str, ttl: Duration) -> Option<T> {
+    let path = path(key)?;
+    match read(key, &path, ttl) {
+        Ok(value) => value,
+        Err(e) => {
+            warn(&format!("The cached entry was ignored: {}", e));
+            None
+        }
+    }
+}
+
+/// Remove every entry from the cache, returning the number removed.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use tyg_template::cache;
+/// use tyg_template::testkit::TempProject;
+///
+/// let _project = TempProject::builder().create().unwrap();
+/// let _: u32 = cache::get_or_compute("answer", Duration::MAX, || Ok(42)).unwrap();
+///
+/// assert_eq!(cache::clear().unwrap(), 1);
+/// assert_eq!(cache::clear().unwrap(), 0);
+/// ```
+pub fn clear() -> Result<usize> {
+    let Some(directory) = directory() else {
+        return result_err_bare!("The cache directory is unknown");
+    };
+    let entries = match fs::read_dir(long_path(&directory)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return result_err_bare!("{}: {}", directory.display(), e),
+    };
+    let journal = Journal::new()?;
+    let mut removed = 0;
+    for entry in entries {
+        let path = entry
+            .or_else(|e| result_err_bare!("{}: {}", directory.display(), e))?
+            .path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            journal.remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    journal.commit()?;
+    Ok(removed)
+}
+
+/// The `cache clear` subcommand, which removes every entry and says how many there were.
+pub fn clear_command(out: &mut dyn Write) -> Result<()> {
+    let removed = clear()?;
+    let directory = directory().unwrap_or_default();
+    match removed {
+        1 => writeln!(out, "Removed 1 entry from {}", directory.display())?,
+        n => writeln!(out, "Removed {} entries from {}", n, directory.display())?,
+    }
+    Ok(())
+}
+
+// The value stored under the key, or None if there is none or it has expired
+fn read<T: DeserializeOwned>(key: &str, path: &Path, ttl: Duration) -> Result<Option<T>> {
+    let text = match fs::read_to_string(long_path(path)) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return result_err_bare!("{}: {}", path.display(), e),
+    };
+    let corrupt = |reason: &str| result_err_bare!("{}: {}", path.display(), reason);
+    let Ok(mut entry) = serde_json::from_str::<Value>(&text) else {
+        return corrupt("Not a cache entry");
+    };
+    let (Some(created), Some(stored_key)) = (entry["created"].as_u64(), entry["key"].as_str())
+    else {
+        return corrupt("Not a cache entry");
+    };
+    // Another key with the same hash is a miss, which the new entry replaces
+    if stored_key != key {
+        return Ok(None);
+    }
+    let age = now().saturating_sub(created);
+    if Duration::from_secs(age) >= ttl {
+        return Ok(None);
+    }
+    match serde_json::from_value(entry["value"].take()) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) => corrupt(&format!("The value does not match its type, {}", e)),
+    }
+}
+
+// Store the value under the key, writing a temporary file that replaces the entry in one step
+fn write<T: Serialize>(key: &str, path: &Path, value: &T) -> Result<()> {
+    let value = serde_json::to_value(value).or_else(|e| result_err_bare!("{}: {}", key, e))?;
+    let entry = json!({ "created": now(), "key": key, "value": value });
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(long_path(parent))
+            .or_else(|e| result_err_bare!("{}: {}", parent.display(), e))?;
+    }
+    let temporary = path.with_extension(format!("{}.tmp", process::id()));
+    fs::write(long_path(&temporary), entry.to_string())
+        .and_then(|()| fs::rename(long_path(&temporary), long_path(path)))
+        .or_else(|e| {
+            let _ = fs::remove_file(long_path(&temporary));
+            result_err_bare!("{}: {}", path.display(), e)
+        })
+}
+
+fn warn(message: &str) {
+    let _ = writeln!(
+        io::stderr(),
+        "{}",
+        ErrorFormat::selected().warning(env!("CARGO_PKG_NAME"), message)
+    );
+}
+
+fn now() -> u64 {
+    clock::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+// The 64 bit FNV-1a hash, which unlike the hasher of the standard library is the same in every
+// release
+fn fnv1a(key: &str) -> u64 {
+    key.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
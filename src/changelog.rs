@@ -0,0 +1,153 @@
+//! The `changelog` subcommand, which shows what has changed in the releases of the application.
+//!
+//! The `CHANGELOG.md` file at the root of the project is compiled into the application. Each
+//! release is a level two heading giving its version, e.g. `## [1.4.0]`, with the changes below
+//! it, and the changes not yet released are under `## [Unreleased]`. The subcommand renders the
+//! releases newer than a version for the terminal with the [`markdown`](crate::markdown) module.
+//!
+//! Without `--since`, the releases shown are those newer than the version that last showed the
+//! changelog, which is recorded in `last-version` in the state directory of the application, so
+//! that after an update the changelog answers the question of what changed. The whole changelog is
+//! shown the first time.
+
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+
+use crate::platform::{self, long_path};
+use crate::update::newer;
+use crate::{markdown, result_err_bare, terminal, Result};
+
+/// The changelog of the application, as it was when the application was compiled.
+pub const TEXT: &str = include_str!("../CHANGELOG.md");
+
+// The heading of the changes not yet released
+const UNRELEASED: &str = "Unreleased";
+
+/// A release in a changelog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Release<'a> {
+    /// The version, or `Unreleased`.
+    pub version: &'a str,
+    /// The changes, as Markdown, starting with the heading of the release.
+    pub text: &'a str,
+}
+
+/// The releases in the changelog, in the order they appear. Anything before the first release is
+/// left out.
+///
+/// # Examples
+/// ```
+/// use tyg_template::changelog::releases;
+///
+/// let text = "# Changelog\n\n## [Unreleased]\n\n- Next\n\n## [1.4.0] - 2024-01-01\n\n- Added\n";
+/// let releases = releases(text);
+///
+/// assert_eq!(releases.len(), 2);
+/// assert_eq!(releases[0].version, "Unreleased");
+/// assert_eq!(releases[1].version, "1.4.0");
+/// assert_eq!(releases[1].text, "## [1.4.0] - 2024-01-01\n\n- Added\n");
+/// ```
+pub fn releases(text: &str) -> Vec<Release<'_>> {
+    let mut starts: Vec<(usize, &str)> = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if let Some(heading) = line.strip_prefix("## ") {
+            let version = heading.trim_start_matches('[');
+            let end = version.find([']', ' ', '\n']).unwrap_or(version.len());
+            starts.push((offset, version[..end].trim_start_matches('v')));
+        }
+        offset += line.len();
+    }
+    let ends = starts.iter().skip(1).map(|&(start, _)| start);
+    starts
+        .iter()
+        .zip(ends.chain([text.len()]))
+        .map(|(&(start, version), end)| Release {
+            version,
+            text: &text[start..end],
+        })
+        .collect()
+}
+
+/// The releases in the changelog newer than the version, with the unreleased changes, or all of
+/// them if no version is given.
+///
+/// # Examples
+/// ```
+/// use tyg_template::changelog::since;
+///
+/// let text = "## [Unreleased]\n\n- Next\n\n## [1.4.0]\n\n- New\n\n## [1.3.0]\n\n- Old\n";
+///
+/// assert_eq!(since(text, Some("1.3.0")), "## [Unreleased]\n\n- Next\n\n## [1.4.0]\n\n- New\n\n");
+/// assert_eq!(since(text, Some("1.4.0")), "## [Unreleased]\n\n- Next\n\n");
+/// assert_eq!(since(text, None), text);
+/// ```
+pub fn since(text: &str, version: Option<&str>) -> String {
+    releases(text)
+        .into_iter()
+        .filter(|release| match version {
+            Some(version) => {
+                release.version.eq_ignore_ascii_case(UNRELEASED) || newer(release.version, version)
+            }
+            None => true,
+        })
+        .map(|release| release.text)
+        .collect()
+}
+
+/// The path of the file recording the version that last showed the changelog, or `None` if the
+/// state directory is unknown.
+pub fn last_version_path() -> Option<PathBuf> {
+    Some(platform::state_directory()?.join("last-version"))
+}
+
+/// The version that last showed the changelog, if one has.
+pub fn last_version() -> Option<String> {
+    let text = fs::read_to_string(long_path(&last_version_path()?)).ok()?;
+    Some(text.trim().to_string()).filter(|version| !version.is_empty())
+}
+
+/// The `changelog` subcommand, which shows the releases newer than the version, or than the last
+/// version recorded, and records the running version.
+///
+/// # Examples
+/// ```
+/// use tyg_template::changelog;
+/// use tyg_template::testkit::TempProject;
+///
+/// let _project = TempProject::builder().create().unwrap();
+///
+/// let mut out = Vec::new();
+/// changelog::changelog(Some("0.0.1"), &mut out).unwrap();
+/// assert!(String::from_utf8(out).unwrap().starts_with("Unreleased\n"));
+/// assert_eq!(changelog::last_version().unwrap(), env!("CARGO_PKG_VERSION"));
+/// ```
+pub fn changelog(version: Option<&str>, out: &mut dyn Write) -> Result<()> {
+    let version = version.map(str::to_string).or_else(last_version);
+    let text = since(TEXT, version.as_deref());
+    if text.is_empty() {
+        writeln!(
+            out,
+            "There are no changes since {}",
+            version.unwrap_or_default()
+        )?;
+    } else {
+        let styled = terminal::styled(io::stdout().is_terminal());
+        write!(out, "{}", markdown::render(text.trim_end(), styled))?;
+    }
+    record(env!("CARGO_PKG_VERSION"))
+}
+
+// Record the version as the last to show the changelog
+fn record(version: &str) -> Result<()> {
+    let Some(path) = last_version_path() else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(long_path(parent))
+            .or_else(|e| result_err_bare!("{}: {}", parent.display(), e))?;
+    }
+    fs::write(long_path(&path), format!("{}\n", version))
+        .or_else(|e| result_err_bare!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,87 @@
+//! The clock that the modules read the time of day from, such as the time of a run in the audit
+//! log or the age of a cache entry, which decides whether the cached answer of the check for a
+//! newer release is still fresh.
+//!
+//! The time is read with [`now`], from the [`SystemClock`] unless another [`Clock`] has been
+//! [entered](enter) on the calling thread, so that tests can drive the time-dependent behavior
+//! with the `FakeClock` of the `testkit` module rather than waiting for it. Work that a run hands to
+//! another thread reads the system clock. The durations measured with `Instant`, such as that of a
+//! run, are not affected.
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::time::{Duration, UNIX_EPOCH};
+//! use tyg_template::clock::{self, Clock};
+//!
+//! struct Frozen;
+//!
+//! impl Clock for Frozen {
+//!     fn now(&self) -> std::time::SystemTime {
+//!         UNIX_EPOCH + Duration::from_secs(1_704_110_400)
+//!     }
+//! }
+//!
+//! {
+//!     let _entered = clock::enter(Arc::new(Frozen));
+//!     assert_eq!(clock::now(), UNIX_EPOCH + Duration::from_secs(1_704_110_400));
+//! }
+//! assert!(clock::now() > UNIX_EPOCH + Duration::from_secs(1_704_110_400));
+//! ```
+
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+thread_local! {
+    // The clock entered on this thread, if any
+    static THREAD: RefCell<Option<Arc<dyn Clock>>> = const { RefCell::new(None) };
+}
+
+/// A source of the time of day.
+pub trait Clock: Send + Sync {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// The clock of the operating system, which is read unless another clock has been entered.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// The current time, from the clock entered on the calling thread or else the system clock.
+pub fn now() -> SystemTime {
+    THREAD
+        .with(|entered| entered.borrow().clone())
+        .map_or_else(|| SystemClock.now(), |clock| clock.now())
+}
+
+/// Enter the clock on the calling thread until the returned guard is dropped, which restores the
+/// clock entered before it.
+pub fn enter(clock: Arc<dyn Clock>) -> Entered {
+    let previous = THREAD.with(|entered| entered.borrow_mut().replace(clock));
+    Entered {
+        previous,
+        _thread: PhantomData,
+    }
+}
+
+/// The clock entered on a thread by [`enter`], which is left when it is dropped.
+#[must_use = "the clock is left as soon as the guard is dropped"]
+pub struct Entered {
+    previous: Option<Arc<dyn Clock>>,
+    // The clock belongs to the thread that entered it
+    _thread: PhantomData<*const ()>,
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        THREAD.with(|entered| *entered.borrow_mut() = previous);
+    }
+}
@@ -0,0 +1,420 @@
+//! Generation and installation of shell completion scripts.
+//!
+//! Scripts are generated by clap_complete for bash, elvish, fish, PowerShell and zsh, by
+//! clap_complete_nushell for nushell and by clap_complete_fig for fig. Installing a script writes
+//! it to the per-user location from which the shell loads completions, which is below
+//! `XDG_DATA_HOME` or `XDG_CONFIG_HOME` when they are set:
+//!
+//! | Shell   | Location                                            | Loaded          |
+//! |---------|-----------------------------------------------------|-----------------|
+//! | bash    | `~/.local/share/bash-completion/completions/<name>` | Automatically   |
+//! | elvish  | `~/.config/elvish/lib/<name>.elv`                   | By `use <name>` |
+//! | fig     | `~/.fig/autocomplete/build/<name>.js`               | Automatically   |
+//! | fish    | `~/.config/fish/completions/<name>.fish`            | Automatically   |
+//! | nushell | `~/.config/nushell/completions/<name>.nu`           | By `source`     |
+//! | zsh     | `~/.local/share/zsh/site-functions/_<name>`         | When in `fpath` |
+//!
+//! PowerShell has no such location, so its script is added to the profile by hand.
+//!
+//! The scripts complete the names of the subcommands and arguments, and the values of arguments
+//! that have a fixed set of them. Values that are only known at run time, such as the languages
+//! of the installed locale bundles, are listed by the application itself through the hidden
+//! `__complete` subcommand, which the bash, fish and zsh scripts call as the user types:
+//!
+//! ```text
+//! $ tyg_template __complete -- tyg_template --lang ''
+//! en
+//! fr
+//! ```
+//!
+//! The words after `--` are those of the command line being completed, the last being the word
+//! under the cursor, which may be empty. The candidates for that word are printed one per line,
+//! and nothing is printed when the word has no run time candidates, in which case the scripts fall
+//! back to their own completions.
+//!
+//! An argument is given run time candidates by registering a provider, a closure listing them,
+//! where it is defined in `cli`, e.g. the subcommands read from the audit log for
+//! `history --subcommand`:
+//!
+//! ```text
+//! .arg(arg!(--subcommand <NAME> "Show only the runs of this subcommand")
+//!      .complete_with(audit::subcommands))
+//! ```
+//!
+//! Providers are looked up by the identifier of the argument, so arguments of different
+//! subcommands that share an identifier share a provider. A provider is only called when its
+//! argument is being completed, and should return no candidates rather than fail.
+
+use std::collections::HashMap;
+use std::env;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use clap::{Arg, Command};
+use clap_complete::Shell;
+use clap_complete_fig::Fig;
+use clap_complete_nushell::Nushell;
+
+use crate::cli;
+use crate::fsx::Journal;
+use crate::{result_err_bare, Result};
+
+// The name under which the completions are registered
+const BIN: &str = env!("CARGO_PKG_NAME");
+
+// A closure listing the run time candidates for the value of an argument
+type Provider = Arc<dyn Fn() -> Vec<String> + Send + Sync>;
+
+// The providers registered for the arguments, by the identifier of the argument
+static PROVIDERS: OnceLock<Mutex<HashMap<String, Provider>>> = OnceLock::new();
+
+/// The names of the shells for which completions can be generated.
+pub const SHELLS: [&str; 7] = [
+    "bash",
+    "elvish",
+    "fig",
+    "fish",
+    "nushell",
+    "powershell",
+    "zsh",
+];
+
+/// Write the completion script for the given shell.
+///
+/// An error is returned if the shell is not one of [`SHELLS`].
+///
+/// # Examples
+/// ```
+/// use tyg_template::completions::generate;
+///
+/// let mut out = Vec::new();
+/// generate("nushell", &mut out).unwrap();
+///
+/// assert!(String::from_utf8_lossy(&out).contains("export extern \"tyg_template fail\""));
+/// assert!(generate("tcsh", &mut out).is_err());
+/// ```
+pub fn generate(shell: &str, out: &mut dyn Write) -> Result<()> {
+    out.write_all(&script(shell)?)?;
+    Ok(())
+}
+
+/// Install the completion script for the given shell in the location from which the shell loads
+/// it, replacing any earlier version.
+///
+/// An error is returned if the shell is not one of [`SHELLS`], if the shell has no such location,
+/// as for PowerShell, or if the script cannot be written.
+///
+/// # Examples
+/// ```
+/// use tyg_template::completions::install;
+/// use tyg_template::testkit::TempProject;
+///
+/// let project = TempProject::builder().create().unwrap();
+///
+/// let mut out = Vec::new();
+/// install("fish", &mut out).unwrap();
+///
+/// assert!(project.home().join(".config/fish/completions/tyg_template.fish").is_file());
+/// assert!(install("powershell", &mut out).is_err());
+/// ```
+pub fn install(shell: &str, out: &mut dyn Write) -> Result<()> {
+    let script = script(shell)?;
+    let (directory, file, hint) = match shell {
+        "bash" => (
+            data_home()?.join("bash-completion/completions"),
+            BIN.to_string(),
+            None,
+        ),
+        "elvish" => (
+            config_home()?.join("elvish/lib"),
+            format!("{}.elv", BIN),
+            Some(format!("Add `use {}` to ~/.config/elvish/rc.elv to load them", BIN)),
+        ),
+        "fig" => (
+            home()?.join(".fig/autocomplete/build"),
+            format!("{}.js", BIN),
+            None,
+        ),
+        "fish" => (
+            config_home()?.join("fish/completions"),
+            format!("{}.fish", BIN),
+            None,
+        ),
+        "nushell" => {
+            let directory = config_home()?.join("nushell/completions");
+            let hint = format!(
+                "Add `source {}` to your nushell config.nu to load them",
+                directory.join(format!("{}.nu", BIN)).display()
+            );
+            (directory, format!("{}.nu", BIN), Some(hint))
+        }
+        "zsh" => {
+            let directory = data_home()?.join("zsh/site-functions");
+            let hint = format!(
+                "Add `fpath=({} $fpath)` to ~/.zshrc before compinit to load them",
+                directory.display()
+            );
+            (directory, format!("_{}", BIN), Some(hint))
+        }
+        _ => {
+            return result_err_bare!(
+                "{}: Completions cannot be installed for this shell, add the output of `{} completions {}` to its profile",
+                shell,
+                BIN,
+                shell
+            )
+        }
+    };
+
+    let journal = Journal::new()?;
+    journal.create_dir_all(&directory)?;
+    let path = directory.join(file);
+    journal.write(&path, script)?;
+    journal.commit()?;
+
+    writeln!(
+        out,
+        "Installed the {} completions in {}",
+        shell,
+        path.display()
+    )?;
+    if let Some(hint) = hint {
+        writeln!(out, "{}", hint)?;
+    }
+    Ok(())
+}
+
+/// Write the run time candidates for the last of the words of a command line, as the
+/// `__complete` subcommand does, one per line.
+///
+/// The first word is taken to be the name of the binary. Nothing is written if the last word is
+/// not the value of an argument with run time candidates.
+///
+/// # Examples
+/// ```
+/// use tyg_template::completions::complete;
+/// use tyg_template::testkit::TempProject;
+///
+/// let _project = TempProject::builder()
+///     .file("home/.local/share/tyg_template/locales/fr.txt", "fail = Montrer une erreur\n")
+///     .file("home/.local/state/tyg_template/audit.log", concat!(
+///         r#"{"time":1704110400,"user":"alice","subcommand":"fail","args":[],"#,
+///         r#""duration_ms":3,"status":1}"#, "\n",
+///     ))
+///     .create()
+///     .unwrap();
+///
+/// let mut out = Vec::new();
+/// complete(&["tyg_template", "fail", "--lang", ""].map(String::from), &mut out).unwrap();
+/// assert_eq!(String::from_utf8_lossy(&out), "en\nfr\n");
+///
+/// let mut out = Vec::new();
+/// complete(&["tyg_template", "--lang=f"].map(String::from), &mut out).unwrap();
+/// assert_eq!(String::from_utf8_lossy(&out), "--lang=fr\n");
+///
+/// let mut out = Vec::new();
+/// complete(&["tyg_template", "history", "--subcommand", "f"].map(String::from), &mut out).unwrap();
+/// assert_eq!(String::from_utf8_lossy(&out), "fail\n");
+///
+/// let mut out = Vec::new();
+/// complete(&["tyg_template", "fa"].map(String::from), &mut out).unwrap();
+/// assert!(out.is_empty());
+/// ```
+pub fn complete(words: &[String], out: &mut dyn Write) -> Result<()> {
+    let Some((current, previous)) = words.split_last() else {
+        return Ok(());
+    };
+    let mut root = cli();
+    root.build();
+
+    // Follow the subcommands to the one being completed, noting an argument awaiting its value
+    let mut command = &root;
+    let mut awaiting = None;
+    for word in previous.iter().skip(1) {
+        if awaiting.take().is_some() {
+            continue;
+        }
+        if word == "--" {
+            return Ok(());
+        }
+        match command.find_subcommand(word) {
+            Some(sub) => command = sub,
+            None => awaiting = value_arg(command, word),
+        }
+    }
+
+    let (arg, prefix, partial) = match awaiting {
+        Some(arg) => (arg, "", current.as_str()),
+        None => match current.split_once('=') {
+            Some((flag, partial)) if flag.starts_with("--") => match value_arg(command, flag) {
+                Some(arg) => (arg, &current[..=flag.len()], partial),
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        },
+    };
+    for candidate in candidates(arg) {
+        if candidate.starts_with(partial) {
+            writeln!(out, "{}{}", prefix, candidate)?;
+        }
+    }
+    Ok(())
+}
+
+// The argument of the command named by the flag, if it takes a value
+fn value_arg<'a>(command: &'a Command, flag: &str) -> Option<&'a Arg> {
+    let arg = match (flag.strip_prefix("--"), flag.strip_prefix('-')) {
+        (Some(long), _) => command.get_arguments().find(|arg| {
+            arg.get_long() == Some(long)
+                || arg
+                    .get_all_aliases()
+                    .is_some_and(|aliases| aliases.contains(&long))
+        }),
+        (None, Some(short)) if short.chars().count() == 1 => {
+            let short = short.chars().next();
+            command.get_arguments().find(|arg| arg.get_short() == short)
+        }
+        _ => None,
+    }?;
+    arg.get_action().takes_values().then_some(arg)
+}
+
+/// Register a provider of the run time candidates for the value of the argument with the given
+/// identifier, replacing any registered before.
+pub fn register(id: &str, provider: impl Fn() -> Vec<String> + Send + Sync + 'static) {
+    let mut providers = PROVIDERS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    providers.insert(id.to_string(), Arc::new(provider));
+}
+
+/// The run time candidates for the value of an argument, listed by the provider registered for
+/// it, or none if there is no provider.
+///
+/// # Examples
+/// ```
+/// use clap::arg;
+/// use tyg_template::completions::{candidates, CompleteWith};
+///
+/// let arg = arg!(--colour <NAME> "The colour of the output")
+///     .complete_with(|| vec!["red".to_string(), "green".to_string()]);
+///
+/// assert_eq!(candidates(&arg), ["red", "green"]);
+/// assert!(candidates(&arg!(--shade <NAME> "The shade of the output")).is_empty());
+/// ```
+pub fn candidates(arg: &Arg) -> Vec<String> {
+    let provider = PROVIDERS.get().and_then(|providers| {
+        let providers = providers.lock().unwrap_or_else(|e| e.into_inner());
+        providers.get(arg.get_id().as_str()).cloned()
+    });
+    // The lock is released before the provider is called, in case it defines arguments itself
+    provider.map(|provider| provider()).unwrap_or_default()
+}
+
+/// Registration of a provider of run time candidates as an argument is defined.
+pub trait CompleteWith {
+    /// Register the provider for the argument, see [`register`].
+    fn complete_with(self, provider: impl Fn() -> Vec<String> + Send + Sync + 'static) -> Self;
+}
+
+impl CompleteWith for Arg {
+    fn complete_with(self, provider: impl Fn() -> Vec<String> + Send + Sync + 'static) -> Arg {
+        register(self.get_id().as_str(), provider);
+        self
+    }
+}
+
+// The part of the script for the shell that offers the candidates listed by the __complete
+// subcommand, if the shell supports them
+fn dynamic_hook(shell: &str) -> Option<String> {
+    let hook = match shell {
+        "bash" => format!(
+            r#"
+_{bin}_dynamic() {{
+    local candidates
+    candidates=$("$1" __complete -- "${{COMP_WORDS[@]:0:$((COMP_CWORD + 1))}}" 2>/dev/null)
+    if [[ -n "$candidates" ]]; then
+        COMPREPLY=( $(compgen -W "$candidates" -- "${{COMP_WORDS[COMP_CWORD]}}") )
+    else
+        _{bin} "$@"
+    fi
+}}
+
+complete -F _{bin}_dynamic -o bashdefault -o default {bin}
+"#,
+            bin = BIN
+        ),
+        "fish" => format!(
+            "\ncomplete -c {bin} -a '({bin} __complete -- (commandline -opc) (commandline -ct | string collect --allow-empty) 2>/dev/null)'\n",
+            bin = BIN
+        ),
+        "zsh" => format!(
+            r#"
+_{bin}_dynamic() {{
+    local -a candidates
+    candidates=(${{(f)"$(${{words[1]}} __complete -- "${{(@)words[1,CURRENT]}}" 2>/dev/null)"}})
+    if (( ${{#candidates}} )); then
+        compadd -a candidates
+    else
+        _{bin} "$@"
+    fi
+}}
+
+compdef _{bin}_dynamic {bin}
+"#,
+            bin = BIN
+        ),
+        _ => return None,
+    };
+    Some(hook)
+}
+
+// Generate the completion script for the given shell
+fn script(shell: &str) -> Result<Vec<u8>> {
+    let mut command = cli();
+    let mut script = Vec::new();
+    match shell {
+        "fig" => clap_complete::generate(Fig, &mut command, BIN, &mut script),
+        "nushell" => clap_complete::generate(Nushell, &mut command, BIN, &mut script),
+        _ => match shell.parse::<Shell>() {
+            Ok(generator) => clap_complete::generate(generator, &mut command, BIN, &mut script),
+            Err(_) => {
+                return result_err_bare!(
+                    "{}: Unknown shell, use one of {}",
+                    shell,
+                    SHELLS.join(", ")
+                )
+            }
+        },
+    }
+    if let Some(hook) = dynamic_hook(shell) {
+        script.extend_from_slice(hook.as_bytes());
+    }
+    Ok(script)
+}
+
+fn home() -> Result<PathBuf> {
+    match env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+        Some(home) => Ok(PathBuf::from(home)),
+        None => result_err_bare!("Cannot install completions, the home directory is unknown"),
+    }
+}
+
+// The base directory named by the given XDG environment variable, or its default below the home
+// directory
+fn xdg_home(name: &str, default: &str) -> Result<PathBuf> {
+    match env::var_os(name).map(PathBuf::from) {
+        Some(path) if path.is_absolute() => Ok(path),
+        _ => Ok(home()?.join(default)),
+    }
+}
+
+fn data_home() -> Result<PathBuf> {
+    xdg_home("XDG_DATA_HOME", ".local/share")
+}
+
+fn config_home() -> Result<PathBuf> {
+    xdg_home("XDG_CONFIG_HOME", ".config")
+}
@@ -0,0 +1,646 @@
+//! The configuration file of the application, checked against a schema of its settings.
+//!
+//! The configuration is written in TOML, in `config.toml` in the configuration directory of the
+//! application, which on Linux is `~/.config/<name>`, or `$XDG_CONFIG_HOME/<name>` when
+//! `XDG_CONFIG_HOME` is set. Each setting of the [`SCHEMA`] has a key, which is dotted for the
+//! settings of a table, e.g. `fetch.timeout` for `timeout` in the `[fetch]` table, and the type
+//! of its value:
+//!
+//! ```text
+//! accessible = true
+//!
+//! [fetch]
+//! timeout = "1m"
+//! ```
+//!
+//! The file is validated before it is used, so that a typo is not silently ignored. [`validate`]
+//! reports every problem at its line and column: a syntax error, an unknown key, with the nearest
+//! known key as a suggestion, and a value of the wrong type, with the type expected. The
+//! `config validate` subcommand checks a file this way without running anything else:
+//!
+//! ```text
+//! config.toml:1:1: Unknown key 'acessible', did you mean 'accessible'?
+//! config.toml:4:11: Invalid value for 'fetch.timeout', expected a string but found an integer
+//! config.toml: 2 problems found
+//! ```
+//!
+//! [`load`] validates the file and then deserializes it with serde, so that the errors are those
+//! of the schema rather than those of serde. The schema is that of the demo settings of the
+//! template; replace them with the settings of your application.
+//!
+//! A file written for an earlier release is upgraded first by the [`MIGRATIONS`], following its
+//! `config_version` setting (see the [`migration`] module). [`load`] writes the upgraded file in
+//! place of the original, which is kept beside it, e.g. as `config.toml.v1.bak`, and warns about
+//! each change made. A backup left by an earlier migration is only replaced with `--force`. The
+//! `config migrate --dry-run` subcommand shows the changes as a diff without making them:
+//!
+//! ```text
+//! --- config.toml
+//! +++ config.toml
+//! @@ -1,2 +1,3 @@
+//! -accessibility = true
+//! +config_version = 3
+//! +accessible = true
+//! config.toml: Renamed 'accessibility' to 'accessible'
+//! config.toml: The configuration would be migrated from version 1 to 3
+//! ```
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Number, Value as Json};
+use toml_edit::{Document, Item, Value};
+
+use crate::diff;
+use crate::fsx::Journal;
+use crate::outcome::Outcome;
+use crate::platform::long_path;
+use crate::prompt::Overrides;
+use crate::{option_err_bare, result_err_bare, scope, ErrorFormat, ErrorKind, Result, ResultExt};
+
+pub mod migration;
+
+use migration::{Change, Migration, Upgrade};
+
+/// The name of the file.
+pub const FILE_NAME: &str = "config.toml";
+
+/// The settings that may be given in the configuration file.
+pub const SCHEMA: &[Setting] = &[
+    Setting {
+        key: migration::VERSION_KEY,
+        value_type: ValueType::Integer,
+        help: "The version of the configuration file, set when it is migrated",
+    },
+    Setting {
+        key: "accessible",
+        value_type: ValueType::Boolean,
+        help: "Turn on output suited to screen readers",
+    },
+    Setting {
+        key: "error_format",
+        value_type: ValueType::String,
+        help: "The format of error reports, text or github",
+    },
+    Setting {
+        key: "fetch.timeout",
+        value_type: ValueType::String,
+        help: "The time allowed for the whole request, e.g. 30s or 1m30s",
+    },
+    Setting {
+        key: "fetch.max_size",
+        value_type: ValueType::String,
+        help: "The largest response accepted, e.g. 512kB or 10MiB",
+    },
+    Setting {
+        key: "fetch.retries",
+        value_type: ValueType::Integer,
+        help: "The number of times a request that failed for a transient reason is retried",
+    },
+    Setting {
+        key: "run_script.keep_going",
+        value_type: ValueType::Boolean,
+        help: "Run the rest of the commands of a script after one fails",
+    },
+    Setting {
+        key: "update.check",
+        value_type: ValueType::Boolean,
+        help: "Check for a newer version of the application now and then",
+    },
+];
+
+/// The migrations upgrading a configuration file written for an earlier release, in the order of
+/// the versions, each from one version to the next (see the [`migration`] module).
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 1,
+        changes: &[
+            Change::Rename {
+                old: "accessibility",
+                new: "accessible",
+            },
+            Change::Convert {
+                key: "fetch.timeout",
+                convert: seconds_to_duration,
+            },
+        ],
+    },
+    Migration {
+        from: 2,
+        changes: &[
+            Change::Rename {
+                old: "keep_going",
+                new: "run_script.keep_going",
+            },
+            Change::Rename {
+                old: "check_updates",
+                new: "update.check",
+            },
+            Change::Remove {
+                key: "fetch.user_agent",
+            },
+        ],
+    },
+];
+
+/// A setting of the configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setting {
+    /// The key of the setting, dotted for a setting in a table, e.g. `fetch.timeout`.
+    pub key: &'static str,
+    /// The type of the value of the setting.
+    pub value_type: ValueType,
+    /// What the setting is for.
+    pub help: &'static str,
+}
+
+/// The type of the value of a setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    /// `true` or `false`.
+    Boolean,
+    /// A whole number.
+    Integer,
+    /// A number, which may be given as a whole number.
+    Float,
+    /// A string.
+    String,
+    /// An array of values of any type.
+    Array,
+}
+
+impl ValueType {
+    /// The type with its article, as used in the messages, e.g. `an integer`.
+    pub fn description(self) -> &'static str {
+        match self {
+            ValueType::Boolean => "a boolean",
+            ValueType::Integer => "an integer",
+            ValueType::Float => "a float",
+            ValueType::String => "a string",
+            ValueType::Array => "an array",
+        }
+    }
+
+    // Whether the item is of the type
+    fn matches(self, item: &Item) -> bool {
+        matches!(
+            (self, item.as_value()),
+            (ValueType::Boolean, Some(Value::Boolean(_)))
+                | (ValueType::Integer, Some(Value::Integer(_)))
+                | (ValueType::Float, Some(Value::Float(_) | Value::Integer(_)))
+                | (ValueType::String, Some(Value::String(_)))
+                | (ValueType::Array, Some(Value::Array(_)))
+        )
+    }
+}
+
+/// A problem found in a configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// The line of the problem, starting at 1.
+    pub line: u32,
+    /// The column of the problem, starting at 1.
+    pub column: u32,
+    /// What the problem is.
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    /// The problem as `line:column: message`, to follow the name of the file.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// The configuration directory of the application, or `None` if it is unknown.
+pub fn directory() -> Option<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| {
+            if cfg!(windows) {
+                env::var_os("APPDATA").map(PathBuf::from)
+            } else {
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+            }
+        })?;
+    Some(config.join(env!("CARGO_PKG_NAME")))
+}
+
+/// The path of the configuration file, or `None` if the configuration directory is unknown.
+pub fn path() -> Option<PathBuf> {
+    Some(directory()?.join(FILE_NAME))
+}
+
+/// Check the text of a configuration file against the schema, returning the problems found in
+/// the order they appear in the text.
+///
+/// # Examples
+/// ```
+/// use tyg_template::config::{self, SCHEMA};
+///
+/// let text = "acessible = true\n\n[fetch]\ntimeout = 30\nretries = 3\n";
+/// let problems: Vec<String> =
+///     config::validate(text, SCHEMA).iter().map(ToString::to_string).collect();
+///
+/// assert_eq!(problems, [
+///     "1:1: Unknown key 'acessible', did you mean 'accessible'?",
+///     "4:11: Invalid value for 'fetch.timeout', expected a string but found an integer",
+/// ]);
+/// assert_eq!(config::validate("timeout = \"30s\"", SCHEMA)[0].message,
+///            "Unknown key 'timeout', did you mean 'fetch.timeout'?");
+/// assert_eq!(config::validate("[update\n", SCHEMA)[0].to_string(),
+///            "1:8: Invalid TOML, unclosed table, expected `]`");
+/// ```
+pub fn validate(text: &str, schema: &[Setting]) -> Vec<Problem> {
+    check(text, schema).1
+}
+
+/// Load the configuration file, checked against the schema, into a value deserialized with serde,
+/// such as a struct of the settings or a `serde_json::Value`. The first problem found in the file
+/// is reported as an error of the `Config` kind, with the number of others, in the scope of
+/// `loading configuration`. A file written for an earlier release is first upgraded with the
+/// [`MIGRATIONS`] and saved, keeping the original beside it, with a warning about each change.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use std::path::Path;
+/// use serde_json::Value;
+/// use tyg_template::{config, ErrorKind};
+/// use tyg_template::testkit::TempProject;
+///
+/// let _project = TempProject::builder()
+///     .file("good.toml", "[fetch]\nretries = 3\n")
+///     .file("bad.toml", "config_version = 3\n[fetch]\nretrys = 3\ntimeout = 30\n")
+///     .file("old.toml", "keep_going = true\n")
+///     .create()
+///     .unwrap();
+///
+/// let settings: Value = config::load(Path::new("good.toml"), config::SCHEMA).unwrap();
+/// assert_eq!(settings["fetch"]["retries"], 3);
+///
+/// let settings: Value = config::load(Path::new("old.toml"), config::SCHEMA).unwrap();
+/// assert_eq!(settings["run_script"]["keep_going"], true);
+/// assert_eq!(fs::read_to_string("old.toml.v1.bak").unwrap(), "keep_going = true\n");
+///
+/// let error = config::load::<Value>(Path::new("bad.toml"), config::SCHEMA).unwrap_err();
+/// assert_eq!(error.kind(), ErrorKind::Config);
+/// assert_eq!(error.scopes(), ["loading configuration"]);
+/// assert!(error.to_string()
+///     .ends_with("bad.toml:3:1: Unknown key 'retrys', did you mean 'retries'? (and 1 more problem)"));
+/// ```
+pub fn load<T: DeserializeOwned>(path: &Path, schema: &[Setting]) -> Result<T> {
+    let _scope = scope("loading configuration");
+    let mut text = fs::read_to_string(long_path(path)).bare(|| path.display())?;
+    if let Some(upgrade) = migration::upgrade(&text, MIGRATIONS).bare(|| path.display())? {
+        let backup = save(path, &text, &upgrade)?;
+        warn(&migrated(path, &upgrade, &backup));
+        for change in &upgrade.changes {
+            warn(&format!("{}: {}", path.display(), change));
+        }
+        text = upgrade.text;
+    }
+    let (document, problems) = check(&text, schema);
+    if let Some(problem) = problems.first() {
+        let more = match problems.len() - 1 {
+            0 => String::new(),
+            1 => " (and 1 more problem)".to_string(),
+            n => format!(" (and {} more problems)", n),
+        };
+        return result_err_bare!(
+            kind = ErrorKind::Config,
+            "{}:{}{}",
+            path.display(),
+            problem,
+            more
+        );
+    }
+    let json = document.map_or(Json::Null, |document| to_json(document.as_item()));
+    serde_json::from_value(json)
+        .or_else(|e| result_err_bare!(kind = ErrorKind::Config, "{}: {}", path.display(), e))
+}
+
+/// The `config validate` subcommand, which checks the configuration file, or the given file,
+/// against the schema and writes the problems found.
+pub fn validate_command(path: Option<&Path>, out: &mut dyn Write) -> Result<Outcome> {
+    let path = file(path, "validate")?;
+    let text = fs::read_to_string(long_path(&path)).bare(|| path.display())?;
+    let problems = validate(&text, SCHEMA);
+    for problem in &problems {
+        writeln!(out, "{}:{}", path.display(), problem)?;
+    }
+    match problems.len() {
+        0 => writeln!(out, "{}: The configuration is valid", path.display())?,
+        1 => writeln!(out, "{}: 1 problem found", path.display())?,
+        n => writeln!(out, "{}: {} problems found", path.display(), n)?,
+    }
+    Ok(Outcome::Findings(problems.len()))
+}
+
+/// The `config migrate` subcommand, which upgrades the configuration file, or the given file, with
+/// the [`MIGRATIONS`], keeping the original beside it, and writes the changes made. If `dry_run` is
+/// set, the changes are shown as a unified diff instead and the file is not modified.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use std::path::Path;
+/// use tyg_template::config;
+/// use tyg_template::testkit::TempProject;
+///
+/// let _project = TempProject::builder()
+///     .file("old.toml", "accessibility = true\n\n[fetch]\ntimeout = 30\n")
+///     .create()
+///     .unwrap();
+///
+/// let mut out = Vec::new();
+/// config::migrate_command(Some(Path::new("old.toml")), true, &mut out).unwrap();
+///
+/// assert!(String::from_utf8_lossy(&out).contains("+timeout = \"30s\""));
+/// assert!(fs::read_to_string("old.toml").unwrap().starts_with("accessibility"));
+///
+/// config::migrate_command(Some(Path::new("old.toml")), false, &mut out).unwrap();
+///
+/// assert!(fs::read_to_string("old.toml").unwrap().starts_with("config_version = 3\naccessible = true"));
+/// assert!(fs::read_to_string("old.toml.v1.bak").unwrap().starts_with("accessibility"));
+/// ```
+pub fn migrate_command(path: Option<&Path>, dry_run: bool, out: &mut dyn Write) -> Result<()> {
+    let path = file(path, "migrate")?;
+    let text = fs::read_to_string(long_path(&path)).bare(|| path.display())?;
+    // A file that is not valid TOML is not migrated, so the syntax error is reported instead
+    if let (None, problems) = check(&text, SCHEMA) {
+        return result_err_bare!(
+            kind = ErrorKind::Config,
+            "{}:{}",
+            path.display(),
+            problems[0]
+        );
+    }
+    let Some(upgrade) = migration::upgrade(&text, MIGRATIONS).bare(|| path.display())? else {
+        writeln!(
+            out,
+            "{}: The configuration is up to date at version {}",
+            path.display(),
+            migration::current_version(MIGRATIONS)
+        )?;
+        return Ok(());
+    };
+    let name = path.display().to_string();
+    // The changes are only listed once they have been made
+    let backup = match dry_run {
+        true => {
+            write!(out, "{}", diff::unified(&name, &name, &text, &upgrade.text))?;
+            None
+        }
+        false => Some(save(&path, &text, &upgrade)?),
+    };
+    for change in &upgrade.changes {
+        writeln!(out, "{}: {}", name, change)?;
+    }
+    match backup {
+        Some(backup) => writeln!(out, "{}", migrated(&path, &upgrade, &backup))?,
+        None => writeln!(
+            out,
+            "{}: The configuration would be migrated from version {} to {}",
+            name, upgrade.from, upgrade.to
+        )?,
+    }
+    Ok(())
+}
+
+// The given file, or the configuration file if none is given, for the subcommand doing the action
+fn file(path: Option<&Path>, action: &str) -> Result<PathBuf> {
+    match path {
+        Some(path) => Ok(path.to_path_buf()),
+        None => self::path().ok_or_else(|| {
+            option_err_bare!(
+                kind = ErrorKind::Config,
+                "The configuration directory is unknown, give the file to {}",
+                action
+            )
+        }),
+    }
+}
+
+// Write the upgraded file in place of the original, which is kept beside it with the version it
+// had, e.g. config.toml.v1.bak, returning the path of the original. Neither is written unless
+// both can be, and the backup of an earlier migration is only replaced with --force.
+fn save(path: &Path, original: &str, upgrade: &Upgrade) -> Result<PathBuf> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{}.bak", upgrade.from));
+    let backup = PathBuf::from(backup);
+    Overrides::selected().check_absent(&backup)?;
+    let journal = Journal::new()?;
+    journal.write(&backup, original)?;
+    journal.write(path, &upgrade.text)?;
+    journal.commit()?;
+    Ok(backup)
+}
+
+// The report of the upgrade of the file, kept in the backup
+fn migrated(path: &Path, upgrade: &Upgrade, backup: &Path) -> String {
+    format!(
+        "{}: Migrated the configuration from version {} to {}, keeping the original in {}",
+        path.display(),
+        upgrade.from,
+        upgrade.to,
+        backup.display()
+    )
+}
+
+// Convert a time given as a number of seconds to a duration, e.g. 30 to "30s"
+fn seconds_to_duration(value: &str) -> Option<String> {
+    let seconds: u64 = value.parse().ok()?;
+    Some(format!("\"{}s\"", seconds))
+}
+
+fn warn(message: &str) {
+    let _ = writeln!(
+        io::stderr(),
+        "{}",
+        ErrorFormat::selected().warning(env!("CARGO_PKG_NAME"), message)
+    );
+}
+
+// The parsed document, if the syntax is valid, and the problems found in it
+fn check<'a>(text: &'a str, schema: &[Setting]) -> (Option<Document<&'a str>>, Vec<Problem>) {
+    let document = match Document::parse(text) {
+        Ok(document) => document,
+        Err(e) => {
+            let message = format!("Invalid TOML, {}", e.message().trim_end());
+            return (None, vec![problem(text, e.span(), message)]);
+        }
+    };
+    let mut problems = Vec::new();
+    check_table(text, document.as_item(), "", schema, &mut problems);
+    problems.sort_by_key(|problem| (problem.line, problem.column));
+    (Some(document), problems)
+}
+
+// Check the keys of a table, with the given prefix, against the schema
+fn check_table(
+    text: &str,
+    table: &Item,
+    prefix: &str,
+    schema: &[Setting],
+    problems: &mut Vec<Problem>,
+) {
+    let Some(table) = table.as_table_like() else {
+        return;
+    };
+    for (key, item) in table.iter() {
+        let key_span = table.get_key_value(key).and_then(|(key, _)| key.span());
+        let full = format!("{}{}", prefix, key);
+        if let Some(setting) = schema.iter().find(|setting| setting.key == full) {
+            if !setting.value_type.matches(item) {
+                let message = format!(
+                    "Invalid value for '{}', expected {} but found {}",
+                    full,
+                    setting.value_type.description(),
+                    found(item)
+                );
+                problems.push(problem(text, item.span().or(key_span), message));
+            }
+        } else if schema
+            .iter()
+            .any(|setting| setting.key.starts_with(&format!("{}.", full)))
+        {
+            if item.is_table_like() {
+                check_table(text, item, &format!("{}.", full), schema, problems);
+            } else {
+                let message = format!(
+                    "Invalid value for '{}', expected a table, e.g. [{}], but found {}",
+                    full,
+                    full,
+                    found(item)
+                );
+                problems.push(problem(text, item.span().or(key_span), message));
+            }
+        } else {
+            let message = match suggestion(key, prefix, schema) {
+                Some(suggestion) => {
+                    format!("Unknown key '{}', did you mean '{}'?", key, suggestion)
+                }
+                None => format!("Unknown key '{}'", key),
+            };
+            problems.push(problem(text, key_span, message));
+        }
+    }
+}
+
+// The nearest key to the unknown key, among the keys of the table, or failing that a key of
+// another table with the same name
+fn suggestion(key: &str, prefix: &str, schema: &[Setting]) -> Option<String> {
+    let mut names: Vec<&str> = schema
+        .iter()
+        .filter_map(|setting| setting.key.strip_prefix(prefix))
+        .map(|rest| rest.split('.').next().unwrap_or(rest))
+        .collect();
+    names.dedup();
+    let nearest = names
+        .into_iter()
+        .map(|name| (distance(key, name), name))
+        .filter(|&(distance, name)| distance * 3 <= name.chars().count())
+        .min();
+    if let Some((_, name)) = nearest {
+        return Some(name.to_string());
+    }
+    schema
+        .iter()
+        .find(|setting| setting.key.rsplit('.').next() == Some(key))
+        .map(|setting| setting.key.to_string())
+}
+
+// The Levenshtein distance between two strings: the number of characters inserted, removed or
+// replaced to turn one into the other
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let replaced = diagonal + usize::from(a != b);
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// The type of an item with its article
+fn found(item: &Item) -> &'static str {
+    match item {
+        Item::None => "nothing",
+        Item::Table(_) | Item::Value(Value::InlineTable(_)) => "a table",
+        Item::ArrayOfTables(_) => "an array of tables",
+        Item::Value(Value::String(_)) => "a string",
+        Item::Value(Value::Integer(_)) => "an integer",
+        Item::Value(Value::Float(_)) => "a float",
+        Item::Value(Value::Boolean(_)) => "a boolean",
+        Item::Value(Value::Datetime(_)) => "a date and time",
+        Item::Value(Value::Array(_)) => "an array",
+    }
+}
+
+// A problem at the start of the span of the text, or at its start if the span is unknown
+fn problem(text: &str, span: Option<Range<usize>>, message: String) -> Problem {
+    let offset = span.map_or(0, |span| span.start);
+    let before = text.get(..offset).unwrap_or(text);
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or(before).chars().count() + 1;
+    Problem {
+        line: line as u32,
+        column: column as u32,
+        message,
+    }
+}
+
+// The item as JSON, with dates and times as strings
+fn to_json(item: &Item) -> Json {
+    match item {
+        Item::None => Json::Null,
+        Item::Value(value) => value_to_json(value),
+        Item::Table(table) => Json::Object(
+            table
+                .iter()
+                .map(|(key, item)| (key.to_string(), to_json(item)))
+                .collect(),
+        ),
+        Item::ArrayOfTables(tables) => Json::Array(
+            tables
+                .iter()
+                .map(|table| {
+                    let object: Map<String, Json> = table
+                        .iter()
+                        .map(|(key, item)| (key.to_string(), to_json(item)))
+                        .collect();
+                    Json::Object(object)
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn value_to_json(value: &Value) -> Json {
+    match value {
+        Value::String(s) => Json::String(s.value().clone()),
+        Value::Integer(i) => Json::Number(Number::from(*i.value())),
+        Value::Float(f) => Number::from_f64(*f.value()).map_or(Json::Null, Json::Number),
+        Value::Boolean(b) => Json::Bool(*b.value()),
+        Value::Datetime(d) => Json::String(d.value().to_string()),
+        Value::Array(array) => Json::Array(array.iter().map(value_to_json).collect()),
+        Value::InlineTable(table) => Json::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), value_to_json(value)))
+                .collect(),
+        ),
+    }
+}
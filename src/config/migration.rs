@@ -0,0 +1,348 @@
+//! Versioned migration of the configuration file, so that a file written for an earlier release is
+//! upgraded to the settings of this one.
+//!
+//! The version of a file is given by its `config_version` setting, and a file without one is
+//! taken to be at version 1. Each [`Migration`] in a registry upgrades a file from one version to
+//! the next with a list of [`Change`]s, such as renaming a setting or converting its value, and
+//! [`upgrade`] applies the migrations from the version of the file onwards in turn, ending by
+//! setting `config_version` to the version after the last migration. The file is edited as text,
+//! so that its comments and layout are kept:
+//!
+//! ```text
+//! # Before                  # After
+//! accessibility = true      config_version = 3
+//! keep_going = true         accessible = true
+//!
+//! [fetch]                   [fetch]
+//! timeout = 30              timeout = "30s"
+//!
+//!                           [run_script]
+//!                           keep_going = true
+//! ```
+//!
+//! Settings given as dotted keys or in inline tables cannot be moved by a migration, which fails
+//! naming the setting to edit by hand.
+
+use std::ops::Range;
+
+use toml_edit::{Document, Item, Table};
+
+use crate::{result_err_bare, ErrorKind, Result};
+
+/// The setting giving the version of the configuration file.
+pub const VERSION_KEY: &str = "config_version";
+
+/// An upgrade of the configuration file from one version to the next.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// The version upgraded from, to the version after it.
+    pub from: i64,
+    /// The changes made to the file, in order.
+    pub changes: &'static [Change],
+}
+
+/// A change made to the configuration file by a migration. Each change is skipped if the file
+/// does not have the setting it changes.
+#[derive(Debug, Clone, Copy)]
+pub enum Change {
+    /// Rename a setting, moving it to another table if the key of its table changes, e.g. from
+    /// `keep_going` to `run_script.keep_going`.
+    Rename {
+        /// The old key, dotted for a setting in a table.
+        old: &'static str,
+        /// The new key, in the same form as the old key.
+        new: &'static str,
+    },
+    /// Convert the value of a setting, given as TOML, e.g. from `30` to `"30s"`. The value is left
+    /// as it is if the conversion gives `None`.
+    Convert {
+        /// The key of the setting.
+        key: &'static str,
+        /// The conversion of the value.
+        convert: fn(&str) -> Option<String>,
+    },
+    /// Remove a setting that is no longer used.
+    Remove {
+        /// The key of the setting.
+        key: &'static str,
+    },
+}
+
+/// The configuration file upgraded by the migrations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upgrade {
+    /// The version of the file before the upgrade.
+    pub from: i64,
+    /// The version of the file after the upgrade.
+    pub to: i64,
+    /// The text of the upgraded file.
+    pub text: String,
+    /// A description of each change made, e.g. `Renamed 'keep_going' to 'run_script.keep_going'`.
+    pub changes: Vec<String>,
+}
+
+/// The version a file is upgraded to by the migrations: the version after the last, or 1 if there
+/// are none.
+pub fn current_version(migrations: &[Migration]) -> i64 {
+    migrations.last().map_or(1, |migration| migration.from + 1)
+}
+
+/// Upgrade the text of a configuration file with the migrations from its version onwards,
+/// returning `None` if it is already at the current version or is not valid TOML, which the
+/// validation of the file reports.
+///
+/// An error of the `Config` kind is returned if the version of the file is not a whole number or is
+/// newer than the current version, or if a change cannot be made.
+///
+/// # Examples
+/// ```
+/// use tyg_template::config::migration::{self, Change, Migration};
+///
+/// const MIGRATIONS: &[Migration] = &[
+///     Migration { from: 1, changes: &[Change::Rename { old: "colour", new: "color" }] },
+///     Migration { from: 2, changes: &[Change::Rename { old: "retries", new: "fetch.retries" }] },
+/// ];
+///
+/// let upgrade = migration::upgrade("colour = true # Be bright\nretries = 3\n", MIGRATIONS).unwrap().unwrap();
+///
+/// assert_eq!((upgrade.from, upgrade.to), (1, 3));
+/// assert_eq!(upgrade.text, "config_version = 3\ncolor = true # Be bright\n\n[fetch]\nretries = 3\n");
+/// assert_eq!(upgrade.changes, ["Renamed 'colour' to 'color'", "Renamed 'retries' to 'fetch.retries'"]);
+/// assert_eq!(migration::upgrade(&upgrade.text, MIGRATIONS).unwrap(), None);
+/// assert!(migration::upgrade("config_version = 4\n", MIGRATIONS).is_err());
+/// ```
+pub fn upgrade(text: &str, migrations: &[Migration]) -> Result<Option<Upgrade>> {
+    let Ok(document) = Document::parse(text) else {
+        return Ok(None);
+    };
+    let from = match document.get(VERSION_KEY) {
+        None => 1,
+        Some(item) => match item.as_integer() {
+            Some(version) => version,
+            None => {
+                return result_err_bare!(
+                    kind = ErrorKind::Config,
+                    "Invalid value for '{}', expected an integer",
+                    VERSION_KEY
+                )
+            }
+        },
+    };
+    let to = current_version(migrations);
+    if from > to {
+        return result_err_bare!(
+            kind = ErrorKind::Config,
+            "Version {} of the configuration is newer than this release supports, which is {}",
+            from,
+            to
+        );
+    }
+    if from == to {
+        return Ok(None);
+    }
+    let mut text = text.to_string();
+    let mut changes = Vec::new();
+    for migration in migrations.iter().filter(|migration| migration.from >= from) {
+        for change in migration.changes {
+            if let Some(description) = apply(&mut text, change)? {
+                changes.push(description);
+            }
+        }
+    }
+    // A version added to the file goes first, before any comment
+    match find(&text, VERSION_KEY)? {
+        Some(entry) => text.replace_range(entry.value, &to.to_string()),
+        None => text.insert_str(0, &format!("{} = {}\n", VERSION_KEY, to)),
+    }
+    Ok(Some(Upgrade {
+        from,
+        to,
+        text,
+        changes,
+    }))
+}
+
+// Make a change to the text, returning its description unless there was nothing to change
+fn apply(text: &mut String, change: &Change) -> Result<Option<String>> {
+    match *change {
+        Change::Rename { old, new } => {
+            let Some(entry) = find(text, old)? else {
+                return Ok(None);
+            };
+            if find(text, new)?.is_some() {
+                return result_err_bare!(
+                    kind = ErrorKind::Config,
+                    "Both '{}' and '{}', which replaces it, are set, remove one of them",
+                    old,
+                    new
+                );
+            }
+            if parent(old) == parent(new) {
+                text.replace_range(entry.key, leaf(new));
+            } else {
+                let value = text[entry.value.clone()].to_string();
+                text.replace_range(entry.line, "");
+                insert(text, new, &value)?;
+            }
+            Ok(Some(format!("Renamed '{}' to '{}'", old, new)))
+        }
+        Change::Convert { key, convert } => {
+            let Some(entry) = find(text, key)? else {
+                return Ok(None);
+            };
+            let value = text[entry.value.clone()].to_string();
+            match convert(&value).filter(|converted| *converted != value) {
+                Some(converted) => {
+                    text.replace_range(entry.value, &converted);
+                    Ok(Some(format!(
+                        "Changed '{}' from {} to {}",
+                        key, value, converted
+                    )))
+                }
+                None => Ok(None),
+            }
+        }
+        Change::Remove { key } => {
+            let Some(entry) = find(text, key)? else {
+                return Ok(None);
+            };
+            text.replace_range(entry.line, "");
+            Ok(Some(format!("Removed '{}', which is no longer used", key)))
+        }
+    }
+}
+
+// A setting in the text: the span of its lines, of the last part of its key and of its value
+struct Entry {
+    line: Range<usize>,
+    key: Range<usize>,
+    value: Range<usize>,
+}
+
+// Find the setting with the given key in the text
+fn find(text: &str, key: &str) -> Result<Option<Entry>> {
+    let document = parse(text)?;
+    let Some(table) = table(&document, key)? else {
+        return Ok(None);
+    };
+    let Some((name, item)) = table.get_key_value(leaf(key)) else {
+        return Ok(None);
+    };
+    let Some((key_span, value)) = name.span().zip(item.span()).filter(|_| item.is_value()) else {
+        return cannot_migrate(key);
+    };
+    let start = text[..key_span.start].rfind('\n').map_or(0, |end| end + 1);
+    Ok(Some(Entry {
+        line: start..line_end(text, value.end),
+        key: key_span,
+        value,
+    }))
+}
+
+// Insert the setting with the given key and value into the text, after the last setting of its
+// table, which is added at the end of the text if it has no header
+fn insert(text: &mut String, key: &str, value: &str) -> Result<()> {
+    let document = parse(text)?;
+    let line = format!("{} = {}\n", leaf(key), value);
+    let parent_key = parent(key);
+    let Some(table) = table(&document, key)?.filter(|table| !table.is_implicit()) else {
+        end_line(text);
+        text.push_str(&format!("\n[{}]\n{}", parent_key.unwrap_or_default(), line));
+        return Ok(());
+    };
+    let last = table
+        .iter()
+        .filter(|(_, item)| item.is_value())
+        .filter_map(|(_, item)| item.span())
+        .map(|span| span.end)
+        .max();
+    let position = match (last, parent_key) {
+        (Some(end), _) => line_end(text, end),
+        // A setting of a root table without settings goes before the first table
+        (None, None) => {
+            let first = table
+                .iter()
+                .filter_map(|(_, item)| match item {
+                    Item::Table(table) => table.span(),
+                    Item::ArrayOfTables(tables) => tables.get(0).and_then(Table::span),
+                    _ => None,
+                })
+                .map(|span| span.start)
+                .min();
+            if let Some(start) = first {
+                text.insert_str(start, &format!("{}\n", line));
+                return Ok(());
+            }
+            text.len()
+        }
+        (None, Some(_)) => match table.span() {
+            Some(header) => line_end(text, header.end),
+            None => return cannot_migrate(key),
+        },
+    };
+    let position = if position == text.len() {
+        end_line(text);
+        text.len()
+    } else {
+        position
+    };
+    text.insert_str(position, &line);
+    Ok(())
+}
+
+// End the last line of the text, if it is not ended
+fn end_line(text: &mut String) {
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+// The table holding the setting with the given key, if its table is in the document. An error is
+// returned if the key leads through a dotted key or an inline table, whose lines are not those of
+// the setting.
+fn table<'a>(document: &'a Document<&str>, key: &str) -> Result<Option<&'a Table>> {
+    let mut table = document.as_table();
+    for part in parent(key).into_iter().flat_map(|parent| parent.split('.')) {
+        table = match table.get(part) {
+            None => return Ok(None),
+            Some(Item::Table(child)) if !child.is_dotted() => child,
+            Some(_) => return cannot_migrate(key),
+        };
+    }
+    Ok(Some(table))
+}
+
+fn parse(text: &str) -> Result<Document<&str>> {
+    Document::parse(text).or_else(|e| {
+        result_err_bare!(
+            kind = ErrorKind::Config,
+            "Invalid TOML after the migration, {}",
+            e.message().trim_end()
+        )
+    })
+}
+
+fn cannot_migrate<T>(key: &str) -> Result<T> {
+    result_err_bare!(
+        kind = ErrorKind::Config,
+        "'{}' cannot be migrated from a dotted key or an inline table, write it in a table of its own",
+        key
+    )
+}
+
+// The key of the table of a setting, or None for a setting of the root table
+fn parent(key: &str) -> Option<&str> {
+    key.rsplit_once('.').map(|(parent, _)| parent)
+}
+
+// The last part of the key of a setting
+fn leaf(key: &str) -> &str {
+    key.rsplit('.').next().unwrap_or(key)
+}
+
+// The offset after the end of the line holding the offset
+fn line_end(text: &str, offset: usize) -> usize {
+    text[offset..]
+        .find('\n')
+        .map_or(text.len(), |end| offset + end + 1)
+}
@@ -0,0 +1,160 @@
+//! Dates and times given on the command line, e.g. `--since 2024-01-01` or `--since "2 days ago"`.
+//!
+//! This module is available when the `datetime` feature is enabled, which adds a dependency on
+//! [jiff](https://docs.rs/jiff). [`DateTime`] implements `FromStr`, so it can be used as the value
+//! of an argument with `value_parser!`. Dates and times without an offset, and relative
+//! expressions, are resolved in the local time zone.
+//!
+//! ```
+//! use clap::{arg, value_parser, Command};
+//! use tyg_template::datetime::DateTime;
+//!
+//! let command = Command::new("example")
+//!     .arg(arg!(--since <WHEN> "Show the entries after this time").value_parser(value_parser!(DateTime)));
+//!
+//! let matches = command.try_get_matches_from(["example", "--since", "2024-01-01T12:00:00Z"]).unwrap();
+//!
+//! assert_eq!(matches.get_one::<DateTime>("since").unwrap().timestamp().as_second(), 1_704_110_400);
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use jiff::civil;
+use jiff::{Span, Timestamp, Zoned};
+
+use crate::{result_err_bare, Error, Result};
+
+// The forms accepted for a date and time, shown when one cannot be parsed
+const FORMS: &str = "give an RFC 3339 date and time (2024-01-01T12:00:00Z), a date (2024-01-01), \
+     a date and time (2024-01-01 12:00), now, today, yesterday, tomorrow or a relative time \
+     (30 minutes ago, 2 days ago)";
+
+/// A point in time in a time zone, given as an RFC 3339 date and time, a local date or date and
+/// time, or a simple expression relative to the current time.
+///
+/// The forms accepted are:
+///
+/// - RFC 3339, e.g. `2024-01-01T12:00:00Z` or `2024-01-01T12:00:00+01:00`.
+/// - A date, e.g. `2024-01-01`, meaning the start of the day.
+/// - A date and time, with `T` or a space between them, e.g. `2024-01-01 12:00`.
+/// - `now`, and `today`, `yesterday` or `tomorrow`, meaning the start of the day.
+/// - A number of seconds, minutes, hours, days or weeks ago, e.g. `2 days ago`.
+///
+/// The value is displayed in RFC 3339 form with the offset of its time zone.
+///
+/// # Examples
+/// ```
+/// use jiff::tz::TimeZone;
+/// use jiff::Timestamp;
+/// use tyg_template::datetime::DateTime;
+/// use tyg_template::{assert_err_msg, Error};
+///
+/// let now = Timestamp::from_second(1_704_110_400).unwrap().to_zoned(TimeZone::UTC);
+///
+/// let since = DateTime::parse_at("2 days ago", &now).unwrap();
+/// assert_eq!(since.to_string(), "2023-12-30T12:00:00+00:00");
+///
+/// let since = DateTime::parse_at("yesterday", &now).unwrap();
+/// assert_eq!(since.to_string(), "2023-12-31T00:00:00+00:00");
+///
+/// let since = DateTime::parse_at("2024-01-01 08:30", &now).unwrap();
+/// assert_eq!(since.to_string(), "2024-01-01T08:30:00+00:00");
+///
+/// assert_err_msg!(DateTime::parse_at("last tuesday", &now), Error::Error(_),
+///                 "last tuesday: Unrecognized date and time, give an RFC 3339 *");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime(Zoned);
+
+impl DateTime {
+    /// Parse a date and time, resolving dates and times without an offset, and relative
+    /// expressions, against the given current time and its time zone.
+    pub fn parse_at(text: &str, now: &Zoned) -> Result<DateTime> {
+        let text = text.trim();
+        let zone = now.time_zone().clone();
+        let resolved = match text.to_lowercase().as_str() {
+            "now" => Ok(now.clone()),
+            "today" => now.start_of_day(),
+            "yesterday" => now.yesterday().and_then(|day| day.start_of_day()),
+            "tomorrow" => now.tomorrow().and_then(|day| day.start_of_day()),
+            relative => match relative.strip_suffix(" ago") {
+                Some(amount) => return ago(text, amount, now),
+                None => {
+                    if let Ok(timestamp) = text.parse::<Timestamp>() {
+                        return Ok(DateTime(timestamp.to_zoned(zone)));
+                    }
+                    if let Ok(datetime) = text.parse::<civil::DateTime>() {
+                        datetime.to_zoned(zone)
+                    } else if let Ok(date) = text.parse::<civil::Date>() {
+                        date.to_zoned(zone)
+                    } else {
+                        return result_err_bare!("{}: Unrecognized date and time, {}", text, FORMS);
+                    }
+                }
+            },
+        };
+        match resolved {
+            Ok(zoned) => Ok(DateTime(zoned)),
+            Err(e) => result_err_bare!("{}: {}", text, e),
+        }
+    }
+
+    /// The date and time in its time zone.
+    pub fn zoned(&self) -> &Zoned {
+        &self.0
+    }
+
+    /// The instant in time, independent of the time zone.
+    pub fn timestamp(&self) -> Timestamp {
+        self.0.timestamp()
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = Error;
+
+    /// Parse a date and time, resolving it against the current time in the local time zone.
+    fn from_str(text: &str) -> Result<DateTime> {
+        DateTime::parse_at(text, &Zoned::now())
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.0.timestamp().display_with_offset(self.0.offset())
+        )
+    }
+}
+
+// Resolve an amount of time before now, e.g. "2 days"
+fn ago(text: &str, amount: &str, now: &Zoned) -> Result<DateTime> {
+    let mut words = amount.split_whitespace();
+    let (Some(number), Some(unit), None) = (words.next(), words.next(), words.next()) else {
+        return result_err_bare!("{}: Unrecognized relative time, {}", text, FORMS);
+    };
+    let Ok(number) = number.parse::<i64>() else {
+        return result_err_bare!("{}: '{}' is not a whole number, {}", text, number, FORMS);
+    };
+    let span = match unit.strip_suffix('s').unwrap_or(unit) {
+        "second" => Span::new().try_seconds(number),
+        "minute" => Span::new().try_minutes(number),
+        "hour" => Span::new().try_hours(number),
+        "day" => Span::new().try_days(number),
+        "week" => Span::new().try_weeks(number),
+        _ => {
+            return result_err_bare!(
+                "{}: Unknown unit '{}', use seconds, minutes, hours, days or weeks",
+                text,
+                unit
+            )
+        }
+    };
+    match span.and_then(|span| now.checked_sub(span)) {
+        Ok(zoned) => Ok(DateTime(zoned)),
+        Err(e) => result_err_bare!("{}: {}", text, e),
+    }
+}
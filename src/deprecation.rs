@@ -0,0 +1,145 @@
+//! Deprecation of flags and subcommands that have been renamed.
+//!
+//! Each renamed flag or subcommand is listed in a registry of [`Deprecated`] entries. The old name
+//! is added to the command line as a hidden alias of the new one, so that it still parses, and a
+//! warning naming its replacement and the release that removes it is shown once whenever the old
+//! name is used. The warnings are written in the selected error format, so the `github` format
+//! turns them into workflow annotations. Setting the `TYG_NO_DEPRECATION_WARNINGS` environment
+//! variable suppresses them, e.g. in tests or when the output is read by another program.
+
+use std::env;
+use std::ffi::OsString;
+
+use clap::Command;
+
+/// The environment variable that suppresses deprecation warnings when it is set.
+pub const SUPPRESS_VAR: &str = "TYG_NO_DEPRECATION_WARNINGS";
+
+/// A flag or subcommand that has been renamed.
+#[derive(Debug, Clone, Copy)]
+pub struct Deprecated {
+    /// The names of the subcommands leading to the flag or subcommand separated by spaces, e.g.
+    /// `generate`, or an empty string for those of the application itself.
+    pub command: &'static str,
+    /// The old name, e.g. `--old` for a flag or `old` for a subcommand.
+    pub old: &'static str,
+    /// The new name, in the same form as the old name.
+    pub new: &'static str,
+    /// The release in which the old name will be removed.
+    pub removal: &'static str,
+}
+
+impl Deprecated {
+    /// The warning shown when the old name is used.
+    ///
+    /// # Examples
+    /// ```
+    /// use tyg_template::deprecation::Deprecated;
+    ///
+    /// let deprecated = Deprecated { command: "", old: "--old", new: "--new", removal: "2.0" };
+    ///
+    /// assert_eq!(deprecated.warning(), "--old is deprecated, use --new; it will be removed in 2.0");
+    /// ```
+    pub fn warning(&self) -> String {
+        format!(
+            "{} is deprecated, use {}; it will be removed in {}",
+            self.old, self.new, self.removal
+        )
+    }
+
+    fn flag(name: &str) -> Option<&str> {
+        name.strip_prefix("--")
+    }
+}
+
+/// Add the old names in the registry to the command as hidden aliases of the new ones.
+///
+/// # Panics
+///
+/// Panics if the new name of an entry does not exist, which the tests reveal as soon as the
+/// registry is changed.
+pub fn apply(mut command: Command, registry: &[Deprecated]) -> Command {
+    for deprecated in registry {
+        let path: Vec<&str> = deprecated.command.split_whitespace().collect();
+        command = alias(command, &path, deprecated);
+    }
+    command
+}
+
+fn alias(command: Command, path: &[&str], deprecated: &Deprecated) -> Command {
+    let Some((first, rest)) = path.split_first() else {
+        return match (
+            Deprecated::flag(deprecated.new),
+            Deprecated::flag(deprecated.old),
+        ) {
+            (Some(new), Some(old)) => {
+                let id = command
+                    .get_arguments()
+                    .find(|arg| arg.get_long() == Some(new))
+                    .map(|arg| arg.get_id().clone())
+                    .unwrap_or_else(|| panic!("{}: No such flag to deprecate", deprecated.new));
+                command.mut_arg(id, |arg| arg.alias(old))
+            }
+            _ => mut_subcommand(command, deprecated.new, |sub| sub.alias(deprecated.old)),
+        };
+    };
+    mut_subcommand(command, first, |sub| alias(sub, rest, deprecated))
+}
+
+// Change the named subcommand, keeping its place among the subcommands, unlike
+// Command::mut_subcommand, so that the completions and schema keep their order
+fn mut_subcommand(command: Command, name: &str, f: impl FnOnce(Command) -> Command) -> Command {
+    if command.find_subcommand(name).is_none() {
+        panic!("{}: No such subcommand to deprecate", name);
+    }
+    let mut f = Some(f);
+    command.mut_subcommands(|sub| match f.take_if(|_| sub.get_name() == name) {
+        Some(f) => f(sub),
+        None => sub,
+    })
+}
+
+/// The warnings for the deprecated names used on a command line, each given once, or none if
+/// warnings are suppressed. The first argument is taken to be the name of the binary.
+///
+/// # Examples
+/// ```
+/// use std::ffi::OsString;
+/// use tyg_template::deprecation::{warnings, Deprecated};
+///
+/// let registry = [Deprecated { command: "fail", old: "--plain", new: "--bare", removal: "2.0" }];
+/// let args: Vec<OsString> = ["bin", "fail", "--plain", "--plain"]
+///     .iter()
+///     .map(OsString::from)
+///     .collect();
+///
+/// assert_eq!(warnings(&args, &registry),
+///            ["--plain is deprecated, use --bare; it will be removed in 2.0"]);
+/// ```
+pub fn warnings(args: &[OsString], registry: &[Deprecated]) -> Vec<String> {
+    if env::var_os(SUPPRESS_VAR).is_some() {
+        return Vec::new();
+    }
+    let args: Vec<_> = args
+        .iter()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy())
+        .take_while(|arg| arg != "--")
+        .collect();
+    registry
+        .iter()
+        .filter(|deprecated| {
+            args.iter()
+                .any(|arg| match Deprecated::flag(deprecated.old) {
+                    Some(_) => {
+                        arg == deprecated.old
+                            || arg
+                                .strip_prefix(deprecated.old)
+                                .is_some_and(|rest| rest.starts_with('='))
+                    }
+                    None => arg == deprecated.old,
+                })
+        })
+        .map(Deprecated::warning)
+        .collect()
+}
@@ -0,0 +1,148 @@
+//! Rendering of the differences between two versions of a text file.
+
+// The number of unchanged lines shown either side of a change
+const CONTEXT: usize = 3;
+
+// A line of the difference between two texts
+#[derive(Clone, Copy, PartialEq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Render the differences between two versions of a text in the unified diff format.
+///
+/// The names label the old and new versions in the header. An empty string is returned if the
+/// texts are the same.
+///
+/// # Examples
+/// ```
+/// use tyg_template::diff::unified;
+///
+/// let old = "[package]\nname = \"old_name\"\nversion = \"0.1.0\"\n";
+/// let new = "[package]\nname = \"new_name\"\nversion = \"0.1.0\"\n";
+///
+/// assert_eq!(unified("Cargo.toml", "Cargo.toml", old, new), "\
+/// --- Cargo.toml
+/// +++ Cargo.toml
+/// @@ -1,3 +1,3 @@
+///  [package]
+/// -name = \"old_name\"
+/// +name = \"new_name\"
+///  version = \"0.1.0\"
+/// ");
+/// assert_eq!(unified("Cargo.toml", "Cargo.toml", old, old), "");
+/// ```
+pub fn unified(old_name: &str, new_name: &str, old: &str, new: &str) -> String {
+    let lines = diff_lines(old, new);
+    let mut result = String::new();
+    let mut index = 0;
+    while let Some(start) = lines[index..]
+        .iter()
+        .position(|l| !matches!(l, Line::Same(_)))
+    {
+        // Extend the hunk until the unchanged lines after a change exceed twice the context
+        let first = (index + start).saturating_sub(CONTEXT);
+        let mut last = index + start;
+        let mut cursor = last;
+        while cursor < lines.len() && cursor - last <= 2 * CONTEXT {
+            if !matches!(lines[cursor], Line::Same(_)) {
+                last = cursor;
+            }
+            cursor += 1;
+        }
+        let end = (last + CONTEXT + 1).min(lines.len());
+
+        if result.is_empty() {
+            result.push_str(&format!("--- {}\n+++ {}\n", old_name, new_name));
+        }
+        let before = &lines[..first];
+        let hunk = &lines[first..end];
+        let old_start = before
+            .iter()
+            .filter(|l| !matches!(l, Line::Added(_)))
+            .count();
+        let new_start = before
+            .iter()
+            .filter(|l| !matches!(l, Line::Removed(_)))
+            .count();
+        let old_count = hunk.iter().filter(|l| !matches!(l, Line::Added(_))).count();
+        let new_count = hunk
+            .iter()
+            .filter(|l| !matches!(l, Line::Removed(_)))
+            .count();
+        result.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_count),
+            range(new_start, new_count)
+        ));
+        for line in hunk {
+            let (prefix, text) = match *line {
+                Line::Same(text) => (' ', text),
+                Line::Removed(text) => ('-', text),
+                Line::Added(text) => ('+', text),
+            };
+            result.push(prefix);
+            result.push_str(text);
+            result.push('\n');
+        }
+        index = end;
+    }
+    result
+}
+
+// Format the range of a hunk, where the start is the number of lines before it
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+// Compare the texts line by line using the longest common subsequence of their lines
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lengths[i][j + 1] >= lengths[i + 1][j]) {
+            lines.push(Line::Added(new[j]));
+            j += 1;
+        } else {
+            lines.push(Line::Removed(old[i]));
+            i += 1;
+        }
+    }
+
+    // Show removed lines before the lines that replace them
+    let mut index = 0;
+    while index < lines.len() {
+        let end = lines[index..]
+            .iter()
+            .position(|l| matches!(l, Line::Same(_)))
+            .map_or(lines.len(), |p| index + p);
+        lines[index..end].sort_by_key(|l| matches!(l, Line::Added(_)));
+        index = end + 1;
+    }
+    lines
+}
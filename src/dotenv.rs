@@ -0,0 +1,199 @@
+//! Loading of environment variables from a `.env` file for local development.
+//!
+//! The file is looked for in the current directory and each of its parents in turn, and then in
+//! the home directory, and the first found is loaded. Its variables are added to the environment
+//! before the command line is parsed, so they are seen by arguments read from the environment, the
+//! language of the help and the secrets, but a variable that is already set keeps its value, as
+//! the environment of the process takes precedence over the file. The `--no-dotenv` flag turns
+//! the loading off.
+//!
+//! Each line of the file sets a variable, as `NAME=value` or `export NAME=value`. Blank lines and
+//! lines starting with `#` are ignored. A value may be quoted: within double quotes `\n`, `\"` and
+//! `\\` are replaced by a new line, a quote and a backslash, and within single quotes the value
+//! is taken as it is. An unquoted value ends at ` #`, which starts a comment.
+//!
+//! ```text
+//! # Local settings, not committed
+//! export TYG_TEMPLATE_TOKEN=s3cr3t
+//! GREETING="Hello\nWorld"
+//! ```
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::platform::long_path;
+use crate::{result_err_bare, Result};
+
+/// The name of the file.
+pub const FILE_NAME: &str = ".env";
+
+/// The `.env` file loaded and the variables it set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loaded {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The names of the variables set from the file, leaving out those already set.
+    pub set: Vec<String>,
+}
+
+/// Find the `.env` file in the directory or the nearest of its parents, or failing that in the
+/// home directory.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use tyg_template::dotenv;
+/// use tyg_template::testkit::TempProject;
+///
+/// let project = TempProject::builder().dir("src/bin").create().unwrap();
+/// fs::write(project.root().join(".env"), "GREETING=hello\n").unwrap();
+///
+/// assert_eq!(dotenv::find(&project.root().join("src/bin")), Some(project.root().join(".env")));
+/// ```
+pub fn find(directory: &Path) -> Option<PathBuf> {
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(FILE_NAME));
+    directory
+        .ancestors()
+        .map(|directory| directory.join(FILE_NAME))
+        .chain(home)
+        .find(|path| path.is_file())
+}
+
+/// Parse the text of a `.env` file into the names and values of its variables, in the order
+/// given. The name of the file is used in the error messages.
+///
+/// # Examples
+/// ```
+/// use tyg_template::{assert_err_msg, dotenv, Error};
+///
+/// let text = "# Settings\nexport NAME=value # comment\nQUOTED=\"two\\nlines\"\nRAW='a\\nb'\n";
+///
+/// assert_eq!(dotenv::parse(".env", text).unwrap(), [
+///     ("NAME".to_string(), "value".to_string()),
+///     ("QUOTED".to_string(), "two\nlines".to_string()),
+///     ("RAW".to_string(), "a\\nb".to_string()),
+/// ]);
+///
+/// assert_err_msg!(dotenv::parse(".env", "NAME\n"), Error::Error(_),
+///                 ".env:1: Expected NAME=value");
+/// assert_err_msg!(dotenv::parse(".env", "\nNAME=\"open\n"), Error::Error(_),
+///                 ".env:2: The closing \" of the value is missing");
+/// ```
+pub fn parse(name: &str, text: &str) -> Result<Vec<(String, String)>> {
+    let mut variables = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            return result_err_bare!("{}:{}: Expected NAME=value", name, number + 1);
+        };
+        let key = key.trim();
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        {
+            return result_err_bare!("{}:{}: Invalid variable name '{}'", name, number + 1, key);
+        }
+        let value = match unquote(value.trim()) {
+            Some(value) => value,
+            None => {
+                return result_err_bare!(
+                    "{}:{}: The closing {} of the value is missing",
+                    name,
+                    number + 1,
+                    &value.trim()[..1]
+                )
+            }
+        };
+        variables.push((key.to_string(), value));
+    }
+    Ok(variables)
+}
+
+// The value without its quotes or comment, or None if a closing quote is missing
+fn unquote(value: &str) -> Option<String> {
+    if let Some(rest) = value.strip_prefix('\'') {
+        return rest.find('\'').map(|end| rest[..end].to_string());
+    }
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut unquoted = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(unquoted),
+                '\\' => match chars.next() {
+                    Some('n') => unquoted.push('\n'),
+                    Some(c) => unquoted.push(c),
+                    None => return None,
+                },
+                c => unquoted.push(c),
+            }
+        }
+        return None;
+    }
+    let value = value.find(" #").map_or(value, |comment| &value[..comment]);
+    Some(value.trim_end().to_string())
+}
+
+/// Load the `.env` file found from the current directory, unless `--no-dotenv` is among the
+/// arguments, setting the variables that are not already set. The first argument is taken to be
+/// the name of the binary.
+///
+/// Returns the file loaded, or `None` if there is none or the loading is turned off.
+///
+/// # Examples
+/// ```
+/// use std::{env, fs};
+/// use std::ffi::OsString;
+/// use tyg_template::dotenv;
+/// use tyg_template::testkit::TempProject;
+///
+/// let project = TempProject::builder().create().unwrap();
+/// fs::write(project.root().join(".env"), "DOTENV_EXAMPLE=loaded\n").unwrap();
+///
+/// let args: Vec<OsString> = ["bin", "--no-dotenv", "fail"].iter().map(OsString::from).collect();
+/// assert_eq!(dotenv::load_from_args(&args).unwrap(), None);
+///
+/// let loaded = dotenv::load_from_args(&args[..1]).unwrap().unwrap();
+/// assert_eq!(loaded.set, ["DOTENV_EXAMPLE"]);
+/// assert_eq!(env::var("DOTENV_EXAMPLE").unwrap(), "loaded");
+/// ```
+pub fn load_from_args(args: &[OsString]) -> Result<Option<Loaded>> {
+    let disabled = args
+        .iter()
+        .skip(1)
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg == "--no-dotenv");
+    if disabled {
+        return Ok(None);
+    }
+    let Some(path) = find(&env::current_dir()?) else {
+        return Ok(None);
+    };
+    load(&path).map(Some)
+}
+
+/// Load the given `.env` file, setting the variables that are not already set.
+pub fn load(path: &Path) -> Result<Loaded> {
+    let text = fs::read_to_string(long_path(path))
+        .or_else(|e| result_err_bare!("{}: {}", path.display(), e))?;
+    let mut set = Vec::new();
+    for (key, value) in parse(&path.display().to_string(), &text)? {
+        if env::var_os(&key).is_none() {
+            env::set_var(&key, value);
+            set.push(key);
+        }
+    }
+    Ok(Loaded {
+        path: path.to_path_buf(),
+        set,
+    })
+}
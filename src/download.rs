@@ -0,0 +1,500 @@
+//! Downloads of files over HTTP, with progress reports, resumption and checksum verification.
+//!
+//! [`download`] writes the body of the response to `<dest>.part`, and renames it to the
+//! destination once it is complete and matches the expected checksum, so that the destination
+//! never holds part of a file. When a download fails part of the way through, the partial file is
+//! kept, and the next download of the same destination asks for the rest of the body with a
+//! `Range` header, starting again if the server does not support ranges. The request is only made
+//! when the `http` feature is enabled.
+//!
+//! The errors tell the kinds of failure apart, so that a caller can retry the ones worth retrying:
+//!
+//! | Failure                                     | Error                                       |
+//! |---------------------------------------------|---------------------------------------------|
+//! | The request or the reading of the body      | `Error::Http` or `Error::Network`           |
+//! | The writing of the file                     | `Error::File`, with the path in the message |
+//! | A checksum that does not match the expected | `Error::Verification`                       |
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::platform::long_path;
+use crate::progress::{self, ProgressFormat, Task};
+use crate::{result_err_bare, terminal, Error, Result};
+
+/// The progress of a download, passed to the progress function after each part of the body is
+/// written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of bytes written so far, including those of an earlier, partial download.
+    pub done: u64,
+    /// The size of the file, if the server gave it.
+    pub total: Option<u64>,
+    /// Whether the whole body has been written.
+    pub finished: bool,
+}
+
+/// A progress function that draws a bar on one line of the output stream, which is redrawn as the
+/// download advances, or when the output is [plain](crate::terminal::plain), e.g. on a dumb
+/// terminal or in CI, writes a status line at every tenth of the download instead. With the
+/// [JSON progress format](ProgressFormat::Json) the download is reported as a
+/// [task](progress_task) instead, and nothing is written to the stream.
+pub fn progress_bar(out: &mut dyn Write) -> Box<dyn FnMut(Progress) + '_> {
+    match ProgressFormat::selected() {
+        ProgressFormat::Human => Box::new(progress_bar_with(out, terminal::plain())),
+        ProgressFormat::Json => Box::new(progress_task("download")),
+    }
+}
+
+/// A progress function that registers the download as a task with the given name in the
+/// [progress registry](crate::progress), counting the bytes written as its items, until the whole
+/// body has been written.
+///
+/// # Examples
+/// ```
+/// use tyg_template::download::{progress_task, Progress};
+/// use tyg_template::progress;
+///
+/// let mut report = progress_task("download tool.tar.gz");
+/// report(Progress { done: 512, total: Some(1024), finished: false });
+///
+/// let state = progress::snapshot().into_iter().find(|s| s.name == "download tool.tar.gz").unwrap();
+/// assert_eq!((state.done, state.total), (512, Some(1024)));
+///
+/// report(Progress { done: 1024, total: Some(1024), finished: true });
+/// assert!(progress::snapshot().iter().all(|s| s.name != "download tool.tar.gz"));
+/// ```
+pub fn progress_task(name: impl fmt::Display) -> impl FnMut(Progress) {
+    let name = name.to_string();
+    let mut task: Option<(Task, u64)> = None;
+    move |progress: Progress| {
+        let (current, reported) =
+            task.get_or_insert_with(|| (progress::start(&name, progress.total), 0));
+        if let Some(total) = progress.total {
+            current.set_total(total);
+        }
+        current.advance(progress.done.saturating_sub(*reported));
+        *reported = progress.done;
+        if progress.finished {
+            task = None;
+        }
+    }
+}
+
+/// A progress function that draws a bar, or writes plain status lines if `plain` is true.
+///
+/// # Examples
+/// ```
+/// use tyg_template::download::{progress_bar_with, Progress};
+///
+/// let mut out = Vec::new();
+/// let mut bar = progress_bar_with(&mut out, false);
+/// bar(Progress { done: 512 * 1024, total: Some(1024 * 1024), finished: false });
+/// bar(Progress { done: 1024 * 1024, total: Some(1024 * 1024), finished: true });
+/// drop(bar);
+///
+/// assert_eq!(String::from_utf8(out).unwrap(),
+///            "\r[##########          ]  50%  0.5 of 1.0 MiB\r[####################] 100%  1.0 of 1.0 MiB\n");
+///
+/// let mut out = Vec::new();
+/// let mut bar = progress_bar_with(&mut out, true);
+/// bar(Progress { done: 512 * 1024, total: Some(1024 * 1024), finished: false });
+/// bar(Progress { done: 1024 * 1024, total: Some(1024 * 1024), finished: true });
+/// drop(bar);
+///
+/// assert_eq!(String::from_utf8(out).unwrap(), "Downloaded 50%\nDownloaded 100%\n");
+/// ```
+pub fn progress_bar_with(out: &mut dyn Write, plain: bool) -> impl FnMut(Progress) + '_ {
+    const WIDTH: u64 = 20;
+    let mut shown = None;
+    move |progress: Progress| {
+        let mebibytes = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+        let line = match progress.total {
+            Some(total) if total > 0 => {
+                let percent = (progress.done.min(total) * 100) / total;
+                // The bar is only redrawn when the percentage changes
+                let step = if plain { percent / 10 } else { percent };
+                if shown == Some(step) && !progress.finished {
+                    return;
+                }
+                shown = Some(step);
+                if plain {
+                    format!("Downloaded {}%", percent)
+                } else {
+                    let filled = (percent * WIDTH / 100) as usize;
+                    format!(
+                        "\r[{}{}] {:>3}%  {:.1} of {:.1} MiB",
+                        "#".repeat(filled),
+                        " ".repeat(WIDTH as usize - filled),
+                        percent,
+                        mebibytes(progress.done),
+                        mebibytes(total)
+                    )
+                }
+            }
+            _ => {
+                let tenths = progress.done / (1024 * 1024 / 10);
+                if shown == Some(tenths) && !progress.finished {
+                    return;
+                }
+                shown = Some(tenths);
+                match plain {
+                    true => format!("Downloaded {:.1} MiB", mebibytes(progress.done)),
+                    false => format!("\r{:.1} MiB", mebibytes(progress.done)),
+                }
+            }
+        };
+        let end = if plain || progress.finished { "\n" } else { "" };
+        let _ = write!(out, "{}{}", line, end);
+        let _ = out.flush();
+    }
+}
+
+/// A SHA-256 digest, given in hexadecimal, e.g. as published next to a release.
+///
+/// # Examples
+/// ```
+/// use tyg_template::download::Sha256;
+/// use tyg_template::{assert_err_msg, Error};
+///
+/// let empty: Sha256 = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855".parse().unwrap();
+///
+/// assert_eq!(Sha256::digest(b""), empty);
+/// assert_eq!(Sha256::digest(b"abc").to_string(),
+///            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+/// assert_err_msg!("e3b0".parse::<Sha256>(), Error::Error(_),
+///                 "e3b0: A SHA-256 checksum has 64 hexadecimal digits");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sha256([u8; 32]);
+
+impl Sha256 {
+    /// The digest of the data.
+    pub fn digest(data: &[u8]) -> Sha256 {
+        let mut hasher = Hasher::new();
+        hasher.update(data);
+        hasher.finish()
+    }
+
+    /// The digest of the contents of the file.
+    pub fn of_file(path: impl AsRef<Path>) -> Result<Sha256> {
+        let path = path.as_ref();
+        let mut file = File::open(long_path(path)).or_else(|e| disk_error(path, e))?;
+        let mut hasher = Hasher::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            match file.read(&mut buffer) {
+                Ok(0) => return Ok(hasher.finish()),
+                Ok(n) => hasher.update(&buffer[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return disk_error(path, e),
+            }
+        }
+    }
+}
+
+impl FromStr for Sha256 {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Sha256> {
+        let digits = text.as_bytes();
+        if digits.len() != 64 || !digits.iter().all(u8::is_ascii_hexdigit) {
+            return result_err_bare!("{}: A SHA-256 checksum has 64 hexadecimal digits", text);
+        }
+        let mut bytes = [0; 32];
+        for (byte, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+            let pair = std::str::from_utf8(pair).unwrap_or_default();
+            *byte = u8::from_str_radix(pair, 16).unwrap_or_default();
+        }
+        Ok(Sha256(bytes))
+    }
+}
+
+impl fmt::Display for Sha256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+/// The path of the partial file that a download of the destination is written to.
+///
+/// # Examples
+/// ```
+/// use std::path::Path;
+/// use tyg_template::download::partial_path;
+///
+/// assert_eq!(partial_path("tool.tar.gz"), Path::new("tool.tar.gz.part"));
+/// ```
+pub fn partial_path(dest: impl AsRef<Path>) -> PathBuf {
+    let mut path = dest.as_ref().as_os_str().to_owned();
+    path.push(".part");
+    PathBuf::from(path)
+}
+
+/// Download the URL to the destination, resuming an earlier download that did not finish, and
+/// check the checksum of the file if one is expected, returning the size of the file. The
+/// progress function is called as the body is written, e.g. with a [`progress_bar`].
+///
+/// A file whose checksum does not match is removed, so that the next download starts again. See
+/// the [module](self) for the errors.
+///
+/// # Examples
+/// ```no_run
+/// use tyg_template::download::{download, progress_bar};
+///
+/// let url = "https://example.com/tool.tar.gz".parse()?;
+/// let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".parse()?;
+/// let mut stderr = std::io::stderr();
+/// download(&url, "tool.tar.gz", Some(&sha256), &mut progress_bar(&mut stderr))?;
+/// # Ok::<(), tyg_template::Error>(())
+/// ```
+#[cfg(feature = "http")]
+pub fn download(
+    url: &crate::fetch::HttpUrl,
+    dest: impl AsRef<Path>,
+    sha256: Option<&Sha256>,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<u64> {
+    use std::fs::OpenOptions;
+
+    let dest = dest.as_ref();
+    let partial = partial_path(dest);
+    let mut done = fs::metadata(long_path(&partial)).map_or(0, |metadata| metadata.len());
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let mut request = agent.get(url.as_str());
+    if done > 0 {
+        request = request.header("Range", format!("bytes={}-", done));
+    }
+    let mut response = request.call()?;
+    let resumed = match response.status().as_u16() {
+        206 => true,
+        // The partial file is already the whole body
+        416 if done > 0 => {
+            drop(response);
+            response = agent.get(url.as_str()).call()?;
+            done = 0;
+            false
+        }
+        _ => {
+            done = 0;
+            false
+        }
+    };
+    let status = response.status().as_u16();
+    if status >= 400 {
+        return Err(ureq::Error::StatusCode(status).into());
+    }
+
+    let total = response.body().content_length().map(|length| length + done);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(long_path(&partial))
+        .or_else(|e| disk_error(&partial, e))?;
+    let mut reader = response.body_mut().as_reader();
+    let mut buffer = vec![0; 64 * 1024];
+    progress(Progress {
+        done,
+        total,
+        finished: false,
+    });
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                let message = format!("{}: {}", url, e);
+                return Err(Error::Network(io::Error::new(e.kind(), message)));
+            }
+        };
+        file.write_all(&buffer[..n])
+            .or_else(|e| disk_error(&partial, e))?;
+        done += n as u64;
+        progress(Progress {
+            done,
+            total,
+            finished: false,
+        });
+    }
+    file.sync_all().or_else(|e| disk_error(&partial, e))?;
+    drop(file);
+    progress(Progress {
+        done,
+        total,
+        finished: true,
+    });
+
+    verify(&partial, sha256)?;
+    fs::rename(long_path(&partial), long_path(dest)).or_else(|e| disk_error(dest, e))?;
+    Ok(done)
+}
+
+/// Download the URL, which fails as the application was built without the `http` feature.
+#[cfg(not(feature = "http"))]
+pub fn download(
+    url: &crate::fetch::HttpUrl,
+    _dest: impl AsRef<Path>,
+    _sha256: Option<&Sha256>,
+    _progress: &mut dyn FnMut(Progress),
+) -> Result<u64> {
+    result_err_bare!(
+        "{}: Downloads are not supported, rebuild the application with --features=http",
+        url
+    )
+}
+
+/// Check that the file has the expected checksum, if there is one, removing it if it does not.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use tyg_template::download::{verify, Sha256};
+/// use tyg_template::testkit::TempProject;
+/// use tyg_template::{assert_err_msg, Error};
+///
+/// let _project = TempProject::builder().file("tool.tar.gz", "abc").create().unwrap();
+/// let empty = Sha256::digest(b"");
+///
+/// assert!(verify("tool.tar.gz", Some(&Sha256::digest(b"abc"))).is_ok());
+/// assert_err_msg!(verify("tool.tar.gz", Some(&empty)), Error::Verification(_),
+///                 "tool.tar.gz: The SHA-256 checksum is ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad, \
+///                  expected e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+/// assert!(!fs::exists("tool.tar.gz").unwrap());
+/// ```
+pub fn verify(path: impl AsRef<Path>, sha256: Option<&Sha256>) -> Result<()> {
+    let path = path.as_ref();
+    let Some(expected) = sha256 else {
+        return Ok(());
+    };
+    let actual = Sha256::of_file(path)?;
+    if actual != *expected {
+        let _ = fs::remove_file(long_path(path));
+        return Err(Error::Verification(
+            format!(
+                "{}: The SHA-256 checksum is {}, expected {}",
+                path.display(),
+                actual,
+                expected
+            )
+            .into(),
+        ));
+    }
+    Ok(())
+}
+
+// A failure to read or write a file, keeping its kind and naming the path
+fn disk_error<T>(path: &Path, e: io::Error) -> Result<T> {
+    let message = format!("{}: {}", path.display(), e);
+    Err(Error::File(io::Error::new(e.kind(), message)))
+}
+
+// The round constants of SHA-256
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+// The SHA-256 hash of FIPS 180-4, which is small enough not to need a dependency
+struct Hasher {
+    state: [u32; 8],
+    block: [u8; 64],
+    filled: usize,
+    length: u64,
+}
+
+impl Hasher {
+    fn new() -> Hasher {
+        Hasher {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+                0x5be0cd19,
+            ],
+            block: [0; 64],
+            filled: 0,
+            length: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+        while !data.is_empty() {
+            let n = data.len().min(64 - self.filled);
+            self.block[self.filled..self.filled + n].copy_from_slice(&data[..n]);
+            self.filled += n;
+            data = &data[n..];
+            if self.filled == 64 {
+                self.compress();
+                self.filled = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Sha256 {
+        let bits = self.length.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.filled != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bits.to_be_bytes());
+        let mut digest = [0; 32];
+        for (bytes, word) in digest.chunks_mut(4).zip(self.state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        Sha256(digest)
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 64];
+        for (word, bytes) in w.iter_mut().zip(self.block.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (word, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+}
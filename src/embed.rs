@@ -0,0 +1,267 @@
+//! Embedding the subcommand engine in a host application, such as a graphical or daemon frontend.
+//!
+//! The host gives the arguments of a command line, as [`run_with_args`](crate::run_with_args)
+//! takes them, and receives the run as a sequence of [`Event`]s rather than as text on the
+//! standard streams and an exit of the process: the lines of output, the diagnostics, the warnings
+//! about the command line, the progress of the tasks and, last, the outcome with its exit code.
+//! [`spawn`] runs the command on a thread of its own and returns a channel receiving the events,
+//! and [`run`] passes them to a callback on the calling thread.
+//!
+//! ```no_run
+//! use tyg_template::embed::{self, Event};
+//!
+//! let events = embed::spawn(["tyg_template", "run-script", "nightly.tyg"]).unwrap();
+//! for event in events {
+//!     match event {
+//!         Event::Output(line) => println!("{}", line),
+//!         Event::Progress(states) => states.iter().for_each(|state| println!("[{}]", state)),
+//!         Event::Finished { exit_code, .. } => println!("Finished with {}", exit_code),
+//!         _ => {}
+//!     }
+//! }
+//! ```
+//!
+//! Only the subcommand is run, with the global options that apply to it, such as `--yes` or
+//! `--sandbox`, [entered](crate::globals) on the thread of the run. The concerns of the process
+//! that [`run`](crate::run) takes care of, such as the audit log, the summary, the resource limits
+//! and the check for a newer release, are left to the host, and the global options selecting them
+//! have no effect. The [progress registry](crate::progress) is shared by the whole process, so the
+//! progress events of a run also show the tasks of any other run at the same time.
+
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::globals::{self, Globals};
+use crate::outcome::{ExitCodes, Outcome};
+use crate::progress::{self, TaskState};
+use crate::{deprecation, multicall, Result, ResultExt, Streams};
+
+// The time between two checks of the progress of the tasks
+const POLL: Duration = Duration::from_millis(100);
+
+/// An event of a run of the engine embedded in a host application.
+#[derive(Debug)]
+pub enum Event {
+    /// A line written to the output stream, without its line ending.
+    Output(String),
+    /// A line written to the diagnostic stream, without its line ending.
+    Diagnostic(String),
+    /// A warning about the command line, such as the use of a deprecated option.
+    Warning(String),
+    /// The states of the tasks in progress, sent whenever an item, the number done or the tasks
+    /// change.
+    Progress(Vec<TaskState>),
+    /// The end of the run, with its outcome and the exit code the application would exit with. It
+    /// is always the last event.
+    Finished {
+        /// The outcome of the subcommand.
+        outcome: Outcome,
+        /// The exit code of the outcome for the subcommand.
+        exit_code: i32,
+    },
+}
+
+/// Run the command line with the given arguments on a thread of its own, returning the channel
+/// receiving its events. The first argument is taken to be the name of the binary, or of an
+/// [applet](crate::multicall). The channel is
+/// closed after the [`Finished`](Event::Finished) event, and the run stops writing output if the
+/// receiver is dropped.
+pub fn spawn<I, T>(args: I) -> Result<Receiver<Event>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let args = multicall::expand(args.into_iter().map(Into::into).collect(), crate::APPLETS);
+    let (events, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("embedded-run".to_string())
+        .spawn(move || session(&args, events))
+        .bare(|| "Cannot start the thread running the command")?;
+    Ok(receiver)
+}
+
+/// Run the command line with the given arguments, passing its events to the callback on the
+/// calling thread, and return the exit code of the run.
+///
+/// # Examples
+/// ```
+/// use tyg_template::embed::{self, Event};
+///
+/// let mut events = Vec::new();
+/// let exit_code = embed::run(["tyg_template", "fail", "--no-location"], |event| events.push(event)).unwrap();
+///
+/// assert_eq!(exit_code, 1);
+/// assert!(matches!(&events[0], Event::Warning(warning) if warning.contains("--bare")));
+/// assert!(matches!(events.last(), Some(Event::Finished { exit_code: 1, .. })));
+///
+/// let mut lines = Vec::new();
+/// let exit_code = embed::run(["tyg_template", "check", "Cargo.toml", "missing.txt"], |event| {
+///     if let Event::Output(line) = event {
+///         lines.push(line);
+///     }
+/// })
+/// .unwrap();
+///
+/// assert_eq!(exit_code, 1);
+/// assert_eq!(lines, ["missing.txt: Does not exist", "1 of 2 files failed the check"]);
+/// ```
+pub fn run<I, T>(args: I, mut on_event: impl FnMut(Event)) -> Result<i32>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let mut exit_code = 1;
+    for event in spawn(args)? {
+        if let Event::Finished {
+            exit_code: code, ..
+        } = event
+        {
+            exit_code = code;
+        }
+        on_event(event);
+    }
+    Ok(exit_code)
+}
+
+// Run the command line, sending its events, while the progress of the tasks is watched by another
+// thread
+fn session(args: &[OsString], events: Sender<Event>) {
+    let (stop, stopped) = mpsc::channel::<()>();
+    let watcher = {
+        let events = events.clone();
+        thread::Builder::new()
+            .name("embedded-progress".to_string())
+            .spawn(move || watch_progress(&stopped, &events))
+            .ok()
+    };
+    let mut subcommand = String::new();
+    let outcome = {
+        let mut streams = Streams::new(
+            Lines::new(events.clone(), Event::Output),
+            Lines::new(events.clone(), Event::Diagnostic),
+        );
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+            Outcome::from(engine(args, &mut streams, &events, &mut subcommand))
+        }))
+        .unwrap_or_else(|payload| Outcome::Err(crate::panicked(payload)));
+        match streams.out.flush() {
+            Err(e) if outcome.error().is_none() => Outcome::Err(e.into()),
+            _ => outcome,
+        }
+    };
+    // The last progress is sent before the run is finished
+    drop(stop);
+    if let Some(watcher) = watcher {
+        let _ = watcher.join();
+    }
+    let exit_code = outcome.exit_code_with(&ExitCodes::find(crate::EXIT_CODES, &subcommand));
+    let _ = events.send(Event::Finished { outcome, exit_code });
+}
+
+// Parse the command line and dispatch the subcommand with the global options of the command line
+// entered, as run_with_args does, sending the warnings as events
+fn engine(
+    args: &[OsString],
+    streams: &mut Streams,
+    events: &Sender<Event>,
+    subcommand: &mut String,
+) -> Result<Outcome> {
+    let Some(matches) = crate::try_matches(args, streams)? else {
+        return Ok(Outcome::Success);
+    };
+    let globals = Globals::from_matches(args, &matches, crate::EXIT_CODES)?;
+    let _entered = globals::enter(globals.into());
+    for warning in deprecation::warnings(args, crate::DEPRECATED) {
+        events
+            .send(Event::Warning(warning))
+            .map_err(|_| stopped())?;
+    }
+    *subcommand = matches.subcommand_name().unwrap_or_default().to_string();
+    crate::dispatch(&matches, streams)
+}
+
+// Send the states of the tasks in progress whenever they change, until told to stop
+fn watch_progress(stop: &Receiver<()>, events: &Sender<Event>) {
+    let mut last = Vec::new();
+    loop {
+        let stopping = !matches!(stop.recv_timeout(POLL), Err(RecvTimeoutError::Timeout));
+        let states = progress::snapshot();
+        if changed(&last, &states) {
+            if events.send(Event::Progress(states.clone())).is_err() {
+                return;
+            }
+            last = states;
+        }
+        if stopping {
+            return;
+        }
+    }
+}
+
+// Whether the tasks in progress have changed, other than in the time they have taken
+fn changed(last: &[TaskState], states: &[TaskState]) -> bool {
+    last.len() != states.len()
+        || last.iter().zip(states).any(|(last, state)| {
+            (&last.name, &last.item, last.done, last.total)
+                != (&state.name, &state.item, state.done, state.total)
+        })
+}
+
+// The error of a write after the host stopped receiving the events
+fn stopped() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "The host application stopped receiving the events",
+    )
+}
+
+// A stream sending each line written to it as an event, and any unfinished line when dropped
+struct Lines {
+    events: Sender<Event>,
+    event: fn(String) -> Event,
+    line: Vec<u8>,
+}
+
+impl Lines {
+    fn new(events: Sender<Event>, event: fn(String) -> Event) -> Lines {
+        Lines {
+            events,
+            event,
+            line: Vec::new(),
+        }
+    }
+
+    fn send(&self, line: &[u8]) -> io::Result<()> {
+        let line = String::from_utf8_lossy(line);
+        let line = line.strip_suffix('\r').unwrap_or(&line).to_string();
+        self.events.send((self.event)(line)).map_err(|_| stopped())
+    }
+}
+
+impl Write for Lines {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        while let Some(end) = self.line.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.line.drain(..=end).collect();
+            self.send(&line[..end])?;
+        }
+        Ok(buf.len())
+    }
+
+    // A line is only sent once it is finished, so that a record is never split
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Lines {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            let _ = self.send(&self.line);
+        }
+    }
+}
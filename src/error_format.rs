@@ -0,0 +1,133 @@
+//! The formats in which the main program reports errors.
+
+use crate::{accessibility, globals, plugin, Error, Location};
+
+/// The format in which errors are reported, selected with the `--error-format` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// The error message after the prefix, e.g. `tyg_template: src/lib.rs:10:5: Something failed`,
+    /// labelled `error:` after the prefix in the accessible mode.
+    #[default]
+    Text,
+    /// A GitHub Actions workflow command, which shows the error as an annotation of the source
+    /// file and line disclosed by the error.
+    Github,
+    /// A format provided by a [plugin](crate::plugin), given by its name.
+    Plugin(&'static str),
+}
+
+impl ErrorFormat {
+    /// The names of the formats of the application as given on the command line.
+    pub const NAMES: [&'static str; 2] = ["text", "github"];
+
+    /// The names of the formats as given on the command line, those of the application followed
+    /// by those of the [plugins](crate::plugin) registered.
+    pub fn names() -> Vec<&'static str> {
+        let plugins = plugin::plugins();
+        let formats = plugins.iter().flat_map(|plugin| plugin.error_formats());
+        Self::NAMES
+            .into_iter()
+            .chain(formats.map(|renderer| renderer.name))
+            .collect()
+    }
+
+    /// The format with the given name, or the text format if the name is unknown.
+    pub fn from_name(name: &str) -> ErrorFormat {
+        match name {
+            "github" => ErrorFormat::Github,
+            _ => match plugin::error_format(name) {
+                Some(renderer) => ErrorFormat::Plugin(renderer.name),
+                None => ErrorFormat::Text,
+            },
+        }
+    }
+
+    /// The format selected on the command line of the current run, as given by
+    /// [`globals::current`], or the text format if there is none.
+    pub fn selected() -> ErrorFormat {
+        globals::current().error_format
+    }
+
+    /// Render an error as an error report with the given prefix. The [scopes](crate::with_scope)
+    /// in which the error happened follow the message as a trail on a line of its own.
+    ///
+    /// # Examples
+    /// ```
+    /// use tyg_template::{Error, ErrorFormat};
+    ///
+    /// let error = Error::Error("src/lib.rs:10:5: Something failed\nbadly".to_string());
+    /// let scoped = Error::Error("Invalid port".to_string())
+    ///     .in_scopes(vec!["loading configuration".to_string(), "reading settings.toml".to_string()]);
+    ///
+    /// assert_eq!(ErrorFormat::Text.error("my_tool", &error),
+    ///            "my_tool: src/lib.rs:10:5: Something failed\nbadly");
+    /// assert_eq!(ErrorFormat::Github.error("my_tool", &error),
+    ///            "::error title=my_tool,file=src/lib.rs,line=10,col=5::Something failed%0Abadly");
+    /// assert_eq!(ErrorFormat::Text.error("my_tool", &scoped),
+    ///            "my_tool: Invalid port\n  while loading configuration > reading settings.toml");
+    /// ```
+    pub fn error(self, prefix: &str, error: &Error) -> String {
+        let mut message = error.to_string();
+        if !error.scopes().is_empty() {
+            message.push_str(&format!("\n  while {}", error.scopes().join(" > ")));
+        }
+        self.render("error", prefix, &message)
+    }
+
+    /// Render a warning message as a warning report with the given prefix. A location disclosed at
+    /// the start of the message is treated as it is for errors.
+    ///
+    /// # Examples
+    /// ```
+    /// use tyg_template::ErrorFormat;
+    ///
+    /// assert_eq!(ErrorFormat::Text.warning("my_tool", "Deprecated"), "my_tool: warning: Deprecated");
+    /// assert_eq!(ErrorFormat::Github.warning("my_tool", "Deprecated"),
+    ///            "::warning title=my_tool::Deprecated");
+    /// ```
+    pub fn warning(self, prefix: &str, message: &str) -> String {
+        self.render("warning", prefix, message)
+    }
+
+    fn render(self, level: &str, prefix: &str, message: &str) -> String {
+        match self {
+            // Screen reader users are told in words that this is an error
+            ErrorFormat::Text if level == "error" && !accessibility::enabled() => {
+                format!("{}: {}", prefix, message)
+            }
+            ErrorFormat::Text => format!("{}: {}: {}", prefix, level, message),
+            ErrorFormat::Github => {
+                let mut properties = format!("title={}", escape_property(prefix));
+                let message = match Location::split(message) {
+                    Some((location, rest)) => {
+                        properties.push_str(&format!(
+                            ",file={},line={},col={}",
+                            escape_property(location.file),
+                            location.line,
+                            location.column
+                        ));
+                        rest
+                    }
+                    None => message,
+                };
+                format!("::{} {}::{}", level, properties, escape_data(message))
+            }
+            ErrorFormat::Plugin(name) => match plugin::error_format(name) {
+                Some(renderer) => (renderer.render)(level, prefix, message),
+                None => ErrorFormat::Text.render(level, prefix, message),
+            },
+        }
+    }
+}
+
+// Escape the message of a workflow command so that it stays on one line
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+// Escape the value of a property of a workflow command, which is also delimited by : and ,
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
@@ -0,0 +1,141 @@
+//! The single path by which the application terminates.
+//!
+//! Work that must be done before the process ends, such as removing temporary files or flushing a
+//! log sink, is registered with [`on_exit`]. The handlers are run, and the standard output and
+//! error streams flushed, by [`exit`], which `main` calls in place of `process::exit` so that
+//! nothing buffered is lost when the application fails, and by the panic hook installed by
+//! [`install_panic_hook`], which `run` installs at start up. On Unix, `run` also installs a
+//! handler with [`terminate_on_signal`], so that the handlers are run when the process is
+//! interrupted or asked to stop, e.g. to roll back the changes of an open
+//! [`fsx::Journal`](crate::fsx::Journal). Any other way out of the process should call [`exit`]
+//! in the same way, or [`terminate`] from a thread other than the main thread.
+//!
+//! ```
+//! use std::sync::atomic::{AtomicBool, Ordering};
+//! use tyg_template::exit;
+//!
+//! static CLEANED: AtomicBool = AtomicBool::new(false);
+//!
+//! exit::on_exit(|| CLEANED.store(true, Ordering::SeqCst));
+//! exit::cleanup();
+//!
+//! assert!(CLEANED.load(Ordering::SeqCst));
+//! ```
+
+use std::io::{self, Write};
+use std::mem;
+use std::panic;
+use std::process;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+
+type Handler = Box<dyn FnOnce() + Send>;
+
+static HANDLERS: Mutex<Vec<Handler>> = Mutex::new(Vec::new());
+
+/// Register work to be done before the process ends. The handlers are run in the reverse order of
+/// their registration, so that something set up later is torn down first.
+pub fn on_exit(handler: impl FnOnce() + Send + 'static) {
+    handlers().push(Box::new(handler));
+}
+
+/// Run the registered handlers, each only once however often this is called, and flush the
+/// standard output and error streams.
+///
+/// # Examples
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use tyg_template::exit;
+///
+/// static ORDER: AtomicUsize = AtomicUsize::new(0);
+///
+/// exit::on_exit(|| assert_eq!(ORDER.fetch_add(1, Ordering::SeqCst), 1));
+/// exit::on_exit(|| assert_eq!(ORDER.fetch_add(1, Ordering::SeqCst), 0));
+/// exit::cleanup();
+/// exit::cleanup();
+///
+/// assert_eq!(ORDER.load(Ordering::SeqCst), 2);
+/// ```
+pub fn cleanup() {
+    run_handlers();
+    // There is nowhere left to report a failure to write the output
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+}
+
+/// Run the registered handlers, flush the standard output and error streams and terminate the
+/// process with the given exit code.
+///
+/// The destructors of the values still alive are not run, so any output stream of the caller
+/// that buffers its output must be flushed or dropped first.
+pub fn exit(code: i32) -> ! {
+    cleanup();
+    process::exit(code)
+}
+
+/// Run the registered handlers and terminate the process with the given exit code, from a thread
+/// other than the main thread, such as a watchdog that stops work which has run away.
+///
+/// The standard output stream is not flushed, as the main thread may hold its lock for as long
+/// as it runs, so output it has buffered is lost. Anything written to the standard output stream
+/// that is not locked is still flushed as the process ends.
+pub fn terminate(code: i32) -> ! {
+    run_handlers();
+    let _ = io::stderr().flush();
+    process::exit(code)
+}
+
+/// Terminate the process with [`terminate`] when it receives `SIGINT`, `SIGTERM` or `SIGHUP`, from a
+/// thread that waits for them, with the exit code 128 plus the number of the signal, as a shell
+/// reports a process killed by it.
+#[cfg(unix)]
+pub fn terminate_on_signal() -> crate::Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+
+    use crate::ResultExt;
+
+    let mut signals =
+        Signals::new([SIGINT, SIGTERM, SIGHUP]).bare(|| "Cannot handle the termination signals")?;
+    thread::Builder::new()
+        .name("exit-signals".to_string())
+        .spawn(move || {
+            if let Some(signal) = signals.forever().next() {
+                terminate(128 + signal);
+            }
+        })
+        .bare(|| "Cannot start the thread waiting for the termination signals")?;
+    Ok(())
+}
+
+/// Install a panic hook that runs the registered handlers after the panic has been reported by
+/// the hook installed before it. Only a panic of the main thread runs them, as the panic of
+/// another thread ends that thread alone and may be caught when it is joined.
+pub fn install_panic_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        if thread::current().name() == Some("main") {
+            cleanup();
+        }
+    }));
+}
+
+// Run the registered handlers, taking them before they are run, so that a handler may register
+// another
+fn run_handlers() {
+    loop {
+        let taken = mem::take(&mut *handlers());
+        if taken.is_empty() {
+            break;
+        }
+        for handler in taken.into_iter().rev() {
+            handler();
+        }
+    }
+}
+
+// The handlers, even if a handler panicked while they were locked
+fn handlers() -> MutexGuard<'static, Vec<Handler>> {
+    HANDLERS.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,106 @@
+//! Compatibility with applications that report errors through eyre.
+//!
+//! This module is available when the `eyre` feature is enabled. The template's macros and
+//! [`Error`] type are still used throughout the application, and the errors are only converted to
+//! an [`eyre::Report`] at its boundary. The `?` operator already converts an [`Error`] into a
+//! report, keeping its whole message and chain of sources. [`report`] additionally moves a
+//! disclosed location out of the message into a section of its own, which the color-eyre handler
+//! shows below the chain of sources.
+//!
+//! To use color-eyre in the main program, replace its body with a call to [`run_eyre`], leaving
+//! with the exit code of the outcome of a subcommand that did not fail:
+//!
+//! ```no_run
+//! fn main() -> eyre::Result<()> {
+//!     let outcome = tyg_template::eyre_compat::run_eyre()?;
+//!     tyg_template::exit::exit(outcome.exit_code())
+//! }
+//! ```
+
+use std::env;
+use std::error;
+use std::ffi::OsString;
+use std::fmt;
+
+use color_eyre::config::{HookBuilder, Theme};
+use color_eyre::{Section, SectionExt};
+use eyre::Report;
+
+use crate::outcome::Outcome;
+use crate::{accessibility, run, terminal, Error, Location};
+
+/// Process the command line as [`run`] does, after installing the color-eyre handler, and convert
+/// any error into an [`eyre::Report`] using [`report`].
+///
+/// The handler is installed without its own location section, which would only show where the
+/// report was created, in favour of the location disclosed by the error, and without colors when
+/// the output is [plain](terminal::plain). An error is returned if a handler has already been installed.
+pub fn run_eyre() -> eyre::Result<Outcome> {
+    let mut hook = HookBuilder::default().display_location_section(false);
+    let args: Vec<OsString> = env::args_os().collect();
+    if accessibility::requested(&args) || terminal::plain() {
+        hook = hook.theme(Theme::new());
+    }
+    hook.install()?;
+    run().map_err(report)
+}
+
+/// Convert an error into an [`eyre::Report`].
+///
+/// The chain of sources of the error is preserved. If the error discloses its location, the
+/// location is removed from the message and added to the report as a section headed `Location:`.
+///
+/// # Examples
+/// ```
+/// use tyg_template::{result_err, Result};
+/// use tyg_template::eyre_compat::report;
+///
+/// color_eyre::config::HookBuilder::default()
+///     .display_location_section(false)
+///     .install()
+///     .unwrap();
+///
+/// let result: Result<()> = result_err!("Something failed");
+/// let report = report(result.unwrap_err());
+///
+/// assert_eq!(report.to_string(), "Something failed");
+/// assert!(format!("{:?}", report).contains("Location:"));
+/// ```
+pub fn report(error: Error) -> Report {
+    let text = error.to_string();
+    match Location::split(&text) {
+        Some((location, message)) => {
+            let location = location.to_string();
+            Report::new(Located {
+                message: message.to_string(),
+                error,
+            })
+            .section(location.header("Location:"))
+        }
+        None => Report::new(error),
+    }
+}
+
+// An error whose disclosed location has been removed from its message
+struct Located {
+    message: String,
+    error: Error,
+}
+
+impl fmt::Debug for Located {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl fmt::Display for Located {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl error::Error for Located {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        error::Error::source(&self.error)
+    }
+}
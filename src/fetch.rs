@@ -0,0 +1,129 @@
+//! The `fetch` subcommand, which demonstrates the handling of network errors in the way that
+//! `file_fail` demonstrates the handling of filing system errors.
+//!
+//! The subcommand is always part of the command line, so that the help and completions are the
+//! same for every build, but the request is only made when the `http` feature is enabled, which
+//! adds a dependency on [ureq](https://docs.rs/ureq). A failed request is returned as an
+//! [`Error::Http`](crate::Error), whose message describes the DNS, connection, TLS, status or body
+//! failure.
+
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::units::ByteSize;
+use crate::{result_err_bare, Error, Result};
+
+/// An `http` or `https` URL with a host, e.g. `https://example.com/index.html`.
+///
+/// Only the form of the URL is checked, so that a malformed URL is rejected as the command line is
+/// parsed. The URL is displayed as it was given.
+///
+/// # Examples
+/// ```
+/// use tyg_template::fetch::HttpUrl;
+/// use tyg_template::{assert_err_msg, Error};
+///
+/// let url: HttpUrl = "https://example.com/index.html".parse().unwrap();
+/// assert_eq!(url.as_str(), "https://example.com/index.html");
+///
+/// assert_err_msg!("ftp://example.com".parse::<HttpUrl>(), Error::Error(_),
+///                 "ftp://example.com: Unsupported scheme 'ftp', use http or https");
+/// assert!("https:///index.html".parse::<HttpUrl>().is_err());
+/// assert!("example.com".parse::<HttpUrl>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpUrl(String);
+
+impl HttpUrl {
+    /// The URL as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for HttpUrl {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<HttpUrl> {
+        let Some((scheme, rest)) = text.split_once("://") else {
+            return result_err_bare!("{}: Not a URL, give e.g. https://example.com/", text);
+        };
+        if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+            return result_err_bare!(
+                "{}: Unsupported scheme '{}', use http or https",
+                text,
+                scheme
+            );
+        }
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        let host = authority.rsplit('@').next().unwrap_or_default();
+        if host.is_empty() || host.starts_with(':') {
+            return result_err_bare!("{}: The URL has no host", text);
+        }
+        if text.contains(char::is_whitespace) {
+            return result_err_bare!("{}: The URL contains spaces, encode them as %20", text);
+        }
+        Ok(HttpUrl(text.to_string()))
+    }
+}
+
+impl fmt::Display for HttpUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Fetch the URL and write the body of the response to the output stream.
+///
+/// The whole request, including reading the body, must finish within the timeout, and a body
+/// larger than the limit is an error. Any failure, including a response status of 400 or more, is
+/// returned as an [`Error::Http`](crate::Error).
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use tyg_template::assert_err;
+/// use tyg_template::fetch::{fetch, HttpUrl};
+/// use tyg_template::units::ByteSize;
+///
+/// // Nothing listens on the discard port, so the connection is refused
+/// let url: HttpUrl = "http://127.0.0.1:9/".parse().unwrap();
+/// let mut out = Vec::new();
+/// let result = fetch(&url, Duration::from_secs(5), ByteSize::new(1024), &mut out);
+///
+/// # #[cfg(feature = "http")]
+/// assert_err!(result, tyg_template::Error::Http(_));
+/// # #[cfg(not(feature = "http"))]
+/// assert_err!(result, tyg_template::Error::Error(_));
+/// ```
+#[cfg(feature = "http")]
+pub fn fetch(url: &HttpUrl, timeout: Duration, limit: ByteSize, out: &mut dyn Write) -> Result<()> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .build()
+        .into();
+    let mut response = agent.get(url.as_str()).call()?;
+    let body = response
+        .body_mut()
+        .with_config()
+        .limit(limit.bytes())
+        .read_to_vec()?;
+    out.write_all(&body)?;
+    Ok(())
+}
+
+/// Fetch the URL, which fails as the application was built without the `http` feature.
+#[cfg(not(feature = "http"))]
+pub fn fetch(
+    url: &HttpUrl,
+    _timeout: Duration,
+    _limit: ByteSize,
+    _out: &mut dyn Write,
+) -> Result<()> {
+    result_err_bare!(
+        "{}: HTTP requests are not supported by this build, rebuild it with --features=http",
+        url
+    )
+}
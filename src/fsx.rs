@@ -0,0 +1,542 @@
+//! Changes to the filing system that can be undone.
+//!
+//! A [`Journal`] makes changes such as writing, renaming and removing files, recording for each
+//! how to undo it, e.g. the backup of a file it overwrote. When the work succeeds the journal is
+//! committed, which forgets the changes and removes the backups. Otherwise the changes are rolled
+//! back, in the reverse order, leaving the files as they were:
+//!
+//! - when the journal is dropped without being committed, e.g. when a handler returns an error
+//!   with `?` and so [`run`](crate::run) fails;
+//! - when the application leaves by the [`exit`](crate::exit) module, e.g. after a panic or on a
+//!   signal such as `SIGINT` or `SIGTERM`, while a journal is still open.
+//!
+//! ```
+//! use std::fs;
+//! use tyg_template::fsx::Journal;
+//! use tyg_template::testkit::TempProject;
+//! use tyg_template::{result_err_bare, Result};
+//!
+//! let _project = TempProject::builder().file("notes.txt", "Original").create().unwrap();
+//!
+//! fn update() -> Result<()> {
+//!     let journal = Journal::new()?;
+//!     journal.write("notes.txt", "Changed")?;
+//!     journal.rename("notes.txt", "renamed.txt")?;
+//!     result_err_bare!("Failed before the journal was committed")
+//! }
+//!
+//! assert!(update().is_err());
+//! assert_eq!(fs::read_to_string("notes.txt").unwrap(), "Original");
+//! assert!(!fs::exists("renamed.txt").unwrap());
+//! ```
+//!
+//! Running against a machine that matters, the changes can be confined to some directories with
+//! the global `--sandbox <DIR>` option, given once for each directory. A journal then refuses a
+//! change to a path outside the [`Sandbox`] with an `Error::SandboxViolation` naming the path,
+//! and logs the change it would have made as a warning, as a dry run does:
+//!
+//! ```text
+//! tyg_template: warning: Would write /etc/app.toml, which is outside the sandbox
+//! tyg_template: /etc/app.toml: Outside the sandbox, the change was not made
+//! ```
+//!
+//! Every subcommand that changes files makes its changes through a journal, so that they are all
+//! confined. The paths are compared once their `.` and `..` components and the symbolic links of
+//! the part of them that exists are resolved, so a link inside the sandbox does not lead out of it.
+
+use std::env;
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Once, Weak};
+
+use crate::platform::long_path;
+use crate::prompt::Overrides;
+use crate::{exit, result_err_bare, Error, ErrorFormat, Result};
+
+// The number of journals opened, which names their backup directories
+static JOURNAL_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// The journals still open, in the order they were opened, which are rolled back if the application
+// leaves
+static OPEN: Mutex<Vec<Weak<Mutex<State>>>> = Mutex::new(Vec::new());
+
+// Registers the rollback of the open journals with the exit module, once for the process
+static ON_EXIT: Once = Once::new();
+
+/// The directories to which the changes made through a [`Journal`] are confined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sandbox {
+    // The directories, with their symbolic links resolved
+    roots: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// A sandbox confining the changes to the given directories. An error is returned if one of
+    /// them is not an existing directory.
+    pub fn new<I, P>(roots: I) -> Result<Sandbox>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut resolved = Vec::new();
+        for root in roots {
+            let root = root.as_ref();
+            match fs::canonicalize(long_path(root)) {
+                Ok(path) if path.is_dir() => resolved.push(path),
+                _ => {
+                    return result_err_bare!(
+                        kind = crate::ErrorKind::Usage,
+                        "--sandbox {}: Not an existing directory",
+                        root.display()
+                    )
+                }
+            }
+        }
+        Ok(Sandbox { roots: resolved })
+    }
+
+    /// Whether a change to the path is allowed, because it is inside one of the directories.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::path::Path;
+    /// use tyg_template::fsx::Sandbox;
+    /// use tyg_template::testkit::TempProject;
+    ///
+    /// let project = TempProject::builder().file("out/report.txt", "").create().unwrap();
+    /// let sandbox = Sandbox::new(["out"]).unwrap();
+    ///
+    /// assert!(sandbox.allows(&project.root().join("out/report.txt")));
+    /// assert!(sandbox.allows(&project.root().join("out/new/data.txt")));
+    /// assert!(!sandbox.allows(&project.root().join("out/../notes.txt")));
+    /// assert!(!sandbox.allows(Path::new("/etc/hosts")));
+    /// assert!(Sandbox::new(["missing"]).is_err());
+    /// ```
+    pub fn allows(&self, path: &Path) -> bool {
+        let path = match path.is_absolute() {
+            true => path.to_path_buf(),
+            false => match env::current_dir() {
+                Ok(dir) => dir.join(path),
+                Err(_) => return false,
+            },
+        };
+        let path = resolve(&path);
+        self.roots.iter().any(|root| path.starts_with(root))
+    }
+
+    /// Whether every directory of the sandbox is inside the other sandbox.
+    pub(crate) fn is_within(&self, other: &Sandbox) -> bool {
+        self.roots.iter().all(|root| other.allows(root))
+    }
+
+    /// The sandbox selected on the command line of the current run, as given by
+    /// [`globals::current`](crate::globals::current), if any.
+    pub fn selected() -> Option<Sandbox> {
+        crate::globals::current().sandbox.clone()
+    }
+}
+
+// How to undo a change
+#[derive(Debug)]
+enum Inverse {
+    // Remove the file or empty directory created
+    Remove(PathBuf),
+    // Put back the file overwritten or removed from its backup
+    Restore { backup: PathBuf, path: PathBuf },
+    // Rename the file back
+    Rename { from: PathBuf, to: PathBuf },
+}
+
+#[derive(Debug)]
+struct State {
+    inverses: Vec<Inverse>,
+    backups: PathBuf,
+    finished: bool,
+    sandbox: Option<Sandbox>,
+}
+
+/// A journal of changes to the filing system, which are undone unless it is committed.
+///
+/// Relative paths are taken relative to the current directory at the time of each change. The
+/// changes should only be made through the journal while it is open, as it cannot undo changes
+/// made to the same files by other means.
+#[derive(Debug)]
+pub struct Journal {
+    state: Arc<Mutex<State>>,
+}
+
+impl Journal {
+    /// Open a journal, confined to the [sandbox](Sandbox::selected) selected on the command line,
+    /// if any.
+    ///
+    /// The journals still open when the application leaves by the [`exit`](crate::exit) module are
+    /// rolled back by a single handler, which a journal leaves once it is committed, rolled back
+    /// or dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::fs;
+    /// use tyg_template::exit;
+    /// use tyg_template::fsx::Journal;
+    /// use tyg_template::testkit::TempProject;
+    ///
+    /// let _project = TempProject::builder().file("notes.txt", "Original").create().unwrap();
+    ///
+    /// for _ in 0..3 {
+    ///     Journal::new().unwrap().commit().unwrap();
+    /// }
+    /// let journal = Journal::new().unwrap();
+    /// journal.write("notes.txt", "Changed").unwrap();
+    /// exit::cleanup();
+    ///
+    /// assert_eq!(fs::read_to_string("notes.txt").unwrap(), "Original");
+    /// ```
+    pub fn new() -> Result<Journal> {
+        Journal::open(Sandbox::selected())
+    }
+
+    /// Open a journal confined to the given sandbox.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::fs;
+    /// use tyg_template::fsx::{Journal, Sandbox};
+    /// use tyg_template::testkit::TempProject;
+    /// use tyg_template::Error;
+    ///
+    /// let _project = TempProject::builder().file("out/report.txt", "Old").create().unwrap();
+    ///
+    /// let journal = Journal::in_sandbox(Sandbox::new(["out"]).unwrap()).unwrap();
+    /// journal.write("out/report.txt", "New").unwrap();
+    ///
+    /// assert!(matches!(journal.write("notes.txt", "Outside"), Err(Error::SandboxViolation { .. })));
+    /// assert!(matches!(journal.rename("out/report.txt", "report.txt"), Err(Error::SandboxViolation { .. })));
+    /// journal.commit().unwrap();
+    ///
+    /// assert_eq!(fs::read_to_string("out/report.txt").unwrap(), "New");
+    /// assert!(!fs::exists("notes.txt").unwrap());
+    /// ```
+    pub fn in_sandbox(sandbox: Sandbox) -> Result<Journal> {
+        Journal::open(Some(sandbox))
+    }
+
+    fn open(sandbox: Option<Sandbox>) -> Result<Journal> {
+        let backups = env::temp_dir().join(format!(
+            "{}-journal-{}-{}",
+            env!("CARGO_PKG_NAME"),
+            process::id(),
+            JOURNAL_COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let state = Arc::new(Mutex::new(State {
+            inverses: Vec::new(),
+            backups,
+            finished: false,
+            sandbox,
+        }));
+        // Roll back if the application leaves while the journal is still open
+        ON_EXIT.call_once(|| exit::on_exit(rollback_open));
+        open_journals().push(Arc::downgrade(&state));
+        Ok(Journal { state })
+    }
+
+    /// Write the contents to a file, creating it or replacing what it held.
+    pub fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+        let path = absolute(path.as_ref())?;
+        let mut state = lock(&self.state);
+        confine(&state, &path, || format!("write {}", path.display()))?;
+        let inverse = match backup(&mut state, &path)? {
+            Some(backup) => Inverse::Restore {
+                backup,
+                path: path.clone(),
+            },
+            None => Inverse::Remove(path.clone()),
+        };
+        fs::write(long_path(&path), contents)
+            .or_else(|e| result_err_bare!("{}: {}", path.display(), e))?;
+        state.inverses.push(inverse);
+        Ok(())
+    }
+
+    /// Rename a file or directory. A file that already has the new name is only replaced when
+    /// `--force` is given, and is put back if the rename is undone.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::fs;
+    /// use std::sync::Arc;
+    /// use tyg_template::fsx::Journal;
+    /// use tyg_template::globals::{self, Globals};
+    /// use tyg_template::prompt::Overrides;
+    /// use tyg_template::testkit::TempProject;
+    ///
+    /// let _project = TempProject::builder().file("a.txt", "A").file("b.txt", "B").create().unwrap();
+    ///
+    /// let journal = Journal::new().unwrap();
+    /// assert!(journal.rename("a.txt", "b.txt").is_err());
+    /// {
+    ///     let overrides = Overrides { yes: false, force: true };
+    ///     let _entered = globals::enter(Arc::new(Globals { overrides, ..Globals::default() }));
+    ///     journal.rename("a.txt", "b.txt").unwrap();
+    /// }
+    /// assert_eq!(fs::read_to_string("b.txt").unwrap(), "A");
+    /// journal.rollback().unwrap();
+    ///
+    /// assert_eq!(fs::read_to_string("a.txt").unwrap(), "A");
+    /// assert_eq!(fs::read_to_string("b.txt").unwrap(), "B");
+    /// ```
+    pub fn rename(&self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<()> {
+        let (from, to) = (absolute(from.as_ref())?, absolute(to.as_ref())?);
+        let mut state = lock(&self.state);
+        for path in [&from, &to] {
+            confine(&state, path, || {
+                format!("rename {} to {}", from.display(), to.display())
+            })?;
+        }
+        Overrides::selected().check_absent(&to)?;
+        // A file replaced by the rename is kept so that it can be put back
+        let replaced = match to.is_file() {
+            true => backup(&mut state, &to)?,
+            false => None,
+        };
+        fs::rename(long_path(&from), long_path(&to)).or_else(|e| {
+            result_err_bare!(
+                "{}: Cannot rename to {}: {}",
+                from.display(),
+                to.display(),
+                e
+            )
+        })?;
+        // Undone in the reverse order, so the file is renamed back before the replaced file is put
+        // back in its place
+        if let Some(backup) = replaced {
+            state.inverses.push(Inverse::Restore {
+                backup,
+                path: to.clone(),
+            });
+        }
+        state.inverses.push(Inverse::Rename { from: to, to: from });
+        Ok(())
+    }
+
+    /// Remove a file.
+    pub fn remove_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = absolute(path.as_ref())?;
+        let mut state = lock(&self.state);
+        confine(&state, &path, || format!("remove {}", path.display()))?;
+        let Some(backup) = backup(&mut state, &path)? else {
+            return result_err_bare!("{}: No such file to remove", path.display());
+        };
+        fs::remove_file(long_path(&path))
+            .or_else(|e| result_err_bare!("{}: {}", path.display(), e))?;
+        state.inverses.push(Inverse::Restore { backup, path });
+        Ok(())
+    }
+
+    /// Create a directory and any of its parents that are missing.
+    pub fn create_dir_all(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = absolute(path.as_ref())?;
+        let mut state = lock(&self.state);
+        let missing: Vec<&Path> = path.ancestors().take_while(|dir| !dir.exists()).collect();
+        if let Some(outermost) = missing.last() {
+            confine(&state, outermost, || {
+                format!("create the directory {}", path.display())
+            })?;
+        }
+        for dir in missing.into_iter().rev() {
+            fs::create_dir(long_path(dir))
+                .or_else(|e| result_err_bare!("{}: {}", dir.display(), e))?;
+            state.inverses.push(Inverse::Remove(dir.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    /// Keep the changes, removing the backups.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::fs;
+    /// use tyg_template::fsx::Journal;
+    /// use tyg_template::testkit::TempProject;
+    ///
+    /// let _project = TempProject::builder().file("old.txt", "Old").create().unwrap();
+    ///
+    /// let journal = Journal::new().unwrap();
+    /// journal.create_dir_all("out/data").unwrap();
+    /// journal.write("out/data/new.txt", "New").unwrap();
+    /// journal.remove_file("old.txt").unwrap();
+    /// journal.commit().unwrap();
+    ///
+    /// assert_eq!(fs::read_to_string("out/data/new.txt").unwrap(), "New");
+    /// assert!(!fs::exists("old.txt").unwrap());
+    /// ```
+    pub fn commit(self) -> Result<()> {
+        let mut state = lock(&self.state);
+        state.finished = true;
+        state.inverses.clear();
+        remove_backups(&state)
+    }
+
+    /// Undo the changes, in the reverse order to that in which they were made.
+    ///
+    /// Every change is undone that can be, and the first failure to undo one is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::fs;
+    /// use tyg_template::fsx::Journal;
+    /// use tyg_template::testkit::TempProject;
+    ///
+    /// let _project = TempProject::builder().file("old.txt", "Old").create().unwrap();
+    ///
+    /// let journal = Journal::new().unwrap();
+    /// journal.create_dir_all("out/data").unwrap();
+    /// journal.write("out/data/new.txt", "New").unwrap();
+    /// journal.remove_file("old.txt").unwrap();
+    /// journal.rollback().unwrap();
+    ///
+    /// assert!(!fs::exists("out").unwrap());
+    /// assert_eq!(fs::read_to_string("old.txt").unwrap(), "Old");
+    /// ```
+    pub fn rollback(self) -> Result<()> {
+        rollback(&mut lock(&self.state))
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        let _ = rollback(&mut lock(&self.state));
+        let state = Arc::as_ptr(&self.state);
+        open_journals().retain(|open| open.as_ptr() != state);
+    }
+}
+
+// The state, even if a panic happened while it was locked
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// The journals still open, even if a panic happened while they were locked
+fn open_journals() -> MutexGuard<'static, Vec<Weak<Mutex<State>>>> {
+    OPEN.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Roll back the journals still open, the last opened first, as the application leaves
+fn rollback_open() {
+    let open = std::mem::take(&mut *open_journals());
+    for state in open.iter().rev().filter_map(Weak::upgrade) {
+        let _ = rollback(&mut lock(&state));
+    }
+}
+
+// Refuse a change to a path outside the sandbox of the journal, logging the change that would have
+// been made as a warning in the error format of the run
+fn confine(state: &State, path: &Path, change: impl FnOnce() -> String) -> Result<()> {
+    match &state.sandbox {
+        Some(sandbox) if !sandbox.allows(path) => {
+            warn(&format!("Would {}, which is outside the sandbox", change()));
+            Err(Error::SandboxViolation { path: path.into() })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn warn(message: &str) {
+    let _ = writeln!(
+        io::stderr(),
+        "{}",
+        ErrorFormat::selected().warning(env!("CARGO_PKG_NAME"), message)
+    );
+}
+
+// The absolute path with its . and .. components resolved and, in the longest part of it that
+// exists, its symbolic links
+fn resolve(path: &Path) -> PathBuf {
+    let mut lexical = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                lexical.pop();
+            }
+            component => lexical.push(component),
+        }
+    }
+    let mut existing = lexical.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(resolved) = fs::canonicalize(long_path(existing)) {
+            return rest
+                .iter()
+                .rev()
+                .fold(resolved, |path, name| path.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return lexical,
+        }
+    }
+}
+
+fn absolute(path: &Path) -> Result<PathBuf> {
+    match path.is_absolute() {
+        true => Ok(path.to_path_buf()),
+        false => Ok(env::current_dir()?.join(path)),
+    }
+}
+
+// Copy the file to the backup directory, returning the backup, or None if there is no such file
+fn backup(state: &mut State, path: &Path) -> Result<Option<PathBuf>> {
+    match fs::metadata(long_path(path)) {
+        Ok(metadata) if metadata.is_file() => {}
+        Ok(_) => return result_err_bare!("{}: Not a file", path.display()),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return result_err_bare!("{}: {}", path.display(), e),
+    }
+    fs::create_dir_all(long_path(&state.backups))
+        .or_else(|e| result_err_bare!("{}: {}", state.backups.display(), e))?;
+    let backup = state.backups.join(state.inverses.len().to_string());
+    fs::copy(long_path(path), long_path(&backup))
+        .or_else(|e| result_err_bare!("{}: Cannot back up the file: {}", path.display(), e))?;
+    Ok(Some(backup))
+}
+
+fn rollback(state: &mut State) -> Result<()> {
+    if state.finished {
+        return Ok(());
+    }
+    state.finished = true;
+    let mut result = Ok(());
+    while let Some(inverse) = state.inverses.pop() {
+        let (path, undone) = match &inverse {
+            Inverse::Remove(path) if path.is_dir() => (path, fs::remove_dir(long_path(path))),
+            Inverse::Remove(path) => (path, fs::remove_file(long_path(path))),
+            Inverse::Restore { backup, path } => (
+                path,
+                fs::copy(long_path(backup), long_path(path)).map(|_| ()),
+            ),
+            Inverse::Rename { from, to } => (from, fs::rename(long_path(from), long_path(to))),
+        };
+        if let (Err(e), Ok(())) = (undone, &result) {
+            result = result_err_bare!("{}: Cannot undo the change: {}", path.display(), e);
+        }
+    }
+    // The backups are only removed once every change has been undone
+    match result {
+        Ok(()) => remove_backups(state),
+        Err(e) => Err(e),
+    }
+}
+
+fn remove_backups(state: &State) -> Result<()> {
+    match fs::remove_dir_all(long_path(&state.backups)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => result_err_bare!("{}: {}", state.backups.display(), e),
+    }
+}
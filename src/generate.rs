@@ -0,0 +1,262 @@
+//! Generation of code in a project created from the template.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::diff;
+use crate::fsx::Journal;
+use crate::platform::long_path;
+use crate::{result_err_bare, Result};
+
+// The location of the error handler relative to the root of the project
+const ERROR_FILE: &str = "tyg_errors/src/lib.rs";
+
+// A run of lines in the error handler that lists each variant of the error enumeration
+struct Section {
+    // A description of the section for use in messages
+    what: &'static str,
+    // The index of the first line after the opening line of the section
+    start: usize,
+    // The index of the closing line of the section
+    end: usize,
+    // The names of the variants listed in the section
+    variants: Vec<String>,
+}
+
+/// Add a variant wrapping another error type to the error enumeration of a project.
+///
+/// The variant is added to the `Error` enum in `tyg_errors/src/lib.rs` below the given root directory,
+/// together with its arm in the `formatter!` macro, its arm in `source()` and an implementation of
+/// `From` for the wrapped type, so that the `?` operator converts the wrapped errors. The wrapped
+/// type must implement `std::error::Error`.
+///
+/// Nothing is changed and an error is returned if the variant already exists, if the wrapped type
+/// is already converted, or if the file has diverged from the shape the generator expects. When
+/// the variants listed by the enum, the macro and `source()` disagree, the error shows the
+/// difference as a diff.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use tyg_template::generate::error_variant;
+/// use tyg_template::testkit::TempProject;
+///
+/// let handler = fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/tyg_errors/src/lib.rs"))
+///     .unwrap();
+/// let project = TempProject::builder()
+///     .file("tyg_errors/src/lib.rs", handler)
+///     .create()
+///     .unwrap();
+///
+/// let mut out = Vec::new();
+/// error_variant(project.root(), "Parse", "std::num::ParseIntError", &mut out).unwrap();
+///
+/// let handler = fs::read_to_string("tyg_errors/src/lib.rs").unwrap();
+/// assert!(handler.contains("    Parse(std::num::ParseIntError),"));
+/// assert!(handler.contains("Error::Parse(ref e) => fmt::Display::fmt(e, $f),"));
+/// assert!(handler.contains("Error::Parse(ref e) => Some(e),"));
+/// assert!(handler.contains("impl From<std::num::ParseIntError> for Error {"));
+///
+/// // The same variant cannot be added twice
+/// assert!(error_variant(project.root(), "Parse", "std::num::TryFromIntError", &mut out).is_err());
+/// ```
+pub fn error_variant(root: &Path, name: &str, wraps: &str, out: &mut dyn Write) -> Result<()> {
+    if !(name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric()))
+    {
+        return result_err_bare!(
+            "{}: Not a valid variant name, use letters and digits starting with a capital letter",
+            name
+        );
+    }
+    if wraps.is_empty()
+        || !wraps
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_:<>".contains(c))
+    {
+        return result_err_bare!("{}: Not a valid type name", wraps);
+    }
+
+    let path = root.join(ERROR_FILE);
+    let text = fs::read_to_string(long_path(&path))
+        .or_else(|e| result_err_bare!("{}: {}", path.display(), e))?;
+    let lines: Vec<&str> = text.lines().collect();
+
+    let enumeration = find_section(
+        &lines,
+        "the Error enum",
+        "pub enum Error {",
+        "pub enum Error {",
+    )?;
+    let formatter = find_section(
+        &lines,
+        "the formatter macro",
+        "macro_rules! formatter {",
+        "match *$self {",
+    )?;
+    let source = find_section(
+        &lines,
+        "the source function",
+        "fn source(&self)",
+        "match *self {",
+    )?;
+    for section in [&formatter, &source] {
+        if section.variants != enumeration.variants {
+            return result_err_bare!(
+                "{}: The variants in {} differ from those in {}, update the file by hand\n{}",
+                ERROR_FILE,
+                section.what,
+                enumeration.what,
+                diff::unified(
+                    enumeration.what,
+                    section.what,
+                    &enumeration.variants.join("\n"),
+                    &section.variants.join("\n")
+                )
+                .trim_end()
+            );
+        }
+    }
+    if enumeration.variants.iter().any(|v| v == name) {
+        return result_err_bare!("{}: The {} variant already exists", ERROR_FILE, name);
+    }
+    if text.contains(&format!("impl From<{}> for Error", wraps)) {
+        return result_err_bare!("{}: {} is already converted to an error", ERROR_FILE, wraps);
+    }
+
+    // Insert from the end of the file so that the earlier line indices remain valid
+    let mut insertions = vec![
+        (
+            enumeration.end,
+            vec![
+                format!(
+                    "{}/// Error of type `{}`.",
+                    indent(&lines, &enumeration),
+                    wraps
+                ),
+                format!("{}{}({}),", indent(&lines, &enumeration), name, wraps),
+            ],
+        ),
+        (
+            formatter.end,
+            vec![format!(
+                "{}Error::{}(ref e) => fmt::Display::fmt(e, $f),",
+                indent(&lines, &formatter),
+                name
+            )],
+        ),
+        (
+            source.end,
+            vec![format!(
+                "{}Error::{}(ref e) => Some(e),",
+                indent(&lines, &source),
+                name
+            )],
+        ),
+    ];
+    insertions.sort_by_key(|(index, _)| std::cmp::Reverse(*index));
+    let mut lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+    for (index, new_lines) in insertions {
+        lines.splice(index..index, new_lines);
+    }
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    lines.extend([
+        String::new(),
+        format!("impl From<{}> for Error {{", wraps),
+        format!("    fn from(err: {}) -> Error {{", wraps),
+        format!("        Error::{}(err)", name),
+        "    }".to_string(),
+        "}".to_string(),
+    ]);
+    let journal = Journal::new()?;
+    journal.write(&path, lines.join("\n") + "\n")?;
+    journal.commit()?;
+
+    writeln!(
+        out,
+        "Added the {} variant wrapping {} to {}",
+        name, wraps, ERROR_FILE
+    )?;
+    let krate = wraps.split("::").next().unwrap_or_default();
+    if wraps.contains("::") && !["std", "core", "alloc", "crate"].contains(&krate) {
+        writeln!(
+            out,
+            "Remember to add the {} crate to the dependencies in Cargo.toml",
+            krate
+        )?;
+    }
+    Ok(())
+}
+
+// Find the section that starts with the opening line after the given anchor, and list its variants
+fn find_section(
+    lines: &[&str],
+    what: &'static str,
+    anchor: &str,
+    opening: &str,
+) -> Result<Section> {
+    let diverged = || {
+        result_err_bare!(
+            "{}: Cannot find {}, the file has diverged from the shape expected by the generator",
+            ERROR_FILE,
+            what
+        )
+    };
+    let Some(anchor) = lines.iter().position(|l| l.contains(anchor)) else {
+        return diverged();
+    };
+    let Some(opening) = lines[anchor..].iter().position(|l| l.contains(opening)) else {
+        return diverged();
+    };
+    let start = anchor + opening + 1;
+    let Some(length) = lines[start..].iter().position(|l| l.trim() == "}") else {
+        return diverged();
+    };
+    let end = start + length;
+
+    // A variant or arm spread over several lines, such as a variant with named fields, continues
+    // on lines indented further than the first
+    let entries: Vec<&str> = lines[start..end]
+        .iter()
+        .copied()
+        .filter(|l| {
+            let l = l.trim();
+            !l.is_empty() && !l.starts_with("//") && !l.starts_with("#[")
+        })
+        .collect();
+    let base = entries
+        .first()
+        .map_or(0, |l| l.len() - l.trim_start().len());
+    let variants = entries
+        .iter()
+        .filter(|l| l.len() - l.trim_start().len() == base)
+        .map(|l| {
+            let l = l.trim();
+            let l = l.strip_prefix("Error::").unwrap_or(l);
+            l[..l
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(l.len())]
+                .to_string()
+        })
+        .filter(|name| !name.is_empty())
+        .collect();
+    Ok(Section {
+        what,
+        start,
+        end,
+        variants,
+    })
+}
+
+// The indentation of the last line of the section, or of its opening line if it is empty
+fn indent(lines: &[&str], section: &Section) -> String {
+    let line = lines[section.start..section.end]
+        .iter()
+        .rev()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or(&lines[section.start - 1]);
+    line[..line.len() - line.trim_start().len()].to_string()
+}
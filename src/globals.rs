@@ -0,0 +1,171 @@
+//! The global options of a run, such as the error format or `--yes`, which the modules read where
+//! they need them rather than having them passed down from the dispatcher.
+//!
+//! [`run`](crate::run) selects the options of its command line for the whole process. A run
+//! within the process, by [`run_with_args`](crate::run_with_args) or the [embedded](crate::embed)
+//! engine, [enters](enter) the options of its command line on the thread it runs on until it
+//! finishes, so that runs on different threads, such as tests run in parallel, each see their own.
+//! Work that a run hands to another thread sees the options selected for the process, or the
+//! defaults.
+//!
+//! ```
+//! use std::sync::Arc;
+//! use tyg_template::globals::{self, Globals};
+//! use tyg_template::prompt::Overrides;
+//!
+//! assert!(!Overrides::selected().force);
+//! {
+//!     let overrides = Overrides { yes: false, force: true };
+//!     let _entered = globals::enter(Arc::new(Globals { overrides, ..Globals::default() }));
+//!     assert!(Overrides::selected().force);
+//! }
+//! assert!(!Overrides::selected().force);
+//! ```
+
+use std::cell::RefCell;
+use std::ffi::OsString;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+
+use crate::fsx::Sandbox;
+use crate::outcome::ExitCodes;
+use crate::progress::ProgressFormat;
+use crate::prompt::Overrides;
+use crate::{accessibility, result_err_bare, ErrorFormat, Result};
+
+// The options selected for the process by run
+static PROCESS: OnceLock<Arc<Globals>> = OnceLock::new();
+
+thread_local! {
+    // The options entered on this thread by a run within the process
+    static THREAD: RefCell<Option<Arc<Globals>>> = const { RefCell::new(None) };
+}
+
+/// The global options of a run.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Globals {
+    /// The format of the error reports, given by `--error-format`.
+    pub error_format: ErrorFormat,
+    /// The format of the progress events, given by `--progress-format`.
+    pub progress_format: ProgressFormat,
+    /// The overrides of prompts and safety checks, given by `--yes` and `--force`.
+    pub overrides: Overrides,
+    /// Whether the failures of a batch are recovered interactively, given by `--interactive`.
+    pub interactive: bool,
+    /// Whether the accessible mode is turned on, by `--accessible` or the environment.
+    pub accessible: bool,
+    /// The directories to which changes are confined, given by `--sandbox`.
+    pub sandbox: Option<Sandbox>,
+    /// The exit codes of the subcommand.
+    pub exit_codes: ExitCodes,
+}
+
+impl Globals {
+    /// The options of a parsed command line, with the table of the exit codes of the subcommands.
+    /// An error is returned if an option is invalid, e.g. a sandbox that is not a directory.
+    pub(crate) fn from_matches(
+        args: &[OsString],
+        matches: &ArgMatches,
+        exit_codes: &[ExitCodes],
+    ) -> Result<Globals> {
+        let name = |id: &str| matches.get_one::<String>(id).map(String::as_str);
+        let sandbox = match matches.get_many::<PathBuf>("sandbox") {
+            Some(roots) => Some(Sandbox::new(roots)?),
+            None => None,
+        };
+        Ok(Globals {
+            error_format: name("error-format")
+                .map_or_else(ErrorFormat::default, ErrorFormat::from_name),
+            progress_format: name("progress-format")
+                .map_or_else(ProgressFormat::default, ProgressFormat::from_name),
+            overrides: Overrides {
+                yes: matches.get_flag("yes"),
+                force: matches.get_flag("force"),
+            },
+            interactive: matches.get_flag("interactive"),
+            accessible: accessibility::requested(args),
+            sandbox,
+            exit_codes: ExitCodes::find(exit_codes, matches.subcommand_name().unwrap_or_default()),
+        })
+    }
+
+    /// The options of a command line run within a run with these options, such as a line of a
+    /// script. The flags given on either command line apply and the formats given on the inner one
+    /// replace these, while the exit codes stay those of the outer run. A sandbox given on the
+    /// inner command line must lie within the sandbox of the outer run, if it has one.
+    pub(crate) fn within(&self, args: &[OsString], matches: &ArgMatches) -> Result<Globals> {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let inner = Globals::from_matches(args, matches, &[])?;
+        let sandbox = match (&self.sandbox, inner.sandbox) {
+            (Some(outer), Some(sandbox)) if !sandbox.is_within(outer) => {
+                return result_err_bare!(
+                    kind = crate::ErrorKind::Usage,
+                    "--sandbox: Not within the sandbox of the run"
+                )
+            }
+            (outer, sandbox) => sandbox.or_else(|| outer.clone()),
+        };
+        Ok(Globals {
+            error_format: match given("error-format") {
+                true => inner.error_format,
+                false => self.error_format,
+            },
+            progress_format: match given("progress-format") {
+                true => inner.progress_format,
+                false => self.progress_format,
+            },
+            overrides: Overrides {
+                yes: self.overrides.yes || inner.overrides.yes,
+                force: self.overrides.force || inner.overrides.force,
+            },
+            interactive: self.interactive || inner.interactive,
+            accessible: self.accessible || inner.accessible,
+            sandbox,
+            exit_codes: self.exit_codes,
+        })
+    }
+}
+
+/// The options entered on the calling thread, or else those selected for the process, or else the
+/// defaults.
+pub fn current() -> Arc<Globals> {
+    THREAD
+        .with(|entered| entered.borrow().clone())
+        .or_else(|| PROCESS.get().cloned())
+        .unwrap_or_default()
+}
+
+/// Select the options for the whole process. Only the first selection is kept.
+pub(crate) fn select(globals: Globals) {
+    let _ = PROCESS.set(Arc::new(globals));
+}
+
+/// Enter the options on the calling thread until the returned guard is dropped, which restores the
+/// options entered before.
+pub fn enter(globals: Arc<Globals>) -> Entered {
+    let previous = THREAD.with(|entered| entered.borrow_mut().replace(globals));
+    Entered {
+        previous,
+        _thread: PhantomData,
+    }
+}
+
+/// The options entered on a thread by [`enter`], which are left when it is dropped.
+#[must_use = "the options are left as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct Entered {
+    previous: Option<Arc<Globals>>,
+    // The options belong to the thread that entered them
+    _thread: PhantomData<*const ()>,
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        THREAD.with(|entered| *entered.borrow_mut() = previous);
+    }
+}
@@ -0,0 +1,198 @@
+//! Renaming of a project cloned from the template.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::diff;
+use crate::fsx::Journal;
+use crate::platform::long_path;
+use crate::{result_err, result_err_bare, Result};
+
+// The extensions of the files in which the name of the project is replaced
+const EXTENSIONS: &[&str] = &["rs", "toml", "md", "rhai"];
+
+// Directories that are never searched for files to update
+const SKIPPED_DIRS: &[&str] = &[".git", "target"];
+
+/// Rename the project rooted at the given directory in place.
+///
+/// The current name is taken from the package name in the `Cargo.toml` of the project and every
+/// occurrence of it is replaced in the manifests, sources, documentation and hooks of the project,
+/// including the crate paths used in the doc examples and tests. Occurrences that form part of a
+/// longer name or follow a `/`, as in a URL, are left alone. If the current name contains hyphens,
+/// the same name spelt with underscores is also replaced.
+///
+/// The new name must start with a letter and contain only letters, digits and underscores, so that
+/// it is valid as both a package name and a crate name.
+///
+/// The files are rewritten through an [`fsx::Journal`](crate::fsx::Journal), so that if one of them
+/// cannot be written those already rewritten are put back, and the changes are confined to the
+/// sandbox selected on the command line. Each updated file is then listed on the output stream. If
+/// `dry_run` is set, the changes are shown as a unified diff instead and no file is modified.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use tyg_template::init::init;
+/// use tyg_template::testkit::TempProject;
+///
+/// let project = TempProject::builder()
+///     .file("Cargo.toml", "[package]\nname = \"demo_app\"\n")
+///     .file("src/main.rs", "fn main() {\n    demo_app::run().unwrap();\n}\n")
+///     .create()
+///     .unwrap();
+///
+/// let mut out = Vec::new();
+/// init(project.root(), "my_tool", true, &mut out).unwrap();
+///
+/// assert!(String::from_utf8_lossy(&out).contains("+    my_tool::run().unwrap();"));
+/// assert!(fs::read_to_string("src/main.rs").unwrap().contains("demo_app::run()"));
+///
+/// init(project.root(), "my_tool", false, &mut out).unwrap();
+///
+/// assert!(fs::read_to_string("Cargo.toml").unwrap().contains("name = \"my_tool\""));
+/// assert!(fs::read_to_string("src/main.rs").unwrap().contains("my_tool::run()"));
+/// assert!(init(project.root(), "my-tool", false, &mut out).is_err());
+/// ```
+pub fn init(root: &Path, name: &str, dry_run: bool, out: &mut dyn Write) -> Result<()> {
+    if !is_valid_name(name) {
+        return result_err_bare!(
+            "{}: Not a valid name, use letters, digits and underscores starting with a letter",
+            name
+        );
+    }
+    let old_name = package_name(&root.join("Cargo.toml"))?;
+    if old_name == name {
+        writeln!(out, "The project is already named {}", name)?;
+        return Ok(());
+    }
+
+    let mut files = Vec::new();
+    find_files(root, &mut files)?;
+    let journal = Journal::new()?;
+    let mut updated = Vec::new();
+    for path in files {
+        let text = match fs::read_to_string(long_path(&path)) {
+            Ok(text) => text,
+            // Files that are not UTF-8 cannot contain a name to replace
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => continue,
+            Err(e) => return result_err!("{}: {}", path.display(), e),
+        };
+        let mut renamed = replace_name(&text, &old_name, name);
+        if old_name.contains('-') {
+            renamed = replace_name(&renamed, &old_name.replace('-', "_"), name);
+        }
+        if renamed == text {
+            continue;
+        }
+
+        let relative = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+        if dry_run {
+            write!(
+                out,
+                "{}",
+                diff::unified(&relative, &relative, &text, &renamed)
+            )?;
+        } else {
+            journal.write(&path, renamed)?;
+        }
+        updated.push(relative);
+    }
+
+    if dry_run {
+        writeln!(
+            out,
+            "{} files would be updated to rename {} to {}",
+            updated.len(),
+            old_name,
+            name
+        )?;
+        return Ok(());
+    }
+    // Only once every file has been rewritten are the changes kept
+    journal.commit()?;
+    for relative in &updated {
+        writeln!(out, "Updated {}", relative)?;
+    }
+    writeln!(
+        out,
+        "Renamed {} to {} in {} files",
+        old_name,
+        name,
+        updated.len()
+    )?;
+    Ok(())
+}
+
+// Check that the name is valid as both a package name and a crate name
+fn is_valid_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Extract the name of the package from the manifest
+fn package_name(manifest: &Path) -> Result<String> {
+    let text = fs::read_to_string(long_path(manifest))
+        .or_else(|e| result_err_bare!("{}: {}", manifest.display(), e))?;
+    let mut in_package = false;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if let (true, Some(value)) = (in_package, line.strip_prefix("name")) {
+            if let Some(value) = value.trim_start().strip_prefix('=') {
+                return Ok(value.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+    result_err_bare!("{}: No package name found", manifest.display())
+}
+
+// Collect the files that may contain the name of the project, in a consistent order
+fn find_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = fs::read_dir(long_path(dir))?
+        .map(|entry| entry.map(|e| dir.join(e.file_name())))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if long_path(&path).is_dir() {
+            if !SKIPPED_DIRS.contains(&file_name.as_ref()) {
+                find_files(&path, files)?;
+            }
+        } else if path
+            .extension()
+            .is_some_and(|e| EXTENSIONS.iter().any(|x| e == *x))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Replace each occurrence of the old name that is not part of a longer name or a URL
+fn replace_name(text: &str, old: &str, new: &str) -> String {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find(old) {
+        let before = rest[..index]
+            .chars()
+            .next_back()
+            .or(result.chars().next_back());
+        let after = rest[index + old.len()..].chars().next();
+        result.push_str(&rest[..index]);
+        if before.is_some_and(|c| is_name_char(c) || c == '/') || after.is_some_and(is_name_char) {
+            result.push_str(old);
+        } else {
+            result.push_str(new);
+        }
+        rest = &rest[index + old.len()..];
+    }
+    result.push_str(rest);
+    result
+}
@@ -12,10 +12,13 @@
 //!
 //! # Examples
 //!
-//! *In the following examples, a Unix like operating system is assumed.*
+//! *In the following examples, a Unix like operating system is assumed. On Windows, the template
+//! switches the console to UTF-8 output with ANSI escape sequences enabled and lifts the limit on
+//! the length of the paths it works with, so the examples behave the same.*
 //!
-//! The best way to use this is simply to do a git clone and then rename everything to suit your
-//! own coding project. I would not recommend using it in its raw form directly, however, before
+//! The best way to use this is to generate a new project from it (see Usage below) or simply to do
+//! a git clone and then rename everything to suit your own coding project. I would not recommend
+//! using it in its raw form directly, however, before
 //! modifying the template to suit your own application, you could try running the sample
 //! application by entering the following at the command prompt:
 //!
@@ -29,11 +32,11 @@
 //!
 //! ```text
 //! $ cargo run -- fail
-//! tyg_template: src/lib.rs:122:9: Error thrown to demonstrate the error handling process
+//! tyg_template: src/lib.rs:<line>:<column>: Error thrown to demonstrate the error handling process
 //! ```
 //! 
 //! This is what I call a disclosed error showing the name of the source file and where in the
-//! source file the error occured.
+//! source file the error occured, in place of `<line>:<column>` above.
 //!
 //! It is also possible to throw non-disclosed errors in which the origin is not disclosed to the
 //! end user, however, this can be overridden by compiling with the `disclose` feature enabled.
@@ -49,7 +52,7 @@
 //!
 //! ```text
 //! $ cargo run --features=disclose -- fail --bare
-//! tyg_template: src/lib.rs:132:9: Error thrown to demonstrate the error handling process
+//! tyg_template: src/lib.rs:<line>:<column>: Error thrown to demonstrate the error handling process
 //! ```
 //!
 //! Notice that the error message now shows the location of the error.
@@ -57,14 +60,358 @@
 //! In general, disclosed errors are ideal for debugging purposes, so during a debug session I
 //! would recommend compiling the application with the `disclose` feature enabled.
 //!
+//! Errors from other functions can be given a message and a location with the `ResultExt` trait,
+//! and `None` converted into an error with the `OptionExt` trait, which saves writing an `or_else`
+//! or `ok_or_else` closure around the macros:
+//!
+//! ```text
+//! File::open(path).msg(|| path.display())?;  // A disclosed error with a message
+//! File::open(path).bare(|| path.display())?; // A non-disclosed error with a message
+//! File::open(path).loc()?;                   // A disclosed error with no extra message
+//! args.next().or_err("No path given")?;      // A disclosed error for None
+//! ```
+//!
+//! Iterators over results, such as the `Counter` of the `recursive_fail` demo, can be processed with
+//! the `ResultIteratorExt` adapters, e.g. `take_until_err`, `map_ok` and `collect_partitioned`.
+//!
+//! The `parallel_fail` demo runs workers in named scoped threads and gathers their errors, and the
+//! panics caught when they are joined, into an `ErrorCollection`, with the name of each thread as
+//! the context of its error.
+//!
 //! # Usage
 //!
-//! As mentioned previously, the best way to use this is simply to do a git clone and then rename
-//! everything to suit your own coding project. My recommendation is that the cli and run functions
-//! in lib.rs are moved to the main program (main.rs). This enables you to start with a clean new
-//! lib.rs. Using this configuration confines the command line interface and top level error
-//! trapping to main.rs. The library crate will then be responsible for the overall functionality
-//! of the application.
+//! The template can be used with [cargo-generate](https://github.com/cargo-generate/cargo-generate)
+//! to create a new project with the package name, author and error prefix of your choice:
+//!
+//! ```text
+//! $ cargo generate --git https://github.com/converse99/tyg_template --allow-commands
+//! ```
+//!
+//! The placeholders are expanded in the files under the `template` directory, which a post-generation
+//! hook then moves into place before checking that the new project builds. The library crate keeps
+//! the name `tyg_template` so that the doc examples and tests continue to build; rename it when
+//! convenient. The fuzz targets refer to the template package by name and need updating by hand.
+//!
+//! Alternatively, do a git clone and then rename the project with the `init` subcommand, previewing
+//! the changes first with `--dry-run`:
+//!
+//! ```text
+//! $ cargo run -- init --name my_tool --dry-run
+//! $ cargo run -- init --name my_tool
+//! ```
+//!
+//! Then adapt everything else to suit your own coding project. My
+//! recommendation is that the cli and run functions in lib.rs are moved to the main program
+//! (main.rs). This enables you to start with a clean new lib.rs. Using this configuration confines
+//! the command line interface and top level error trapping to main.rs. The library crate will then
+//! be responsible for the overall functionality of the application.
+//!
+//! The error handling lives in the `tyg_errors` crate of the workspace, so that other binaries can
+//! depend on it without the rest of the template. The template re-exports its contents, so the
+//! `Error` type and the error macros can be used from either crate.
+//!
+//! Errors from other crates are best wrapped in their own variant of the `Error` enum. The `generate`
+//! subcommand adds such a variant to `tyg_errors/src/lib.rs` along with everything the error handler
+//! needs:
+//!
+//! ```text
+//! $ cargo run -- generate error-variant Json --wraps serde_json::Error
+//! ```
+//!
+//! Shell completions are printed by the `completions` subcommand for bash, elvish, fig, fish,
+//! nushell, PowerShell and zsh. With `--install` the script is written to the per-user location
+//! from which the shell loads completions instead:
+//!
+//! ```text
+//! $ cargo run -- completions --install nushell
+//! ```
+//!
+//! The bash, fish and zsh scripts also complete values that are only known at run time, such as the
+//! languages of the installed locale bundles for `--lang`, by calling the hidden `__complete`
+//! subcommand of the application. An argument is given such values by registering a closure that
+//! lists them where it is defined, e.g. `.complete_with(audit::subcommands)`.
+//!
+//! External tools such as documentation generators can read a JSON description of the command line
+//! interface, with its subcommands, arguments, value types and defaults, from the hidden
+//! `dump-cli-schema` subcommand. The `schema` module describes the format.
+//!
+//! Arguments given in human units can use the types of the `units` module as their value parser,
+//! e.g. `value_parser!(ByteSize)` for sizes such as `10MB` or `1.5GiB` and
+//! `value_parser!(HumanDuration)` for durations such as `5m30s` or `250ms`. Their errors list the
+//! forms that are accepted, and the values display in the same form for echoing back to the user.
+//! With the `datetime` feature enabled, `value_parser!(DateTime)` from the `datetime` module
+//! accepts dates and times such as `2024-01-01`, `2024-01-01T12:00:00Z` or `2 days ago`, resolved
+//! in the local time zone. With the `regex` feature enabled, `value_parser!(Pattern)` from the
+//! `pattern` module compiles a regular expression as the command line is parsed, and reports a
+//! syntax error at the part of the pattern at fault.
+//!
+//! Flags and subcommands that are renamed as the application evolves can keep their old names for a
+//! while by listing them in the `DEPRECATED` registry next to the `cli` function. The old names still
+//! work but show a warning naming the replacement and the release that removes them, unless the
+//! `TYG_NO_DEPRECATION_WARNINGS` environment variable is set:
+//!
+//! ```text
+//! $ cargo run -- fail --no-location
+//! tyg_template: warning: --no-location is deprecated, use --bare; it will be removed in 0.2.0
+//! ```
+//!
+//! The help can be translated by adding locale bundles to the data directory of the application,
+//! e.g. `~/.local/share/tyg_template/locales/fr.txt` on Linux. The language is taken from `LANG`,
+//! or from the `--lang` option, and the compiled in English text is used for anything that has not
+//! been translated. The `locale` module describes the format of the bundles.
+//!
+//! Network errors are demonstrated by the `fetch` subcommand, which fetches a URL when compiled with
+//! the `http` feature enabled. A failed request is returned as the `Http` variant of the `Error`
+//! enum, whose message describes the DNS, connection, TLS, status or body failure:
+//!
+//! ```text
+//! $ cargo run --features=http -- fetch https://no-such-host.invalid/
+//! tyg_template: io: failed to lookup address information: Name or service not known
+//! ```
+//!
+//! The `net` module checks that a host can be resolved and reached, with errors worded for the user,
+//! e.g. `api.example.com: Cannot resolve the host name, are you online?`. They are returned as the
+//! `Network` variant of the `Error` enum, and `Error::is_transient` tells whether such a failure, or
+//! that of an HTTP request, is worth retrying.
+//!
+//! The application leaves by the `exit` module, which runs the cleanup registered with
+//! `exit::on_exit`, e.g. removing temporary files, and flushes the output before the process ends,
+//! whether it succeeds, fails with the exit code of the kind of its error or panics, or, on Unix, is
+//! interrupted or asked to stop by a signal.
+//!
+//! For local development, variables set in a `.env` file in the current directory, one of its
+//! parents or the home directory are added to the environment before the command line is parsed,
+//! without overriding those already set. The `--no-dotenv` flag turns this off, and `--debug` shows
+//! which file was loaded (see the `dotenv` module).
+//!
+//! The `secrets` module stores secrets such as API tokens in the keyring of the platform when the
+//! `keyring` feature is enabled, with an environment variable named after the service and account,
+//! e.g. `TYG_TEMPLATE_TOKEN`, taking its place in headless environments. The `config auth`
+//! subcommand shows how to store, show and delete a token with it.
+//!
+//! Settings are read from `config.toml` in the configuration directory of the application, and checked
+//! against the schema of the settings in the `config` module before they are used: an unknown key is
+//! reported with the nearest known key as a suggestion, and a value of the wrong type with the type
+//! expected, each at its line and column. The `config validate` subcommand checks a file without running
+//! anything else, and exits with 1 when it finds problems.
+//!
+//! A file written for an earlier release is upgraded when it is loaded. Its `config_version` setting
+//! gives its version, 1 if it has none, and the migrations in the `MIGRATIONS` registry of the `config`
+//! module each upgrade a file from one version to the next by renaming, converting or removing
+//! settings. The upgraded file replaces the original, which is kept beside it as e.g.
+//! `config.toml.v1.bak`, with a warning about each change. `config migrate --dry-run` shows the upgrade
+//! as a diff without making it.
+//!
+//! With the `metrics` feature enabled, each run adds to counters of invocations, time taken, errors by
+//! kind and warnings in a Prometheus textfile named by the `TYG_METRICS_FILE` environment variable,
+//! for collection by the node exporter (see the `metrics` module).
+//!
+//! The global `--max-memory` and `--max-runtime` options limit the resident memory and the time taken
+//! by a run, e.g. `--max-runtime 10m`, so that a runaway run fails predictably with an
+//! `Error::ResourceLimit` naming the limit exceeded, which exits with 75, rather than being killed
+//! when the machine runs out of memory. A watchdog thread polls the resources, long running work
+//! calls `limits::check()` to stop cleanly, and work that does not is stopped through the exit
+//! handlers.
+//!
+//! The `--accessible` flag, or the `TYG_ACCESSIBLE` environment variable, turns on output suited to
+//! screen readers: the help and errors are shown without colors, and messages are labelled in words,
+//! e.g. `error:` and `done:`.
+//!
+//! Output is plain, without colors, cursor movement or animations, in the accessible mode, when the
+//! `TERM` environment variable is `dumb` and when the application runs in CI, as told by the `CI`
+//! environment variable or those of common CI services, so that escape sequences do not leak into
+//! logs. A progress bar is then replaced by a plain status line every tenth of the way. Output
+//! added to the application should check `terminal::plain()`, or `terminal::styled()` for colors
+//! (see the `terminal` module).
+//!
+//! On shared machines, setting the `TYG_AUDIT_LOG` environment variable keeps a record of each run,
+//! with the time, the user, the command line with the values of secret flags redacted, the time taken
+//! and the exit code, in `audit.log` in the state directory of the application. The `history`
+//! subcommand shows the record, e.g. `history --failed --limit 10` (see the `audit` module).
+//!
+//! Subcommands that move, overwrite or remove files should make the changes through an
+//! `fsx::Journal`, which keeps what is needed to undo them. The changes are kept when the journal is
+//! committed, and otherwise rolled back when it is dropped, e.g. because the handler returned an
+//! error, or when the application leaves after a panic or a signal such as `SIGTERM`.
+//!
+//! The global `--yes` flag answers yes to the confirmation prompts of `prompt::confirm`, such as the
+//! one `init` asks before renaming the project, and the global `--force` flag overrides safety
+//! checks, such as the refusal of `config migrate` to replace the backup of an earlier migration.
+//! An operation that is refused says which flag would allow it.
+//!
+//! With the global `--sandbox <DIR>` option, given once for each directory, the changes made through
+//! an `fsx::Journal` are confined to those directories, for running a tool against a machine that
+//! matters. Every subcommand that changes files, such as `init`, `generate`, `config migrate`,
+//! `completions --install` and `cache clear`, makes its changes through one. A change to a path
+//! outside them is not made: it is logged as a warning saying what would have been done, as a dry
+//! run does, and refused with an `Error::SandboxViolation` naming the path, which exits with 77.
+//! The records the application keeps of its own runs, such as the audit log, the entries of the
+//! cache, the metrics and the `--summary-file`, are written wherever they are configured.
+//!
+//! The `shellwords` module quotes words for POSIX shells and PowerShell, for commands that print a
+//! suggested command line or compose one for `sh -c`, and splits a line into words by the rules of a
+//! POSIX shell.
+//!
+//! The `run-script` subcommand runs the commands given on the lines of a file, e.g.
+//! `run-script ops.tyg`, each written as it would be after the name of the application, with global
+//! options such as `--yes` applying to the command of their line. The script stops at the first
+//! command that fails, reporting the file and line of the command, unless `--keep-going` is given,
+//! in which case the rest of the commands are run and the failures are summarised at the end.
+//!
+//! With the global `--interactive` flag, when the standard input is a terminal, a failure in a batch
+//! such as a script asks whether to retry the item, skip it, abort the batch or skip every failure
+//! that follows. The answer is fed back to the loop by the recovery hook of its `ErrorCollection`,
+//! made with `ErrorCollection::with_recovery` and consulted by `attempt`, so that any batch can offer
+//! the same choices with `prompt::recovery_hook()`.
+//!
+//! Expensive lookups, such as the metadata of an API or a parsed index, can be cached with
+//! `cache::get_or_compute(key, ttl, f)`, which keeps the result as JSON in the cache directory of the
+//! application, e.g. `~/.cache/<name>`, until it is older than the time to live. An entry that cannot
+//! be read is recomputed with a warning. The `cache clear` subcommand removes every entry.
+//!
+//! Files are downloaded with `download::download(url, dest, sha256, progress)` when the application
+//! is compiled with the `http` feature. The body is written to `<dest>.part`, which a later download
+//! resumes with a `Range` request, and is renamed into place once its SHA-256 checksum matches. The
+//! progress function can be `download::progress_bar`. Network, disk and checksum failures are
+//! returned as the `Http` or `Network`, `File` and `Verification` variants of the `Error` enum.
+//!
+//! Setting the `TYG_UPDATE_CHECK` environment variable turns on a check for a newer release, when
+//! the application is compiled with the `http` feature. The release endpoint, `update::RELEASE_URL`
+//! or the one named by `TYG_UPDATE_URL`, is asked at most once a day by a copy of the application
+//! running in the background, which caches the answer. A successful run ends with a notice such as
+//! `A newer version (1.4.0) is available` when the cached answer is newer. The check never delays or
+//! fails the command.
+//!
+//! The `changelog` subcommand shows the changes listed in `CHANGELOG.md`, which is compiled into the
+//! application, rendered for the terminal by the `markdown` module. It shows the releases after the
+//! version given with `--since`, or else those after the version that last showed the changelog, so
+//! that after an update it tells the user what changed. Add the changes of each release under
+//! `## [Unreleased]` as they are made.
+//!
+//! For automation, the global `--summary json` option writes a single JSON object to the error
+//! stream when the command finishes, successfully or not, with its status, exit code, subcommand,
+//! duration, numbers of errors and warnings and the key statistics recorded with `summary::stat`.
+//! The `--summary-file <PATH>` option writes it to a file instead. See the `summary` module.
+//!
+//! Every error has an `ErrorKind`, such as `Config`, `NotFound` or `Network`, which decides the
+//! exit code of a failed run, following `sysexits.h`, e.g. 78 for a configuration error, and is
+//! reported by the `--summary json` option and the metrics. The kind follows from the variant of
+//! the error unless it is given as the first argument of an error macro, e.g. `result_err!(kind =
+//! ErrorKind::Config, "Invalid value for {}", key)`, or with `with_kind`. General errors, such as
+//! those of the `Error` variant, exit with 1.
+//!
+//! A phase of work can be named with `with_scope!("loading configuration", { ... })`, or with the
+//! guard returned by `scope("loading configuration")`, so that every error created within it, by
+//! the error macros, the `ResultExt` and `OptionExt` methods or `?` on an I/O error, carries the
+//! label without each message repeating it. `Error::inner` gives the error underneath its scopes,
+//! e.g. to match its variant. Scopes nest on a stack kept for each thread, and the error is
+//! reported with the trail of the scopes it happened in:
+//!
+//! ```text
+//! tyg_template: settings.toml:2:1: Unknown key 'retrys', did you mean 'retries'?
+//!   while loading configuration > reading settings.toml
+//! ```
+//!
+//! Long running work, such as the commands of `run-script`, registers its progress with the
+//! `progress` module, giving the item it is working on and the number done. On Unix, with the
+//! `signals` feature, sending the application `SIGUSR1`, e.g. `kill -USR1 <pid>`, writes the tasks
+//! in progress to the error stream without interrupting them:
+//!
+//! ```text
+//! tyg_template: progress after 1m4s
+//!   run-script nightly.tyg: 12 of 40 (30%), at nightly.tyg:14, 1m3s elapsed
+//! ```
+//!
+//! For wrappers such as CI scripts and graphical frontends, the global `--progress-format json`
+//! option makes the progress registry write events as JSON lines to the error stream when a task
+//! starts, completes an item, reaches a new percentage, finishes or fails, in place of progress
+//! bars. The human format stays the default:
+//!
+//! ```text
+//! {"event":"task_started","name":"run-script nightly.tyg","task":0,"total":40}
+//! {"done":1,"event":"item_completed","item":"nightly.tyg:1","task":0}
+//! {"done":1,"event":"progress","percent":2,"task":0,"total":40}
+//! ```
+//!
+//! A subcommand with findings to report, such as the `check` demo, returns an `Outcome`:
+//! `Outcome::Success`, `Outcome::Findings(n)` or `Outcome::Err(error)`. The dispatcher maps it to
+//! the exit code given for the subcommand in the `EXIT_CODES` table, so that `check` exits with 1
+//! when it finds problems and with 2 when it cannot finish. Subcommands not in the table exit with
+//! 1 for findings and with the code of the kind of an error. See the `outcome` module.
+//!
+//! When the application is to be run as a systemd service, compile it with the `systemd` feature
+//! enabled. It then tells systemd when it is ready and when it is stopping, which suits services of
+//! `Type=notify`, and sends errors to the journal with their source location as separate fields.
+//!
+//! When the application runs in a GitHub Actions workflow, give it the `--error-format github`
+//! option. Errors are then reported as workflow commands, which GitHub shows as annotations on the
+//! source file and line disclosed by the error.
+//!
+//! Applications that report errors with [color-eyre](https://docs.rs/color-eyre) can enable the
+//! `eyre` feature and call `eyre_compat::run_eyre` from the main program. The template's macros are
+//! still used internally and the errors are converted to reports at the boundary, with any disclosed
+//! location shown in a section of its own.
+//!
+//! To ship one binary under several names, as busybox does, list the applets in the `APPLETS` table
+//! of `src/lib.rs`, each a name and the subcommand it runs, and install them as links to the binary.
+//! Run through a link named `tyg-check`, the binary runs `check`, so `tyg-check notes.txt` is
+//! `tyg_template check notes.txt`. `tyg_template --list-applets` lists the applets with the commands
+//! that install them as symbolic or hard links. See the `multicall` module.
+//!
+//! A host application, such as a graphical or daemon frontend, can run the subcommands without the
+//! process writing to the standard streams or exiting. `embed::spawn` runs a command line on a thread
+//! of its own and returns a channel of `embed::Event`s, and `embed::run` passes them to a callback.
+//! The events are the lines of output and diagnostics, warnings about the command line, the progress
+//! of the tasks and, last, the outcome of the subcommand with its exit code.
+//!
+//! Other crates can extend the application with compiled-in plugins. A plugin implements the
+//! `plugin::Plugin` trait, declaring its subcommands as clap commands, running them, and optionally
+//! providing formats of error reports that `--error-format` selects by name. Registered with
+//! `plugin::register` at the start of `main`, before `run`, its subcommands appear in the help,
+//! completions and scripts like those of the application. A plugin whose subcommand or format name
+//! is already taken by the application or an earlier plugin is refused with an error.
+//!
+//! # Testing
+//!
+//! The `testkit` module contains helpers for testing applications built from the template. It is
+//! only compiled with the `testing` feature enabled, which the package enables for its own tests and
+//! doc examples through a dev-dependency on itself, so that a release build leaves it out. The
+//! snapshot tests in `tests/snapshots.rs` lock in the output of each subcommand and fail if any
+//! subcommand or argument is not exercised by one of them. After an intentional change to the
+//! output, regenerate the snapshot files as follows:
+//!
+//! ```text
+//! $ TYG_UPDATE_SNAPSHOTS=1 cargo test
+//! ```
+//!
+//! The test in `tests/examples.rs` runs the example command lines in the help and the `cargo run`
+//! command lines in the README through the command line parser, so that an example that no longer
+//! parses fails the tests.
+//!
+//! The time of day is read from the `clock` module, where a test can enter the `testkit::FakeClock`
+//! and advance it by hand, so that the expiry of cache entries and the times in the audit log are
+//! tested without waiting for them.
+//!
+//! The property tests in `tests/properties.rs` use the proptest strategies in `testkit::strategy`.
+//!
+//! The benchmarks in `benches` measure the creation and formatting of errors and the throughput of
+//! the output streams. Run them with the `disclose` feature as well to compare the bare macros when
+//! they disclose the location:
+//!
+//! ```text
+//! $ cargo bench
+//! $ cargo bench --features=disclose
+//! ```
+//!
+//! # Fuzzing
+//!
+//! The `fuzz` directory contains [cargo-fuzz](https://github.com/rust-fuzz/cargo-fuzz) targets for
+//! the command line parsing (`cli_args`) and for the error location parsing (`error_text`). Fuzzing
+//! requires a nightly toolchain:
+//!
+//! ```text
+//! $ cargo +nightly fuzz run cli_args
+//! ```
 //!
 //! # Initial File Structure
 //!
@@ -72,35 +419,250 @@
 //!  tyg_template --|
 //!                 |- Cargo.toml
 //!                 |- Cargo.lock
+//!                 |- cargo-generate.toml {The cargo-generate configuration}
+//!                 |- CHANGELOG.md {The changes in each release}
 //!                 |- LICENCE
 //!                 |- README.md
 //!                 |- src
 //!                     |- main.rs {The main program}
 //!                     |- lib.rs {The library crate root}
-//!                     |- error.rs {The error handler}
+//!                     |- error_format.rs {The formats of error reports}
+//!                     |- output.rs {The output streams}
+//!                     |- accessibility.rs {Output suited to screen readers}
+//!                     |- audit.rs {The audit log}
+//!                     |- cache.rs {The cache of the results of expensive lookups}
+//!                     |- changelog.rs {The changelog subcommand}
+//!                     |- clock.rs {The clock read for the time of day}
+//!                     |- completions.rs {Shell completion scripts}
+//!                     |- config.rs {The configuration file and the schema of its settings}
+//!                     |- datetime.rs {Arguments giving dates and times}
+//!                     |- deprecation.rs {Deprecation of renamed flags}
+//!                     |- diff.rs {The diff renderer}
+//!                     |- download.rs {Downloads with progress, resumption and checksums}
+//!                     |- dotenv.rs {Loading of .env files}
+//!                     |- embed.rs {Embedding the engine in a host application}
+//!                     |- exit.rs {The exit path of the application}
+//!                     |- eyre_compat.rs {Compatibility with eyre}
+//!                     |- fetch.rs {The fetch subcommand}
+//!                     |- fsx.rs {Changes to files that can be undone}
+//!                     |- generate.rs {The code generators}
+//!                     |- globals.rs {The global options of a run}
+//!                     |- init.rs {The init subcommand}
+//!                     |- limits.rs {Limits on the resources of a run}
+//!                     |- locale.rs {Translation of the help}
+//!                     |- markdown.rs {Rendering of Markdown for the terminal}
+//!                     |- metrics.rs {Export of Prometheus metrics}
+//!                     |- multicall.rs {Dispatch of the applets of a multicall binary}
+//!                     |- net.rs {Network connectivity checks}
+//!                     |- outcome.rs {The outcome of a subcommand and its exit codes}
+//!                     |- pattern.rs {Arguments giving regular expressions}
+//!                     |- platform.rs {Platform specific support}
+//!                     |- plugin.rs {Plugins compiled in from other crates}
+//!                     |- progress.rs {The progress of the tasks in progress}
+//!                     |- prompt.rs {Confirmation prompts}
+//!                     |- schema.rs {The JSON schema of the command line}
+//!                     |- script.rs {The run-script subcommand}
+//!                     |- secrets.rs {Secrets stored in the keyring}
+//!                     |- shellwords.rs {Quoting and splitting of shell words}
+//!                     |- summary.rs {The end of run summary for automation}
+//!                     |- systemd.rs {Integration with systemd}
+//!                     |- terminal.rs {The capabilities of the terminal}
+//!                     |- testkit.rs {Helpers for testing}
+//!                     |- units.rs {Arguments in human units}
+//!                     |- update.rs {The check for a newer release}
+//!                     |- config
+//!                         |- migration.rs {Versioned migration of the configuration file}
+//!                     |- platform
+//!                         |- linux.rs {Linux memory support}
+//!                         |- windows.rs {Windows console, path and memory support}
+//!                     |- testkit
+//!                         |- strategy.rs {Strategies for property testing}
+//!                 |- benches
+//!                     |- errors.rs {Benchmarks of the errors}
+//!                     |- output.rs {Benchmarks of the output streams}
+//!                 |- tests
+//!                     |- examples.rs {Checks the documented examples parse}
+//!                     |- snapshots.rs {Snapshot tests of the subcommands}
+//!                     |- shellwords.rs {Checks the quoting of awkward words}
+//!                     |- download.rs {Checks downloads against a local server}
+//!                     |- signals.rs {Checks the progress written on SIGUSR1 and the rollback on SIGTERM}
+//!                     |- embed.rs {Checks the events of an embedded run}
+//!                     |- plugin.rs {Checks the subcommands of a plugin}
+//!                     |- progress.rs {Checks the JSON progress events}
+//!                     |- sandbox.rs {Checks the changes refused by --sandbox}
+//!                     |- update.rs {Checks the update check is not audited}
+//!                     |- properties.rs {Property tests of the error formatting}
+//!                     |- template.rs {Checks the generated manifest}
+//!                     |- snapshots {The snapshot files}
+//!                 |- tyg_errors {The error handling crate}
+//!                     |- Cargo.toml
+//!                     |- README.md
+//!                     |- src
+//!                         |- lib.rs {The error handler}
+//!                         |- collection.rs {The collection of the errors of a batch}
+//!                         |- ext.rs {Extension traits for results, options and iterators}
+//!                         |- kind.rs {The classification of errors and their exit codes}
+//!                         |- scope.rs {The scopes recorded by errors as a trail}
+//!                         |- strategy.rs {Strategies for property testing}
+//!                 |- fuzz {Fuzz targets for cargo-fuzz}
+//!                 |- template {Files expanded by cargo-generate}
 //! ```
 
-mod error;
-pub use error::{Error, Result};
+pub use tyg_errors::{
+    current_scopes, option_err, option_err_bare, result_err, result_err_bare, scope, with_scope,
+    Error, ErrorCollection, ErrorKind, Location, OptionExt, Recovery, Result, ResultExt,
+    ResultIteratorExt, ScopeGuard, Scoped,
+};
+
+mod error_format;
+pub use error_format::ErrorFormat;
+mod output;
+pub use output::Streams;
+
+pub mod accessibility;
+pub mod audit;
+pub mod cache;
+pub mod changelog;
+pub mod clock;
+pub mod completions;
+pub mod config;
+#[cfg(feature = "datetime")]
+pub mod datetime;
+pub mod deprecation;
+pub mod diff;
+pub mod download;
+pub mod dotenv;
+pub mod embed;
+pub mod exit;
+#[cfg(feature = "eyre")]
+pub mod eyre_compat;
+pub mod fetch;
+pub mod fsx;
+pub mod generate;
+pub mod globals;
+pub mod init;
+pub mod limits;
+pub mod locale;
+pub mod markdown;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod multicall;
+pub mod net;
+pub mod outcome;
+#[cfg(feature = "regex")]
+pub mod pattern;
+pub mod platform;
+pub mod plugin;
+pub mod progress;
+pub mod prompt;
+pub mod schema;
+pub mod script;
+pub mod secrets;
+pub mod shellwords;
+pub mod summary;
+#[cfg(feature = "systemd")]
+pub mod systemd;
+pub mod terminal;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testkit;
+pub mod units;
+pub mod update;
 
-use std::ffi::OsStr;
-use std::fs::File;
+use std::any::Any;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
 
-use clap::{arg, Command};
+use clap::error::ErrorKind as CliErrorKind;
+use clap::parser::ValueSource;
+use clap::{arg, value_parser, ArgAction, ArgMatches, ColorChoice, Command};
+
+use completions::CompleteWith;
+use deprecation::Deprecated;
+use fetch::HttpUrl;
+use globals::Globals;
+use limits::Limits;
+use locale::Locale;
+use multicall::Applet;
+use outcome::{ExitCodes, Outcome};
+use progress::ProgressFormat;
+use units::{ByteSize, HumanDuration};
 
 // This should be compiled using Cargo so that the verson number can be extracted
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// The prefix shown before error messages and the notice of a newer release. This is the name of
+/// the binary unless it is overridden by setting the TYG_ERROR_PREFIX environment variable when
+/// building.
+pub const ERROR_PREFIX: &str = match option_env!("TYG_ERROR_PREFIX") {
+    Some(prefix) => prefix,
+    None => env!("CARGO_PKG_NAME"),
+};
+
+// The examples shown at the end of the help. The text can be translated with the examples key of a
+// locale bundle.
+const EXAMPLES: &str = concat!(
+    "Examples:\n",
+    "  ", env!("CARGO_PKG_NAME"), " fail --bare\n",
+    "  ", env!("CARGO_PKG_NAME"), " file_fail --better missing.txt\n",
+    "  ", env!("CARGO_PKG_NAME"), " --lang fr --help"
+);
+
 // The cli function is used to specify the form of the command line using the builder style. You
-// will need to modify this to suit your own application.
-fn cli() -> Command<'static> {
-    Command::new("tyg_template")
+// will need to modify this to suit your own application. The text given here is the English text,
+// which can be translated by the locale bundles (see the locale module).
+fn cli() -> Command {
+    let command = Command::new(env!("CARGO_PKG_NAME"))
         .version(VERSION)
         .about("A demonstration of a basic command line application using clap with error handling. \
                This is designed to be used as a basic template when starting a new command line project")
         .subcommand_required(true)
         .arg_required_else_help(true)
-        .arg(arg!(-d --debug "Show debugging information. Not currently used")
+        .after_help(EXAMPLES)
+        .arg(arg!(-d --debug "Show debugging information, e.g. the .env file loaded")
+             .global(true))
+        .arg(arg!(--"no-dotenv" "Do not load environment variables from a .env file")
+             .global(true))
+        .arg(arg!(--accessible "Write plain output for screen readers, without colors and with messages labelled in words")
+             .global(true))
+        .arg(arg!(-y --yes "Answer yes to every confirmation prompt")
+             .global(true))
+        .arg(arg!(--force "Override safety checks, e.g. the refusal to replace a file that exists")
+             .global(true))
+        .arg(arg!(--interactive "Ask whether to retry, skip or abort when an item of a batch fails, if the input is a terminal")
+             .global(true))
+        .arg(arg!(--summary <FORMAT> "Write a summary of the run for automation to the error stream, or to the --summary-file")
+             .value_parser(summary::FORMATS)
+             .global(true))
+        .arg(arg!(--"summary-file" <PATH> "Write the summary of the run to this file instead of the error stream")
+             .value_parser(value_parser!(PathBuf))
+             .requires("summary")
+             .global(true))
+        .arg(arg!(--lang <LANG> "The language of the help, e.g. fr or fr_FR, instead of the one set by LANG")
+             .complete_with(Locale::languages)
+             .global(true))
+        .arg(arg!(--"max-memory" <SIZE> "Stop the run with an error when its resident memory exceeds this, e.g. 512MiB")
+             .value_parser(value_parser!(ByteSize))
+             .global(true))
+        .arg(arg!(--"max-runtime" <DURATION> "Stop the run with an error when it takes longer than this, e.g. 10m")
+             .value_parser(value_parser!(HumanDuration))
+             .global(true))
+        .arg(arg!(--"error-format" <FORMAT> "The format of error reports, github producing GitHub Actions annotations")
+             .value_parser(ErrorFormat::names())
+             .default_value("text")
+             .global(true))
+        .arg(arg!(--"progress-format" <FORMAT> "The format of progress reports, json writing events as JSON lines to the error stream")
+             .value_parser(ProgressFormat::NAMES)
+             .default_value("human")
+             .global(true))
+        .arg(arg!(--sandbox <DIR> "Refuse changes by the subcommand to files outside this directory, logging them instead; may be given more than once")
+             .value_parser(value_parser!(PathBuf))
+             .action(ArgAction::Append)
              .global(true))
         .subcommand(
             Command::new("fail")
@@ -113,10 +675,151 @@ fn cli() -> Command<'static> {
             Command::new("file_fail")
             .about("Show how to handle a regular filing system error e.g. file not found")
             .arg(arg!(--better "A better rendition of the error message"))
-            .arg(arg!(<PATH> "Path to an invalid file (i.e. one that doesn't exist)").allow_invalid_utf8(true)))
+            .arg(arg!(<PATH> "Path to an invalid file (i.e. one that doesn't exist)")
+                 .value_parser(value_parser!(OsString))))
+        .subcommand(
+            Command::new("parallel_fail")
+            .about("Show how to handle errors and panics in threads run in parallel"))
+        .subcommand(
+            Command::new("check")
+            .about("Show how to report findings with an exit code of their own, by checking that files exist and are not empty")
+            .arg(arg!(<PATH>... "The files to check")
+                 .value_parser(value_parser!(PathBuf))))
+        .subcommand(
+            Command::new("fetch")
+            .about("Show how to handle a network error e.g. host not found, when built with the http feature")
+            .arg(arg!(<URL> "The http or https URL to fetch")
+                 .value_parser(value_parser!(HttpUrl)))
+            .arg(arg!(--timeout <DURATION> "The time allowed for the whole request, e.g. 30s or 1m30s")
+                 .value_parser(value_parser!(HumanDuration))
+                 .default_value("30s"))
+            .arg(arg!(--"max-size" <SIZE> "The largest response accepted, e.g. 512kB or 10MiB")
+                 .value_parser(value_parser!(ByteSize))
+                 .default_value("10MiB")))
+        .subcommand(
+            Command::new("config")
+            .about("Manage the configuration of the application")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new("auth")
+                .about("Show the start of the API token, or store or delete it in the keyring of the platform")
+                .arg(arg!(--token <TOKEN> "Store the token in the keyring")
+                     .conflicts_with("delete"))
+                .arg(arg!(--delete "Delete the token from the keyring")))
+            .subcommand(
+                Command::new("validate")
+                .about("Check the configuration file against the schema of the settings without running anything")
+                .arg(arg!([FILE] "The file to check, by default config.toml in the configuration directory")
+                     .value_parser(value_parser!(PathBuf))))
+            .subcommand(
+                Command::new("migrate")
+                .about("Upgrade a configuration file written for an earlier release, keeping the original beside it")
+                .arg(arg!([FILE] "The file to upgrade, by default config.toml in the configuration directory")
+                     .value_parser(value_parser!(PathBuf)))
+                .arg(arg!(--"dry-run" "Show the changes as a diff without making them"))))
+        .subcommand(
+            Command::new("cache")
+            .about("Manage the cache of the results of expensive lookups")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new("clear")
+                .about("Remove every entry from the cache")))
+        .subcommand(
+            Command::new("run-script")
+            .about("Run the subcommands given on the lines of a script file in turn")
+            .arg(arg!(<FILE> "The script, with a command line without the name of the application on each line")
+                 .value_parser(value_parser!(PathBuf)))
+            .arg(arg!(--"keep-going" "Run the rest of the commands after one fails, and report all the failures at the end")))
+        .subcommand(
+            Command::new("completions")
+            .about("Print the completion script for a shell, or install it where the shell loads it")
+            .arg(arg!(<SHELL> "The shell for which to generate the completions")
+                 .value_parser(completions::SHELLS))
+            .arg(arg!(--install "Install the script in the per-user location for the shell instead of printing it")))
+        .subcommand(
+            Command::new("init")
+            .about("Rename a project cloned from the template, replacing the current name throughout")
+            .arg(arg!(--name <NAME> "The new name of the project").required(true))
+            .arg(arg!(--"dry-run" "Show the changes as a diff without making them"))
+            .arg(arg!(--path <DIR> "The root directory of the project")
+                 .value_parser(value_parser!(PathBuf))
+                 .default_value(".")))
+        .subcommand(
+            Command::new("generate")
+            .about("Generate code in the project")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new("error-variant")
+                .about("Add a variant wrapping another error type to the Error enum in tyg_errors/src/lib.rs")
+                .arg(arg!(<NAME> "The name of the variant"))
+                .arg(arg!(--wraps <TYPE> "The error type wrapped by the variant").required(true))
+                .arg(arg!(--path <DIR> "The root directory of the project")
+                     .value_parser(value_parser!(PathBuf))
+                     .default_value("."))))
+        .subcommand(
+            Command::new("changelog")
+            .about("Show the changes in the releases since the version that last showed them")
+            .arg(arg!(--since <VERSION> "Show the changes in the releases after this version")))
+        .subcommand(
+            Command::new("history")
+            .about("Show the runs recorded in the audit log, which is written when TYG_AUDIT_LOG is set")
+            .arg(arg!(--subcommand <NAME> "Show only the runs of this subcommand")
+                 .complete_with(audit::subcommands))
+            .arg(arg!(--failed "Show only the runs that failed"))
+            .arg(arg!(--limit <COUNT> "Show only this number of the latest runs")
+                 .value_parser(value_parser!(usize))))
+        .subcommand(
+            Command::new("dump-cli-schema")
+            .about("Print a JSON description of the command line interface for external tools")
+            .hide(true))
+        .subcommand(
+            Command::new("__update-check")
+            .about("Ask the release endpoint for the latest version and cache the answer, for the update check")
+            .hide(true))
+        .subcommand(
+            Command::new("__complete")
+            .about("List the run time completions of the last word, for use by the completion scripts")
+            .hide(true)
+            .arg(arg!([WORDS] ... "The words of the command line being completed")
+                 .trailing_var_arg(true)
+                 .allow_hyphen_values(true)));
+    // The subcommands of the plugins are added last (see the plugin module)
+    plugin::merge(deprecation::apply(command, DEPRECATED))
 }
 
-/// Process the command line using clap
+// The flags and subcommands that have been renamed. The old names still work, with a warning, until
+// they are removed in the given release (see the deprecation module).
+const DEPRECATED: &[Deprecated] = &[
+    Deprecated { command: "fail", old: "--no-location", new: "--bare", removal: "0.2.0" },
+];
+
+// The exit codes of the subcommands whose findings or errors exit with codes of their own (see the
+// outcome module).
+const EXIT_CODES: &[ExitCodes] = &[
+    ExitCodes { subcommand: "check", findings: 1, error: Some(2) },
+];
+
+// The names under which the binary runs a subcommand when it is installed as links to itself (see
+// the multicall module).
+const APPLETS: &[Applet] = &[
+    Applet { name: "tyg-check", subcommand: "check" },
+    Applet { name: "tyg-fetch", subcommand: "fetch" },
+    Applet { name: "tyg-run", subcommand: "run-script" },
+    Applet { name: "tyg-validate", subcommand: "config validate" },
+];
+
+// Warn about any deprecated names used on the command line, in the error format of the run,
+// returning the number of warnings
+fn warn_deprecated(args: &[OsString], streams: &mut Streams) -> Result<usize> {
+    let warnings = deprecation::warnings(args, DEPRECATED);
+    for warning in &warnings {
+        writeln!(streams.err, "{}", ErrorFormat::selected().warning(env!("CARGO_PKG_NAME"), warning))?;
+    }
+    Ok(warnings.len())
+}
+
+/// Process the command line using clap, returning the outcome of the subcommand, which gives the
+/// exit code of the process with [`Outcome::exit_code`]
 ///
 /// # Example
 /// ```no_run
@@ -124,30 +827,331 @@ fn cli() -> Command<'static> {
 ///
 /// println!("{:?}", answer);
 /// ```
-pub fn run() -> Result<()> {
-    let matches = cli().get_matches();
+pub fn run() -> Result<Outcome> {
+    exit::install_panic_hook();
+    // Failing to handle the signals only leaves the process to end without its cleanup
+    #[cfg(unix)]
+    let _ = exit::terminate_on_signal();
+    platform::init_console();
+    // A link named after an applet runs its subcommand
+    let args = multicall::expand(std::env::args_os().collect(), APPLETS);
+    if multicall::list_requested(&args) {
+        multicall::list(APPLETS, &mut io::stdout())?;
+        return Ok(Outcome::Success);
+    }
+    // The .env file is loaded first, as it may set the variables read by the rest of the start up
+    let dotenv = dotenv::load_from_args(&args)?;
+    let locale = Locale::from_args(&args)?;
+    let mut command = locale.translate(cli());
+    // Plain output, e.g. on a dumb terminal, in CI or in the accessible mode, has no colors
+    if accessibility::requested(&args) || terminal::plain() {
+        command = command.color(ColorChoice::Never);
+    }
+    let matches = command.get_matches_from(&args);
+    globals::select(Globals::from_matches(&args, &matches, EXIT_CODES)?);
+    // The limits apply to the whole process, so they are only enforced here
+    limits::start(Limits {
+        memory: matches.get_one::<ByteSize>("max-memory").copied(),
+        runtime: matches.get_one::<HumanDuration>("max-runtime").copied(),
+    })?;
+    let mut streams = Streams::stdio();
+
+    // Failing to notify the service manager is not a reason to stop the application
+    #[cfg(feature = "systemd")]
+    let _ = systemd::notify("READY=1");
+    // Failing to handle the signal only loses the dump of the progress it asks for
+    #[cfg(all(unix, feature = "signals"))]
+    let _ = progress::dump_on_signal();
+    let update = update::Check::start(matches.subcommand_name());
+    let outcome = session(&args, &matches, dotenv, &mut streams)?;
+    // The notice of a newer release is only given after a successful run
+    if outcome.error().is_none() {
+        update.finish(&mut streams.err);
+    }
+    #[cfg(feature = "systemd")]
+    let _ = systemd::notify("STOPPING=1");
+    match outcome {
+        Outcome::Err(e) => Err(e),
+        outcome => Ok(outcome),
+    }
+}
 
+/// Process the given command line arguments, writing any output to the given streams
+///
+/// Unlike [`run`], the process is never terminated by a command line parsing error. Help and
+/// version requests are written to the output stream and any other parsing error is returned as an
+/// [`Error::Cli`]. The first argument is taken to be the name of the binary, or of an
+/// [applet](multicall) that runs a subcommand.
+///
+/// The global options apply to the run as they do to [`run`], entered on the calling thread for its
+/// duration (see [`globals`]), except for the resource limits, the check for a newer release and
+/// the other concerns of the whole process, which are left to [`run`].
+///
+/// # Example
+/// ```
+/// use tyg_template::{Error, Streams};
+///
+/// let mut out = Vec::new();
+/// let mut err = Vec::new();
+/// let answer = tyg_template::run_with_args(["tyg_template", "recursive_fail"],
+///                                          &mut Streams::new(&mut out, &mut err));
+///
+/// assert!(answer.is_err());
+/// assert!(String::from_utf8_lossy(&out).contains("Cycle 4"));
+///
+/// let answer = tyg_template::run_with_args(["tyg_template", "no_such_command"],
+///                                          &mut Streams::new(&mut out, &mut err));
+///
+/// assert!(matches!(answer, Err(Error::Cli(_))));
+/// ```
+pub fn run_with_args<I, T>(args: I, streams: &mut Streams) -> Result<Outcome>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    run_with_globals(args, streams).1
+}
+
+// Process the given command line arguments as run_with_args does, returning the global options of
+// the run with its result, so that an error can be reported as the application reports it
+pub(crate) fn run_with_globals<I, T>(args: I, streams: &mut Streams) -> (Arc<Globals>, Result<Outcome>)
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let args = multicall::expand(args.into_iter().map(Into::into).collect(), APPLETS);
+    let mut globals = globals::current();
+    let mut run = || {
+        let dotenv = dotenv::load_from_args(&args)?;
+        let Some(matches) = try_matches(&args, streams)? else {
+            return Ok(Outcome::Success);
+        };
+        globals = Arc::new(Globals::from_matches(&args, &matches, EXIT_CODES)?);
+        let _entered = globals::enter(globals.clone());
+        session(&args, &matches, dotenv, streams)
+    };
+    let result = match run() {
+        Ok(Outcome::Err(e)) => Err(e),
+        result => result,
+    };
+    (globals, result)
+}
+
+// Run the subcommand of a parsed command line, with the global options of the run selected, and
+// do what the global options ask for around it: the warnings about deprecated names, the report of
+// the .env file loaded, the audit log, the metrics and the summary
+fn session(args: &[OsString], matches: &ArgMatches, dotenv: Option<dotenv::Loaded>, streams: &mut Streams) -> Result<Outcome> {
+    let warnings = warn_deprecated(args, streams)?;
+    if matches.get_flag("debug") {
+        match dotenv {
+            Some(loaded) => writeln!(streams.err, "dotenv: {} loaded, setting {}", loaded.path.display(),
+                                     if loaded.set.is_empty() { "nothing".to_string() } else { loaded.set.join(", ") })?,
+            None => writeln!(streams.err, "dotenv: No .env file loaded")?,
+        }
+    }
+
+    let started = (clock::now(), Instant::now());
+    let outcome = Outcome::from(dispatch(matches, streams));
+    // Write any buffered output, reporting a failure unless the command has already failed
+    let outcome = match streams.out.flush() {
+        Err(e) if outcome.error().is_none() => Outcome::Err(e.into()),
+        _ => outcome,
+    };
+    // The background check for a newer release is not a command of the user, so it is not counted
+    if matches.subcommand_name() != Some("__update-check") {
+        audit::record(args, matches.subcommand_name().unwrap_or_default(), started.0, started.1.elapsed(),
+                      outcome.exit_code(), &mut streams.err);
+        #[cfg(feature = "metrics")]
+        metrics::export(&metrics::Invocation {
+            subcommand: matches.subcommand_name().unwrap_or_default(),
+            duration: started.1.elapsed(),
+            warnings,
+            error: outcome.error(),
+        }, &mut streams.err);
+    }
+    if let Some(format) = matches.get_one::<String>("summary") {
+        summary::write(&summary::Summary {
+            subcommand: matches.subcommand_name().unwrap_or_default(),
+            duration: started.1.elapsed(),
+            warnings,
+            outcome: &outcome,
+        }, format, matches.get_one::<PathBuf>("summary-file").map(PathBuf::as_path), &mut streams.err);
+    }
+    Ok(outcome)
+}
+
+// Parse the command line without terminating the process. Help, version and applet list requests
+// are written to the output stream, leaving no matches, and any other parsing error is returned.
+fn try_matches(args: &[OsString], streams: &mut Streams) -> Result<Option<ArgMatches>> {
+    if multicall::list_requested(args) {
+        multicall::list(APPLETS, &mut streams.out)?;
+        return Ok(None);
+    }
+    let locale = Locale::from_args(args)?;
+    match locale.translate(cli()).try_get_matches_from(args) {
+        Ok(matches) => Ok(Some(matches)),
+        Err(e) => match e.kind() {
+            CliErrorKind::DisplayHelp | CliErrorKind::DisplayVersion => {
+                write!(streams.out, "{}", e)?;
+                Ok(None)
+            }
+            _ => Err(e.into()),
+        },
+    }
+}
+
+// The dispatch function calls the handler for the subcommand selected on the command line. You
+// will need to modify this to suit your own application. A handler with findings to report returns
+// its outcome, which is mapped to the exit code of the subcommand (see EXIT_CODES).
+fn dispatch(matches: &ArgMatches, streams: &mut Streams) -> Result<Outcome> {
     match matches.subcommand() {
         Some(("fail", sub_matches)) => {
-            let bare = sub_matches.is_present("bare");
-            let _ = error_demo(bare)?;
-            println!("This should not be displayed because an error was forced...");
+            let bare = sub_matches.get_flag("bare");
+            error_demo(bare)?;
+            writeln!(streams.out, "This should not be displayed because an error was forced...")?;
         }
         Some(("recursive_fail", _sub_matches)) => {
-            let _ = recursive_fail_demo()?;
-            println!("This should not be displayed because an error was forced...");
+            recursive_fail_demo(&mut streams.out)?;
+            writeln!(streams.out, "This should not be displayed because an error was forced...")?;
         }
         Some(("file_fail", sub_matches)) => {
-            let better = sub_matches.is_present("better");
-            let path = sub_matches.value_of_os("PATH").ok_or_else(|| option_err!("No path specified"))?;
-            let _ = file_fail_demo(better, path)?;
-            println!("Now see what happens when an invalid file is entered");
+            let better = sub_matches.get_flag("better");
+            let path = sub_matches.get_one::<OsString>("PATH").ok_or_else(|| option_err!("No path specified"))?;
+            file_fail_demo(better, path)?;
+            writeln!(streams.out, "Now see what happens when an invalid file is entered")?;
+        }
+        Some(("parallel_fail", _sub_matches)) => {
+            parallel_fail_demo(&mut streams.out)?;
+            writeln!(streams.out, "This should not be displayed because an error was forced...")?;
+        }
+        Some(("check", sub_matches)) => {
+            let paths: Vec<PathBuf> = sub_matches.get_many::<PathBuf>("PATH").into_iter().flatten().cloned().collect();
+            return check_demo(&paths, &mut streams.out);
+        }
+        Some(("fetch", sub_matches)) => {
+            let url = sub_matches.get_one::<HttpUrl>("URL").ok_or_else(|| option_err!("No URL specified"))?;
+            let timeout = sub_matches.get_one::<HumanDuration>("timeout").ok_or_else(|| option_err!("No timeout specified"))?;
+            let limit = sub_matches.get_one::<ByteSize>("max-size").ok_or_else(|| option_err!("No size specified"))?;
+            fetch::fetch(url, timeout.duration(), *limit, &mut streams.out)?;
+        }
+        Some(("config", sub_matches)) => match sub_matches.subcommand() {
+            Some(("auth", sub_matches)) => {
+                let token = sub_matches.get_one::<String>("token");
+                secrets::auth(token.map(String::as_str), sub_matches.get_flag("delete"), &mut streams.out)?;
+            }
+            Some(("validate", sub_matches)) => {
+                let path = sub_matches.get_one::<PathBuf>("FILE");
+                return config::validate_command(path.map(PathBuf::as_path), &mut streams.out);
+            }
+            Some(("migrate", sub_matches)) => {
+                let path = sub_matches.get_one::<PathBuf>("FILE");
+                config::migrate_command(path.map(PathBuf::as_path), sub_matches.get_flag("dry-run"), &mut streams.out)?;
+            }
+            _ => unreachable!(),
+        },
+        Some(("cache", sub_matches)) => match sub_matches.subcommand() {
+            Some(("clear", _sub_matches)) => {
+                cache::clear_command(&mut streams.out)?;
+            }
+            _ => unreachable!(),
+        },
+        Some(("run-script", sub_matches)) => {
+            let path = sub_matches.get_one::<PathBuf>("FILE").ok_or_else(|| option_err!("No script specified"))?;
+            script::run(path, sub_matches.get_flag("keep-going"), streams, &mut run_script_command)?;
+        }
+        Some(("completions", sub_matches)) => {
+            let shell = sub_matches.get_one::<String>("SHELL").ok_or_else(|| option_err!("No shell specified"))?;
+            if sub_matches.get_flag("install") {
+                completions::install(shell, &mut streams.out)?;
+            } else {
+                completions::generate(shell, &mut streams.out)?;
+            }
+        }
+        Some(("init", sub_matches)) => {
+            let name = sub_matches.get_one::<String>("name").ok_or_else(|| option_err!("No name specified"))?;
+            let path = sub_matches.get_one::<PathBuf>("path").ok_or_else(|| option_err!("No path specified"))?;
+            let dry_run = sub_matches.get_flag("dry-run");
+            if !dry_run {
+                prompt::confirm_stdin(&format!("Rename the project in {} to {}?", path.display(), name), &mut streams.err)?;
+            }
+            init::init(path, name, dry_run, &mut streams.out)?;
+        }
+        Some(("generate", sub_matches)) => match sub_matches.subcommand() {
+            Some(("error-variant", sub_matches)) => {
+                let name = sub_matches.get_one::<String>("NAME").ok_or_else(|| option_err!("No name specified"))?;
+                let wraps = sub_matches.get_one::<String>("wraps").ok_or_else(|| option_err!("No type specified"))?;
+                let path = sub_matches.get_one::<PathBuf>("path").ok_or_else(|| option_err!("No path specified"))?;
+                generate::error_variant(path, name, wraps, &mut streams.out)?;
+            }
+            _ => unreachable!(),
+        },
+        Some(("changelog", sub_matches)) => {
+            changelog::changelog(sub_matches.get_one::<String>("since").map(String::as_str), &mut streams.out)?;
+        }
+        Some(("history", sub_matches)) => {
+            let filter = audit::Filter {
+                subcommand: sub_matches.get_one::<String>("subcommand").cloned(),
+                failed: sub_matches.get_flag("failed"),
+                limit: sub_matches.get_one::<usize>("limit").copied(),
+            };
+            audit::history(&filter, &mut streams.out)?;
+        }
+        Some(("dump-cli-schema", _sub_matches)) => {
+            schema::dump(&mut streams.out)?;
+        }
+        Some(("__update-check", _sub_matches)) => {
+            update::refresh()?;
+        }
+        Some(("__complete", sub_matches)) => {
+            let words: Vec<String> = sub_matches.get_many::<String>("WORDS").into_iter().flatten().cloned().collect();
+            completions::complete(&words, &mut streams.out)?;
+        }
+        Some((name, sub_matches)) if plugin::provides(name) => {
+            return plugin::run(name, sub_matches, streams);
         }
         _ => unreachable!(), // If all subcommands are defined above, anything else is unreachabe!()
     }
 
     // Continued program logic goes here...
-    Ok(())
+    Ok(Outcome::Success)
+}
+
+// The global options that apply to the whole run, which cannot be given on a line of a script
+const RUN_OPTIONS: &[&str] = &["debug", "no-dotenv", "lang", "summary", "summary-file", "max-memory", "max-runtime"];
+
+// Run a command of a script, which is parsed as if it were given after the name of the application,
+// with the global options given on its line entered on top of those of the run
+fn run_script_command(words: &[String], streams: &mut Streams) -> Result<()> {
+    let args = std::iter::once(env!("CARGO_PKG_NAME")).chain(words.iter().map(String::as_str));
+    let matches = match cli().try_get_matches_from(args) {
+        Ok(matches) => matches,
+        Err(e) => match e.kind() {
+            CliErrorKind::DisplayHelp | CliErrorKind::DisplayVersion => {
+                write!(streams.out, "{}", e)?;
+                return Ok(());
+            }
+            // Only the first line of the error is kept, as the usage would break up the summary
+            _ => {
+                let message = e.to_string();
+                let line = message.lines().next().unwrap_or_default();
+                return result_err_bare!("{}", line.trim_start_matches("error: "));
+            }
+        },
+    };
+    if matches.subcommand_name() == Some("run-script") {
+        return result_err_bare!("A script cannot run another script");
+    }
+    // The options that apply to the whole run are taken from the command line of the script
+    if let Some(option) = RUN_OPTIONS.iter().find(|id| matches.value_source(id) == Some(ValueSource::CommandLine)) {
+        return result_err_bare!("--{} applies to the whole run, give it before run-script", option);
+    }
+    let args: Vec<OsString> = words.iter().map(OsString::from).collect();
+    let _entered = globals::enter(Arc::new(globals::current().within(&args, &matches)?));
+    // A command that finds problems fails, so that the script stops unless it keeps going
+    match dispatch(&matches, streams)? {
+        Outcome::Findings(n) if n > 0 => result_err_bare!("{} problems found", n),
+        _ => Ok(()),
+    }
 }
 
 /// Generate a custom error
@@ -155,6 +1159,9 @@ pub fn run() -> Result<()> {
 /// # Examples
 /// ```
 /// use tyg_template;
+/// use tyg_template::testkit::strip_locations;
+///
+/// let message = "Error thrown to demonstrate the error handling process";
 ///
 /// // Show error message and its location
 /// let answer = tyg_template::error_demo(false);
@@ -169,6 +1176,10 @@ pub fn run() -> Result<()> {
 ///
 /// assert!(answer.is_err());
 /// println!("{:?}", answer);
+///
+/// // Either way, the message is the same once the location has been removed
+/// assert_eq!(strip_locations(&tyg_template::error_demo(false).unwrap_err().to_string()), message);
+/// assert_eq!(strip_locations(&tyg_template::error_demo(true).unwrap_err().to_string()), message);
 /// ```
 pub fn error_demo(bare: bool) -> Result<()> {
     if bare {
@@ -216,19 +1227,22 @@ impl Iterator for Counter {
 /// ```
 /// use tyg_template;
 ///
+/// use tyg_template::testkit::strip_locations;
+///
 /// // Show error message and its location
-/// let answer = tyg_template::recursive_fail_demo();
+/// let answer = tyg_template::recursive_fail_demo(&mut std::io::stdout());
 ///
 /// assert!(answer.is_err());
 /// println!("{:?}", answer);
+/// assert_eq!(strip_locations(&answer.unwrap_err().to_string()), "Failed at cycle 5");
 /// ```
-pub fn recursive_fail_demo() -> Result<()> {
-    println!("We need to fail at cycle 5");
+pub fn recursive_fail_demo(out: &mut dyn Write) -> Result<()> {
+    writeln!(out, "We need to fail at cycle 5")?;
     let counter = Counter::new();
     for n in counter {
         // The ? will trap the error and return it to the calling context
         // If the n value was ok then extract it and then print to screen
-        println!("Cycle {}", n?);
+        writeln!(out, "Cycle {}", n?)?;
     }
     Ok(())
 }
@@ -239,6 +1253,7 @@ pub fn recursive_fail_demo() -> Result<()> {
 /// ```
 /// use std::ffi::OsString;
 /// use tyg_template;
+/// use tyg_template::testkit::strip_locations;
 ///
 /// let file_name = OsString::from("Non-existent file name"); 
 ///
@@ -253,14 +1268,130 @@ pub fn recursive_fail_demo() -> Result<()> {
 ///
 /// assert!(answer.is_err());
 /// println!("{:?}", answer);
+/// assert!(strip_locations(&answer.unwrap_err().to_string()).starts_with("Non-existent file name: "));
 /// ```
 pub fn file_fail_demo(better: bool, path: &OsStr) -> Result<()> {
     let file = File::open(path);
     if better {
-        // do something a bit better
-        file.or_else(|e| result_err!("{}: {}", path.to_string_lossy(), e))?;
+        // do something a bit better, adding the path and location to the error
+        file.msg(|| path.to_string_lossy())?;
     } else {
         file?;
     }
     Ok(())
 }
+
+// The number of threads run by the parallel_fail demo
+const WORKERS: u32 = 4;
+
+/// Generate errors and a panic in threads run in parallel, gathering them into a single error
+///
+/// Each worker runs in a named scoped thread. The error returned by a worker, and the panic of a
+/// worker caught when it is joined, are collected in an `ErrorCollection` with the name of the
+/// thread as their context, so that every failure is reported rather than only the first.
+///
+/// # Examples
+/// ```
+/// use tyg_template;
+///
+/// use tyg_template::testkit::strip_locations;
+///
+/// let mut out = Vec::new();
+/// let answer = tyg_template::parallel_fail_demo(&mut out);
+///
+/// assert_eq!(strip_locations(&answer.unwrap_err().to_string()),
+///            "2 of 4 failed:\n  worker-2: Failed to process batch 2\n  worker-3: Panicked: Batch 3 is corrupt");
+/// assert!(String::from_utf8(out).unwrap().ends_with("worker-1 summed to 55\nworker-4 summed to 820\n"));
+/// ```
+pub fn parallel_fail_demo(out: &mut dyn Write) -> Result<()> {
+    writeln!(out, "We need worker-2 to fail and worker-3 to panic")?;
+    let mut errors = ErrorCollection::new();
+    thread::scope(|scope| {
+        // Start every worker before joining any, so that they run in parallel
+        let handles: Vec<_> = (1..=WORKERS)
+            .map(|n| {
+                let name = format!("worker-{}", n);
+                let handle = thread::Builder::new()
+                    .name(name.clone())
+                    .spawn_scoped(scope, move || batch(n));
+                (name, handle)
+            })
+            .collect();
+        for (name, handle) in handles {
+            let result = handle
+                .bare(|| "Cannot start the thread")
+                .and_then(|handle| handle.join().unwrap_or_else(|payload| Err(panicked(payload))));
+            if let Some(sum) = errors.record(&name, result) {
+                writeln!(out, "{} summed to {}", name, sum)?;
+            }
+        }
+        Ok::<_, Error>(())
+    })?;
+    errors.into_result()
+}
+
+// The work of a thread of the parallel_fail demo, which fails for the second batch and panics for
+// the third
+fn batch(n: u32) -> Result<u32> {
+    match n {
+        2 => result_err!("Failed to process batch {}", n),
+        3 => panic!("Batch {} is corrupt", n),
+        _ => Ok((1..=n * 10).sum()),
+    }
+}
+
+// Convert the payload of a panic caught when joining a thread into an error
+fn panicked(payload: Box<dyn Any + Send>) -> Error {
+    let message = match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => payload
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| "The thread panicked".to_string()),
+    };
+    Error::Error(format!("Panicked: {}", message)).with_kind(ErrorKind::Internal)
+}
+
+/// Check that the files exist and are not empty, reporting those that are not as findings
+///
+/// A file that cannot be checked, e.g. for want of permission, is an error rather than a finding,
+/// as the check would be incomplete. The `check` subcommand exits with 1 when there are findings
+/// and with 2 on an error (see the `outcome` module).
+///
+/// # Examples
+/// ```
+/// use std::path::PathBuf;
+/// use tyg_template::outcome::Outcome;
+/// use tyg_template::testkit::TempProject;
+///
+/// let _project = TempProject::builder()
+///     .file("full.txt", "data")
+///     .file("empty.txt", "")
+///     .create()
+///     .unwrap();
+/// let paths = ["full.txt", "empty.txt", "missing.txt"].map(PathBuf::from);
+/// let mut out = Vec::new();
+/// let outcome = tyg_template::check_demo(&paths, &mut out).unwrap();
+///
+/// assert!(matches!(outcome, Outcome::Findings(2)));
+/// assert_eq!(String::from_utf8(out).unwrap(),
+///            "empty.txt: Is empty\nmissing.txt: Does not exist\n2 of 3 files failed the check\n");
+/// ```
+pub fn check_demo(paths: &[PathBuf], out: &mut dyn Write) -> Result<Outcome> {
+    let mut findings = 0;
+    for path in paths {
+        let problem = match fs::metadata(platform::long_path(path)) {
+            Ok(metadata) if metadata.is_dir() => Some("Is a directory"),
+            Ok(metadata) if metadata.len() == 0 => Some("Is empty"),
+            Ok(_) => None,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Some("Does not exist"),
+            Err(e) => return Err(e).bare(|| path.display()),
+        };
+        if let Some(problem) = problem {
+            findings += 1;
+            writeln!(out, "{}: {}", path.display(), problem)?;
+        }
+    }
+    writeln!(out, "{} of {} files failed the check", findings, paths.len())?;
+    Ok(Outcome::Findings(findings))
+}
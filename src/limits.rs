@@ -0,0 +1,130 @@
+//! Limits on the resources of a run, so that a runaway run fails predictably rather than being
+//! killed by the operating system when it runs out of memory, or left running.
+//!
+//! The global `--max-memory` and `--max-runtime` options set the limits, which [`start`] enforces
+//! with a watchdog thread that polls the time since the start and the resident memory of the
+//! process. Once a limit is exceeded, [`check`] returns an `Error::ResourceLimit` naming it, so
+//! that work which checks now and then, such as a script between its commands, stops through the
+//! usual path of an error. Work that does not check is stopped by the watchdog a second later:
+//! it reports the error, runs the handlers registered with [`exit::on_exit`] and ends the process
+//! with the exit code of the error, 75 unless the subcommand has its own, with
+//! [`exit::terminate`]. Output buffered by the work is then lost.
+//!
+//! ```text
+//! tyg_template: Exceeded the runtime limit of 10m given by --max-runtime
+//! ```
+//!
+//! The resident memory is measured on Linux and Windows, and a memory limit is refused on other
+//! platforms. The memory is measured between two polls, so a burst of allocations can go beyond
+//! the limit before the watchdog notices it.
+
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::outcome::Outcome;
+use crate::units::{ByteSize, HumanDuration};
+use crate::{exit, platform, result_err_bare, Error, ErrorFormat, Result, ResultExt};
+
+// The time between two polls of the watchdog
+const POLL: Duration = Duration::from_millis(100);
+
+// The time given to work that checks the limits to stop before the watchdog stops the process
+const GRACE: Duration = Duration::from_secs(1);
+
+// The message naming the limit exceeded, once one has been
+static EXCEEDED: OnceLock<String> = OnceLock::new();
+
+/// The limits on the resources of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// The most resident memory the run may use.
+    pub memory: Option<ByteSize>,
+    /// The longest time the run may take.
+    pub runtime: Option<HumanDuration>,
+}
+
+impl Limits {
+    /// The limit exceeded by a run that has taken the given time and uses the given resident
+    /// memory in bytes, if any, as a message naming it.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tyg_template::limits::Limits;
+    ///
+    /// let limits = Limits { memory: Some("64MiB".parse().unwrap()), runtime: Some("1m".parse().unwrap()) };
+    ///
+    /// assert_eq!(limits.exceeded(Duration::from_secs(30), Some(1024 * 1024)), None);
+    /// assert_eq!(limits.exceeded(Duration::from_secs(61), None).unwrap(),
+    ///            "Exceeded the runtime limit of 1m given by --max-runtime");
+    /// assert_eq!(limits.exceeded(Duration::ZERO, Some(100 * 1024 * 1024)).unwrap(),
+    ///            "Exceeded the memory limit of 64 MiB given by --max-memory, using 100.0 MiB");
+    /// ```
+    pub fn exceeded(&self, elapsed: Duration, memory: Option<u64>) -> Option<String> {
+        if let Some(runtime) = self.runtime.filter(|runtime| elapsed > runtime.duration()) {
+            return Some(format!(
+                "Exceeded the runtime limit of {} given by --max-runtime",
+                runtime
+            ));
+        }
+        match (self.memory, memory) {
+            (Some(limit), Some(memory)) if memory > limit.bytes() => Some(format!(
+                "Exceeded the memory limit of {} given by --max-memory, using {:.1} MiB",
+                limit,
+                memory as f64 / (1024.0 * 1024.0)
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Enforce the limits from now on, with a watchdog thread, unless there are none. A memory limit
+/// is refused where the memory cannot be measured.
+pub fn start(limits: Limits) -> Result<()> {
+    if limits == Limits::default() {
+        return Ok(());
+    }
+    if let Some(memory) = limits.memory {
+        if platform::resident_memory().is_none() {
+            return result_err_bare!(
+                "--max-memory {}: The memory cannot be measured on this platform",
+                memory
+            );
+        }
+    }
+    let started = Instant::now();
+    thread::Builder::new()
+        .name("limits-watchdog".to_string())
+        .spawn(move || watch(limits, started))
+        .bare(|| "Cannot start the thread enforcing the resource limits")?;
+    Ok(())
+}
+
+/// Fail with an `Error::ResourceLimit` if a limit has been exceeded. Long running work should
+/// call this now and then, so that it stops cleanly.
+pub fn check() -> Result<()> {
+    match EXCEEDED.get() {
+        Some(message) => Err(Error::ResourceLimit(message.as_str().into())),
+        None => Ok(()),
+    }
+}
+
+// Poll the resources until a limit is exceeded, and stop the process if the work has not stopped
+// by the end of the grace period
+fn watch(limits: Limits, started: Instant) {
+    let message = loop {
+        thread::sleep(POLL);
+        if let Some(message) = limits.exceeded(started.elapsed(), platform::resident_memory()) {
+            break message;
+        }
+    };
+    let _ = EXCEEDED.set(message.clone());
+    thread::sleep(GRACE);
+    let error = Error::ResourceLimit(message.into());
+    eprintln!(
+        "{}",
+        ErrorFormat::selected().error(env!("CARGO_PKG_NAME"), &error)
+    );
+    exit::terminate(Outcome::Err(error).exit_code());
+}
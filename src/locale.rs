@@ -0,0 +1,298 @@
+//! Translation of the help shown for the command line.
+//!
+//! The English text of the command line interface is compiled in and is used whenever no
+//! translation is available. Translations are read at run time from locale bundles, which are text
+//! files named after their language, e.g. `fr.txt` or `pt_BR.txt`, in the `locales` directory of
+//! the data directory of the application. On Linux this is
+//! `~/.local/share/<name>/locales`, or `$XDG_DATA_HOME/<name>/locales` when `XDG_DATA_HOME` is set.
+//!
+//! Each line of a bundle holds a key and its translation separated by `=`. Blank lines and lines
+//! starting with `#` are ignored, and `\n` in a translation starts a new line. The keys name the
+//! text being translated:
+//!
+//! - `about` is the description of the application and `examples` its EXAMPLES section.
+//! - The name of a subcommand is its description, e.g. `fail`, and the name of a nested subcommand
+//!   is joined to its parent by a `.`, e.g. `generate.error-variant`.
+//! - An argument is named after its subcommand, or on its own for the arguments of the application,
+//!   e.g. `debug`, `fail.bare` or `file_fail.PATH`.
+//! - The EXAMPLES section of a subcommand is named after it, e.g. `fail.examples`.
+//!
+//! `{bin}` in a translation is replaced by the name of the application. Text without a
+//! translation in the bundle is shown in English.
+//!
+//! ```text
+//! # French
+//! about = Une démonstration d'une application en ligne de commande
+//! fail = Montrer comment renvoyer une erreur
+//! fail.bare = Afficher l'erreur sans le fichier source ni le numéro de ligne
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use clap::Command;
+
+use crate::platform::long_path;
+use crate::{result_err_bare, Result};
+
+// The language of the compiled in text
+const ENGLISH: &str = "en";
+
+/// The language in which the help for the command line is shown.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use tyg_template::locale::Locale;
+/// use tyg_template::testkit::TempProject;
+///
+/// let _project = TempProject::builder().create().unwrap();
+/// let directory = Locale::directory().unwrap();
+/// fs::create_dir_all(&directory).unwrap();
+/// fs::write(directory.join("fr.txt"), "# French\nfail = Montrer comment renvoyer une erreur\n")
+///     .unwrap();
+///
+/// let locale = Locale::load("fr_FR.UTF-8").unwrap();
+///
+/// assert_eq!(locale.language(), "fr");
+/// assert_eq!(locale.text("fail"), Some("Montrer comment renvoyer une erreur"));
+/// assert_eq!(locale.text("fail.bare"), None);
+/// assert!(Locale::load("xx").is_err());
+/// ```
+pub struct Locale {
+    language: String,
+    texts: HashMap<String, String>,
+}
+
+impl Locale {
+    /// The compiled in English locale.
+    pub fn english() -> Locale {
+        Locale {
+            language: ENGLISH.to_string(),
+            texts: HashMap::new(),
+        }
+    }
+
+    /// Load the locale bundle for the given language.
+    ///
+    /// The language is given in the form used by the `LANG` environment variable, e.g. `fr`,
+    /// `fr_FR` or `fr_FR.UTF-8`. The bundle for the language and territory is used if there is
+    /// one, otherwise the bundle for the language alone. English, `C` and `POSIX` select the
+    /// compiled in English text. An error is returned if there is no bundle for the language or
+    /// the bundle cannot be read.
+    pub fn load(language: &str) -> Result<Locale> {
+        // Remove the character set and modifier, e.g. fr_FR.UTF-8@euro becomes fr_FR
+        let tag = language.split(['.', '@']).next().unwrap_or_default();
+        let primary = tag.split(['_', '-']).next().unwrap_or_default();
+        if tag.is_empty() || tag == "C" || tag == "POSIX" || primary == ENGLISH {
+            return Ok(Locale::english());
+        }
+
+        let Some(directory) = Locale::directory() else {
+            return result_err_bare!(
+                "{}: No locale bundle found, the data directory is unknown",
+                language
+            );
+        };
+        let mut candidates = vec![tag];
+        if primary != tag {
+            candidates.push(primary);
+        }
+        for candidate in candidates {
+            let path = directory.join(format!("{}.txt", candidate));
+            let text = match fs::read_to_string(long_path(&path)) {
+                Ok(text) => text,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return result_err_bare!("{}: {}", path.display(), e),
+            };
+            return Ok(Locale {
+                language: candidate.to_string(),
+                texts: parse_bundle(&text)
+                    .or_else(|e| result_err_bare!("{}:{}", path.display(), e))?,
+            });
+        }
+        result_err_bare!(
+            "{}: No locale bundle found in {}",
+            language,
+            directory.display()
+        )
+    }
+
+    /// The locale selected by the environment.
+    ///
+    /// The language is taken from the first of the `LC_ALL`, `LC_MESSAGES` and `LANG` environment
+    /// variables that is set. The compiled in English locale is returned if none of them is set or
+    /// if the locale bundle for the language cannot be loaded, as the environment applies to every
+    /// application whether or not it has been translated.
+    pub fn from_env() -> Locale {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|language| Locale::load(&language).ok())
+            .unwrap_or_else(Locale::english)
+    }
+
+    /// The locale selected by the `--lang` option of a command line, or by the environment if the
+    /// option is not given.
+    ///
+    /// The command line is searched before it is parsed, so that the help can be translated. An
+    /// error is returned if the bundle for a language selected on the command line cannot be
+    /// loaded.
+    pub fn from_args(args: &[OsString]) -> Result<Locale> {
+        let mut args = args
+            .iter()
+            .skip(1)
+            .map(|arg| arg.to_string_lossy())
+            .take_while(|arg| arg != "--");
+        while let Some(arg) = args.next() {
+            if let Some(language) = arg.strip_prefix("--lang=") {
+                return Locale::load(language);
+            }
+            if arg == "--lang" {
+                return Locale::load(&args.next().unwrap_or_default());
+            }
+        }
+        Ok(Locale::from_env())
+    }
+
+    /// The directory containing the locale bundles, or `None` if the data directory is unknown.
+    pub fn directory() -> Option<PathBuf> {
+        let data = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| {
+                if cfg!(windows) {
+                    env::var_os("APPDATA").map(PathBuf::from)
+                } else {
+                    env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+                }
+            })?;
+        Some(data.join(env!("CARGO_PKG_NAME")).join("locales"))
+    }
+
+    /// The languages in which the help can be shown, i.e. English and the languages of the
+    /// locale bundles in the [`directory`](Locale::directory), in alphabetical order.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::fs;
+    /// use tyg_template::locale::Locale;
+    /// use tyg_template::testkit::TempProject;
+    ///
+    /// let _project = TempProject::builder().create().unwrap();
+    /// assert_eq!(Locale::languages(), ["en"]);
+    ///
+    /// let directory = Locale::directory().unwrap();
+    /// fs::create_dir_all(&directory).unwrap();
+    /// fs::write(directory.join("pt_BR.txt"), "").unwrap();
+    /// fs::write(directory.join("fr.txt"), "").unwrap();
+    ///
+    /// assert_eq!(Locale::languages(), ["en", "fr", "pt_BR"]);
+    /// ```
+    pub fn languages() -> Vec<String> {
+        let mut languages = vec![ENGLISH.to_string()];
+        if let Some(entries) =
+            Locale::directory().and_then(|directory| fs::read_dir(long_path(&directory)).ok())
+        {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|extension| extension == "txt") {
+                    if let Some(stem) = path.file_stem() {
+                        languages.push(stem.to_string_lossy().into_owned());
+                    }
+                }
+            }
+        }
+        languages.sort();
+        languages.dedup();
+        languages
+    }
+
+    /// The language of the locale.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The translation of the text with the given key, or `None` if there is no translation.
+    pub fn text(&self, key: &str) -> Option<&str> {
+        self.texts.get(key).map(String::as_str)
+    }
+
+    /// Replace the text of the command and its subcommands and arguments by the translations in
+    /// the locale. The command is returned untouched, without walking its tree, when the locale
+    /// has no translations, as for the compiled in English locale.
+    pub fn translate(&self, command: Command) -> Command {
+        if self.texts.is_empty() {
+            return command;
+        }
+        self.translate_command(command, "")
+    }
+
+    fn translate_command(&self, mut command: Command, path: &str) -> Command {
+        let about = if path.is_empty() { "about" } else { path };
+        if let Some(text) = self.translation(about) {
+            command = command.about(text);
+        }
+        if let Some(text) = self.translation(&join_key(path, "examples")) {
+            command = command.after_help(text);
+        }
+
+        let ids: Vec<String> = command
+            .get_arguments()
+            .map(|arg| arg.get_id().to_string())
+            .collect();
+        for id in ids {
+            if let Some(text) = self.translation(&join_key(path, &id)) {
+                command = command.mut_arg(id, |arg| arg.help(text));
+            }
+        }
+
+        let names: Vec<String> = command
+            .get_subcommands()
+            .map(|sub| sub.get_name().to_string())
+            .collect();
+        for name in names {
+            let sub_path = join_key(path, &name);
+            command = command.mut_subcommand(name, |sub| self.translate_command(sub, &sub_path));
+        }
+        command
+    }
+
+    // The translation of the text with the given key, ready for display
+    fn translation(&self, key: &str) -> Option<String> {
+        self.text(key)
+            .map(|text| text.replace("{bin}", env!("CARGO_PKG_NAME")))
+    }
+}
+
+fn join_key(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+// Parse the lines of a locale bundle, reporting the number of any line in error
+fn parse_bundle(text: &str) -> std::result::Result<HashMap<String, String>, String> {
+    let mut texts = HashMap::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!(
+                "{}: Expected a key and its translation separated by =",
+                number + 1
+            ));
+        };
+        texts.insert(key.trim().to_string(), value.trim().replace("\\n", "\n"));
+    }
+    Ok(texts)
+}
@@ -1,6 +1,28 @@
+use tyg_template::outcome::Outcome;
+use tyg_template::ERROR_PREFIX;
+
 fn main() {
-    match tyg_template::run() {
-        Ok(_) => println!("The process completed normally"),
-        Err(e) => eprintln!("tyg_template: {}", e),
+    let outcome = Outcome::from(tyg_template::run());
+    let code = outcome.exit_code();
+    match outcome {
+        // Reported on stderr so that it does not mix with output such as a completion script. The
+        // findings of a subcommand have been reported by the subcommand.
+        Outcome::Success | Outcome::Findings(_) => {
+            if tyg_template::accessibility::enabled() {
+                eprintln!("done: The process completed normally");
+            } else {
+                eprintln!("The process completed normally");
+            }
+        }
+        #[cfg(feature = "systemd")]
+        Outcome::Err(e) if tyg_template::systemd::report_error(ERROR_PREFIX, &e) => {}
+        Outcome::Err(e) => {
+            eprintln!(
+                "{}",
+                tyg_template::ErrorFormat::selected().error(ERROR_PREFIX, &e)
+            );
+        }
     }
+    // Leave by the exit module, so that the cleanup registered by the application is done
+    tyg_template::exit::exit(code)
 }
@@ -0,0 +1,100 @@
+//! Rendering of Markdown for the terminal.
+//!
+//! Only the parts of Markdown used by documents such as the changelog are rendered: headings,
+//! bullet lists, emphasis, code spans and links. When the output is styled, headings and strong
+//! emphasis are bold and code spans are in color, and otherwise the markers are removed, apart
+//! from the backticks of code spans, leaving plain text that reads well and suits screen readers.
+//! Anything else is written as it is.
+
+use clap::builder::styling::{AnsiColor, Style};
+
+/// Render the Markdown text for the terminal, styled or as plain text.
+///
+/// # Examples
+/// ```
+/// use tyg_template::markdown::render;
+///
+/// let text = "## [1.4.0]\n\n### Added\n\n- The **changelog** subcommand, see `--help`.\n\
+///             - [Docs](https://example.com)\n";
+///
+/// assert_eq!(render(text, false),
+///            "1.4.0\n\nAdded\n\n  - The changelog subcommand, see `--help`.\n  - Docs (https://example.com)\n");
+/// assert!(render(text, true).contains("\x1b[1m1.4.0\x1b[0m"));
+/// ```
+pub fn render(text: &str, styled: bool) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+            let heading = trimmed[hashes..].trim();
+            // A version in brackets, as in the headings of a changelog, is shown without them
+            let heading = heading
+                .strip_prefix('[')
+                .and_then(|heading| heading.strip_suffix(']'))
+                .unwrap_or(heading);
+            let style = bold(styled);
+            rendered.push_str(&format!("{style}{}{style:#}", inline(heading, false)));
+        } else if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            let indent = &line[..line.len() - trimmed.len()];
+            rendered.push_str(&format!("  {}- {}", indent, inline(item, styled)));
+        } else {
+            rendered.push_str(&inline(line, styled));
+        }
+        rendered.push('\n');
+    }
+    rendered
+}
+
+// Render the emphasis, code spans and links within a line
+fn inline(line: &str, styled: bool) -> String {
+    let mut rendered = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                let code = &rest[1..=end];
+                match styled {
+                    true => {
+                        let style = Style::new().fg_color(Some(AnsiColor::Cyan.into()));
+                        rendered.push_str(&format!("{style}{}{style:#}", code));
+                    }
+                    false => rendered.push_str(&format!("`{}`", code)),
+                }
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+        if let Some(strong) = rest.strip_prefix("**").or_else(|| rest.strip_prefix("__")) {
+            let marker = &rest[..2];
+            if let Some(end) = strong.find(marker) {
+                let style = bold(styled);
+                rendered.push_str(&format!("{style}{}{style:#}", &strong[..end]));
+                rest = &strong[end + 2..];
+                continue;
+            }
+        }
+        if c == '[' {
+            if let Some((text, after)) = rest[1..].split_once("](") {
+                if let Some((url, after)) = after.split_once(')') {
+                    rendered.push_str(&format!("{} ({})", inline(text, styled), url));
+                    rest = after;
+                    continue;
+                }
+            }
+        }
+        rendered.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    rendered
+}
+
+fn bold(styled: bool) -> Style {
+    match styled {
+        true => Style::new().bold(),
+        false => Style::new(),
+    }
+}
@@ -0,0 +1,209 @@
+//! Export of the counts of invocations, errors and warnings as Prometheus metrics.
+//!
+//! This module is available when the `metrics` feature is enabled. When the `TYG_METRICS_FILE`
+//! environment variable names a file, each invocation adds to the counters in that file, which is
+//! written in the text format read by the textfile collector of the Prometheus node exporter:
+//!
+//! - `<name>_invocations_total`, by subcommand.
+//! - `<name>_duration_seconds_total`, the time spent running each subcommand.
+//! - `<name>_errors_total`, by subcommand and kind of error, e.g. `file` or `network`.
+//! - `<name>_warnings_total`, by subcommand, e.g. for the use of deprecated flags.
+//!
+//! The file is replaced as a whole, so that the collector never reads it half written. A failure
+//! to export the metrics is reported as a warning and never changes the result of the command.
+//!
+//! ```text
+//! # TYPE tyg_template_invocations_total counter
+//! tyg_template_invocations_total{subcommand="fail"} 3
+//! # TYPE tyg_template_errors_total counter
+//! tyg_template_errors_total{subcommand="fail",kind="general"} 3
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::platform::long_path;
+use crate::{result_err_bare, Error, ErrorFormat, Result};
+
+/// The environment variable naming the file to which the metrics are written.
+pub const FILE_VAR: &str = "TYG_METRICS_FILE";
+
+// The metrics, with their help, in the order written
+const METRICS: &[(&str, &str)] = &[
+    (
+        "invocations_total",
+        "The number of times each subcommand was run",
+    ),
+    (
+        "duration_seconds_total",
+        "The time spent running each subcommand",
+    ),
+    (
+        "errors_total",
+        "The number of failed runs of each subcommand by kind of error",
+    ),
+    (
+        "warnings_total",
+        "The number of warnings shown by each subcommand",
+    ),
+];
+
+/// The outcome of an invocation of the application, to be added to the metrics.
+#[derive(Debug)]
+pub struct Invocation<'a> {
+    /// The name of the subcommand run.
+    pub subcommand: &'a str,
+    /// The time taken to run it.
+    pub duration: Duration,
+    /// The number of warnings shown.
+    pub warnings: usize,
+    /// The error returned, if the subcommand failed.
+    pub error: Option<&'a Error>,
+}
+
+/// The kind of an error, as given by the `kind` label of the error counter, which is the name of
+/// its [`ErrorKind`](crate::ErrorKind).
+///
+/// # Examples
+/// ```
+/// use std::io;
+/// use tyg_template::{metrics, Error};
+///
+/// assert_eq!(metrics::kind(&Error::Error("Failed".to_string())), "general");
+/// assert_eq!(metrics::kind(&Error::File(io::Error::from(io::ErrorKind::NotFound))), "not_found");
+/// ```
+pub fn kind(error: &Error) -> &'static str {
+    error.kind().name()
+}
+
+/// Add the invocation to the metrics in the text of a metrics file, which may be empty, returning
+/// the new text. Metrics not written by the application are dropped.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use tyg_template::metrics::{self, Invocation};
+/// use tyg_template::Error;
+///
+/// let error = Error::Error("Failed".to_string());
+/// let invocation = Invocation {
+///     subcommand: "fail",
+///     duration: Duration::from_millis(250),
+///     warnings: 1,
+///     error: Some(&error),
+/// };
+///
+/// let text = metrics::update("", &invocation);
+/// let text = metrics::update(&text, &invocation);
+///
+/// assert!(text.contains("\ntyg_template_invocations_total{subcommand=\"fail\"} 2\n"));
+/// assert!(text.contains("\ntyg_template_duration_seconds_total{subcommand=\"fail\"} 0.5\n"));
+/// assert!(text.contains("\ntyg_template_errors_total{subcommand=\"fail\",kind=\"general\"} 2\n"));
+/// assert!(text.contains("\ntyg_template_warnings_total{subcommand=\"fail\"} 2\n"));
+/// ```
+pub fn update(text: &str, invocation: &Invocation) -> String {
+    let prefix = prefix();
+    let mut samples: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+        let Some((series, value)) = line.rsplit_once(' ') else {
+            continue;
+        };
+        let (Some((metric, labels)), Ok(value)) = (series.split_once('{'), value.parse::<f64>())
+        else {
+            continue;
+        };
+        if let Some(name) = metric.strip_prefix(&prefix) {
+            if METRICS.iter().any(|(known, _)| *known == name) {
+                samples
+                    .entry(name.to_string())
+                    .or_default()
+                    .insert(format!("{{{}", labels), value);
+            }
+        }
+    }
+
+    let subcommand = format!("{{subcommand=\"{}\"}}", escape(invocation.subcommand));
+    let mut add = |name: &str, labels: &str, amount: f64| {
+        *samples
+            .entry(name.to_string())
+            .or_default()
+            .entry(labels.to_string())
+            .or_default() += amount;
+    };
+    add("invocations_total", &subcommand, 1.0);
+    add(
+        "duration_seconds_total",
+        &subcommand,
+        invocation.duration.as_secs_f64(),
+    );
+    add("warnings_total", &subcommand, invocation.warnings as f64);
+    if let Some(error) = invocation.error {
+        let labels = format!(
+            "{{subcommand=\"{}\",kind=\"{}\"}}",
+            escape(invocation.subcommand),
+            kind(error)
+        );
+        add("errors_total", &labels, 1.0);
+    }
+
+    let mut text = String::new();
+    for (name, help) in METRICS {
+        let Some(series) = samples.get(*name) else {
+            continue;
+        };
+        text.push_str(&format!("# HELP {}{} {}\n", prefix, name, help));
+        text.push_str(&format!("# TYPE {}{} counter\n", prefix, name));
+        for (labels, value) in series {
+            text.push_str(&format!("{}{}{} {}\n", prefix, name, labels, value));
+        }
+    }
+    text
+}
+
+/// Add the invocation to the metrics file named by `TYG_METRICS_FILE`, if it is set. A failure is
+/// written to the error stream as a warning, so that it cannot mask the result of the command.
+pub fn export(invocation: &Invocation, err: &mut dyn Write) {
+    let Some(path) = env::var_os(FILE_VAR) else {
+        return;
+    };
+    if let Err(e) = write(Path::new(&path), invocation) {
+        let message = format!("The metrics were not exported: {}", e);
+        let _ = writeln!(
+            err,
+            "{}",
+            ErrorFormat::selected().warning(env!("CARGO_PKG_NAME"), &message)
+        );
+    }
+}
+
+fn write(path: &Path, invocation: &Invocation) -> Result<()> {
+    let text = match fs::read_to_string(long_path(path)) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return result_err_bare!("{}: {}", path.display(), e),
+    };
+    // Written beside the file and renamed over it, so that it is replaced as a whole
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(long_path(Path::new(&temporary)), update(&text, invocation))
+        .and_then(|_| fs::rename(long_path(Path::new(&temporary)), long_path(path)))
+        .or_else(|e| result_err_bare!("{}: {}", path.display(), e))
+}
+
+// The prefix of the names of the metrics, the name of the application as a metric name
+fn prefix() -> String {
+    let name: String = env!("CARGO_PKG_NAME")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}_", name)
+}
+
+// A label value with its backslashes and quotes escaped
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,138 @@
+//! Multicall installation, in which one binary is installed under several names, as busybox is.
+//!
+//! Each name is an [`Applet`] that runs a subcommand. When the binary is run through a link named
+//! after an applet, the subcommand of the applet is put in front of the arguments before they are
+//! parsed, so that `tyg-check notes.txt` runs as `tyg_template check notes.txt`:
+//!
+//! ```text
+//! const APPLETS: &[Applet] = &[
+//!     Applet { name: "tyg-check", subcommand: "check" },
+//!     Applet { name: "tyg-validate", subcommand: "config validate" },
+//! ];
+//! ```
+//!
+//! The name is that of the file the binary was run as, without its directory or, on Windows, its
+//! `.exe` extension. Running the binary with `--list-applets` as its only argument lists the
+//! applets and the commands that install them as symbolic or hard links, which [`list`] writes.
+
+use std::ffi::{OsStr, OsString};
+use std::io::Write;
+use std::path::Path;
+
+use crate::Result;
+
+/// The flag listing the applets, given as the first argument.
+pub const LIST_FLAG: &str = "--list-applets";
+
+/// A name under which the binary runs a subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Applet {
+    /// The name of the link to the binary, e.g. `tyg-check`.
+    pub name: &'static str,
+    /// The subcommand run, with the names of its parents separated by spaces, e.g.
+    /// `config validate`.
+    pub subcommand: &'static str,
+}
+
+/// The applet that the binary was run as, given the first argument of the command line.
+///
+/// # Examples
+/// ```
+/// use std::ffi::OsStr;
+/// use tyg_template::multicall::{self, Applet};
+///
+/// let applets = [Applet { name: "tyg-check", subcommand: "check" }];
+///
+/// assert_eq!(multicall::applet(OsStr::new("/usr/local/bin/tyg-check"), &applets), Some(&applets[0]));
+/// assert_eq!(multicall::applet(OsStr::new("tyg_template"), &applets), None);
+/// ```
+pub fn applet<'a>(argv0: &OsStr, applets: &'a [Applet]) -> Option<&'a Applet> {
+    let path = Path::new(argv0);
+    let name = match path.extension() {
+        Some(extension) if cfg!(windows) && extension.eq_ignore_ascii_case("exe") => {
+            path.file_stem()?
+        }
+        _ => path.file_name()?,
+    };
+    applets.iter().find(|applet| name == applet.name)
+}
+
+/// The command line with the name of the binary and the subcommand of the applet in place of the
+/// first argument, if that names an applet, or the command line as it is.
+///
+/// # Examples
+/// ```
+/// use tyg_template::multicall::{self, Applet};
+///
+/// let applets = [Applet { name: "tyg-validate", subcommand: "config validate" }];
+///
+/// assert_eq!(multicall::expand(vec!["tyg-validate".into(), "app.toml".into()], &applets),
+///            ["tyg_template", "config", "validate", "app.toml"]);
+/// assert_eq!(multicall::expand(vec!["tyg_template".into(), "check".into()], &applets),
+///            ["tyg_template", "check"]);
+/// ```
+pub fn expand(args: Vec<OsString>, applets: &[Applet]) -> Vec<OsString> {
+    let Some(applet) = args.first().and_then(|argv0| applet(argv0, applets)) else {
+        return args;
+    };
+    std::iter::once(env!("CARGO_PKG_NAME"))
+        .chain(applet.subcommand.split_whitespace())
+        .map(OsString::from)
+        .chain(args.into_iter().skip(1))
+        .collect()
+}
+
+/// Whether the command line asks for the list of the applets.
+pub fn list_requested(args: &[OsString]) -> bool {
+    args.len() == 2 && args[1] == LIST_FLAG
+}
+
+/// Write the applets with their subcommands, followed by the commands that install them as links
+/// to the binary in its directory, as symbolic links or, where those are not available, hard links.
+pub fn list(applets: &[Applet], out: &mut dyn Write) -> Result<()> {
+    let width = applets
+        .iter()
+        .map(|applet| applet.name.len())
+        .max()
+        .unwrap_or(0);
+    for applet in applets {
+        writeln!(out, "{:width$}  {}", applet.name, applet.subcommand)?;
+    }
+    let binary = env!("CARGO_PKG_NAME");
+    writeln!(out)?;
+    if cfg!(windows) {
+        writeln!(
+            out,
+            "Install the applets as links in the directory of {}.exe, e.g. with symbolic links:",
+            binary
+        )?;
+        for applet in applets {
+            writeln!(out, "  mklink {}.exe {}.exe", applet.name, binary)?;
+        }
+        writeln!(
+            out,
+            "or, without the privilege to create symbolic links, with hard links:"
+        )?;
+        for applet in applets {
+            writeln!(out, "  mklink /H {}.exe {}.exe", applet.name, binary)?;
+        }
+    } else {
+        writeln!(
+            out,
+            "Install the applets as links in the directory of {}, e.g. with symbolic links:",
+            binary
+        )?;
+        writeln!(out, "  cd \"$(dirname \"$(command -v {})\")\"", binary)?;
+        for applet in applets {
+            writeln!(out, "  ln -s {} {}", binary, applet.name)?;
+        }
+        writeln!(
+            out,
+            "or, for a package or file system without symbolic links, with hard links:"
+        )?;
+        for applet in applets {
+            writeln!(out, "  ln {} {}", binary, applet.name)?;
+        }
+    }
+    Ok(())
+}
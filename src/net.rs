@@ -0,0 +1,105 @@
+//! Checks of network connectivity with errors worded for the user.
+//!
+//! The errors are returned as [`Error::Network`](crate::Error), keeping the kind of the
+//! underlying failure so that [`Error::is_transient`](crate::Error::is_transient) tells a failure
+//! worth retrying, such as a timeout or a refused connection, from one that is not.
+
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::units::HumanDuration;
+use crate::{Error, Result};
+
+/// Resolve a host name, or parse an IP address, into the socket addresses for the port.
+///
+/// A host name that cannot be resolved is reported as a transient error suggesting that the
+/// network may be down, since that is the usual cause when the name is correct.
+///
+/// # Examples
+/// ```
+/// use tyg_template::net::resolve;
+///
+/// let addresses = resolve("127.0.0.1", 8080).unwrap();
+///
+/// assert_eq!(addresses[0].to_string(), "127.0.0.1:8080");
+///
+/// let error = resolve("no-such-host.invalid", 443).unwrap_err();
+/// assert!(error.is_transient());
+/// assert!(error.to_string().ends_with("are you online?"));
+/// ```
+pub fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+    match (host, port).to_socket_addrs() {
+        Ok(addresses) => {
+            let addresses: Vec<SocketAddr> = addresses.collect();
+            if addresses.is_empty() {
+                Err(network(ErrorKind::HostUnreachable, unresolved(host)))
+            } else {
+                Ok(addresses)
+            }
+        }
+        Err(_) => Err(network(ErrorKind::HostUnreachable, unresolved(host))),
+    }
+}
+
+/// Check that a TCP connection can be made to the port of the host within the timeout, returning
+/// the address connected to.
+///
+/// Each address of the host is tried in turn, and the failure to connect to the last of them is
+/// reported if none can be reached.
+///
+/// # Examples
+/// ```
+/// use std::net::TcpListener;
+/// use std::time::Duration;
+/// use tyg_template::net::check_reachable;
+///
+/// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+/// let port = listener.local_addr().unwrap().port();
+///
+/// assert!(check_reachable("127.0.0.1", port, Duration::from_secs(5)).is_ok());
+///
+/// drop(listener);
+/// let error = check_reachable("127.0.0.1", port, Duration::from_secs(5)).unwrap_err();
+///
+/// assert!(error.is_transient());
+/// assert!(error.to_string().contains("Connection refused"));
+/// ```
+pub fn check_reachable(host: &str, port: u16, timeout: Duration) -> Result<SocketAddr> {
+    let mut failure = None;
+    for address in resolve(host, port)? {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(_) => return Ok(address),
+            Err(e) => failure = Some(e),
+        }
+    }
+    let e = failure.unwrap_or_else(|| io::Error::from(ErrorKind::HostUnreachable));
+    let message = match e.kind() {
+        ErrorKind::TimedOut => format!(
+            "{}:{}: No response within {}, is the host behind a firewall?",
+            host,
+            port,
+            HumanDuration::new(timeout)
+        ),
+        ErrorKind::ConnectionRefused => format!(
+            "{}:{}: Connection refused, is the service running?",
+            host, port
+        ),
+        ErrorKind::HostUnreachable | ErrorKind::NetworkUnreachable | ErrorKind::NetworkDown => {
+            format!(
+                "{}:{}: The host cannot be reached, are you online?",
+                host, port
+            )
+        }
+        _ => format!("{}:{}: {}", host, port, e),
+    };
+    Err(network(e.kind(), message))
+}
+
+fn unresolved(host: &str) -> String {
+    format!("{}: Cannot resolve the host name, are you online?", host)
+}
+
+fn network(kind: ErrorKind, message: String) -> Error {
+    Error::Network(io::Error::new(kind, message))
+}
@@ -0,0 +1,155 @@
+//! The outcome of a subcommand, and the exit codes it maps to for each subcommand.
+//!
+//! A handler that does more than succeed or fail, such as a check that reports what it finds,
+//! returns an [`Outcome`]. The dispatcher maps the outcome to the exit code of the process using
+//! the [`ExitCodes`] of the subcommand, so that, for example, `check` exits with 1 when it finds
+//! problems but with 2 when it cannot finish:
+//!
+//! ```text
+//! const EXIT_CODES: &[ExitCodes] = &[
+//!     ExitCodes { subcommand: "check", findings: 1, error: Some(2) },
+//! ];
+//! ```
+//!
+//! A subcommand without exit codes of its own uses [`ExitCodes::DEFAULT`], under which findings
+//! exit with 1 and an error exits with the code of its [kind](crate::ErrorKind).
+
+use std::fmt;
+
+use crate::{globals, Error, Result};
+
+/// The outcome of a subcommand.
+#[derive(Debug)]
+pub enum Outcome {
+    /// The subcommand did what was asked.
+    Success,
+    /// The subcommand finished, and found the given number of problems, e.g. files that failed a
+    /// check. No problems found is a success.
+    Findings(usize),
+    /// The subcommand failed.
+    Err(Error),
+}
+
+impl Outcome {
+    /// The error of a failed subcommand.
+    pub fn error(&self) -> Option<&Error> {
+        match self {
+            Outcome::Err(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The exit code of the outcome, with the exit codes of the subcommand selected on the
+    /// command line of the current run.
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code_with(&ExitCodes::selected())
+    }
+
+    /// The exit code of the outcome with the given exit codes.
+    ///
+    /// # Examples
+    /// ```
+    /// use tyg_template::outcome::{ExitCodes, Outcome};
+    /// use tyg_template::{Error, ErrorKind};
+    ///
+    /// let check = ExitCodes { subcommand: "check", findings: 1, error: Some(2) };
+    /// let error = || Error::Error("Cannot read".to_string()).with_kind(ErrorKind::Io);
+    ///
+    /// assert_eq!(Outcome::Success.exit_code_with(&check), 0);
+    /// assert_eq!(Outcome::Findings(0).exit_code_with(&check), 0);
+    /// assert_eq!(Outcome::Findings(3).exit_code_with(&check), 1);
+    /// assert_eq!(Outcome::Err(error()).exit_code_with(&check), 2);
+    /// assert_eq!(Outcome::Err(error()).exit_code_with(&ExitCodes::DEFAULT), 74);
+    /// ```
+    pub fn exit_code_with(&self, codes: &ExitCodes) -> i32 {
+        match self {
+            Outcome::Success | Outcome::Findings(0) => 0,
+            Outcome::Findings(_) => codes.findings,
+            Outcome::Err(e) => codes.error.unwrap_or_else(|| e.kind().exit_code()),
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    /// The outcome as shown by the snapshot tests, e.g. `Ok`, `Findings: 3` or `Err: message`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Success => write!(f, "Ok"),
+            Outcome::Findings(n) => write!(f, "Findings: {}", n),
+            Outcome::Err(e) => write!(f, "Err: {}", e),
+        }
+    }
+}
+
+impl From<Error> for Outcome {
+    fn from(error: Error) -> Outcome {
+        Outcome::Err(error)
+    }
+}
+
+impl From<Result<()>> for Outcome {
+    fn from(result: Result<()>) -> Outcome {
+        match result {
+            Ok(()) => Outcome::Success,
+            Err(e) => Outcome::Err(e),
+        }
+    }
+}
+
+impl From<Result<Outcome>> for Outcome {
+    fn from(result: Result<Outcome>) -> Outcome {
+        result.unwrap_or_else(Outcome::Err)
+    }
+}
+
+/// The exit codes of a subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitCodes {
+    /// The name of the subcommand.
+    pub subcommand: &'static str,
+    /// The exit code when the subcommand finds problems.
+    pub findings: i32,
+    /// The exit code when the subcommand fails, or `None` for the exit code of the kind of the
+    /// error.
+    pub error: Option<i32>,
+}
+
+impl ExitCodes {
+    /// The exit codes of a subcommand without exit codes of its own.
+    pub const DEFAULT: ExitCodes = ExitCodes {
+        subcommand: "",
+        findings: 1,
+        error: None,
+    };
+
+    /// The exit codes of the subcommand in the table, or the default exit codes if it has none.
+    ///
+    /// # Examples
+    /// ```
+    /// use tyg_template::outcome::ExitCodes;
+    ///
+    /// let table = [ExitCodes { subcommand: "check", findings: 1, error: Some(2) }];
+    ///
+    /// assert_eq!(ExitCodes::find(&table, "check").error, Some(2));
+    /// assert_eq!(ExitCodes::find(&table, "fail"), ExitCodes::DEFAULT);
+    /// ```
+    pub fn find(table: &[ExitCodes], subcommand: &str) -> ExitCodes {
+        table
+            .iter()
+            .find(|codes| codes.subcommand == subcommand)
+            .copied()
+            .unwrap_or(ExitCodes::DEFAULT)
+    }
+
+    /// The exit codes of the subcommand selected on the command line of the current run, as
+    /// given by [`globals::current`], or the default exit codes if there is none.
+    pub fn selected() -> ExitCodes {
+        globals::current().exit_codes
+    }
+}
+
+impl Default for ExitCodes {
+    fn default() -> ExitCodes {
+        ExitCodes::DEFAULT
+    }
+}
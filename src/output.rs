@@ -0,0 +1,54 @@
+use std::io::{self, BufWriter, IsTerminal, Write};
+
+/// The output streams written to by the subcommand handlers.
+///
+/// Handlers write to these streams rather than directly to the standard output and error streams
+/// so that their output can be redirected, for example when testing.
+///
+/// # Examples
+/// ```
+/// use std::io::Write;
+/// use tyg_template::Streams;
+///
+/// let mut out = Vec::new();
+/// let mut err = Vec::new();
+/// {
+///     let mut streams = Streams::new(&mut out, &mut err);
+///     writeln!(streams.out, "Hello").unwrap();
+/// }
+///
+/// assert_eq!(out, b"Hello\n");
+/// assert!(err.is_empty());
+/// ```
+pub struct Streams<'a> {
+    /// The stream used for normal output.
+    pub out: Box<dyn Write + 'a>,
+    /// The stream used for diagnostic output.
+    pub err: Box<dyn Write + 'a>,
+}
+
+impl<'a> Streams<'a> {
+    /// Create a set of streams writing to the given destinations.
+    pub fn new(out: impl Write + 'a, err: impl Write + 'a) -> Streams<'a> {
+        Streams {
+            out: Box::new(out),
+            err: Box::new(err),
+        }
+    }
+
+    /// Create a set of streams writing to the standard output and error streams.
+    ///
+    /// The standard output stream is locked for the lifetime of the streams. When it is redirected
+    /// to a file or a pipe its output is also buffered, so that commands writing many lines are
+    /// not slowed by a flush for each line, whereas a terminal still sees each line as it is
+    /// written. Buffered output is written when the streams are flushed or dropped, so call
+    /// `flush` on the output stream to detect a failure to write it.
+    pub fn stdio() -> Streams<'static> {
+        let stdout = io::stdout();
+        if stdout.is_terminal() {
+            Streams::new(stdout.lock(), io::stderr())
+        } else {
+            Streams::new(BufWriter::new(stdout.lock()), io::stderr())
+        }
+    }
+}
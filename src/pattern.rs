@@ -0,0 +1,112 @@
+//! Regular expressions given on the command line, e.g. `--filter <REGEX>`.
+//!
+//! This module is available when the `regex` feature is enabled, which adds a dependency on
+//! [regex](https://docs.rs/regex). [`Pattern`] implements `FromStr`, so it can be used as the
+//! value of an argument with `value_parser!`, and the pattern is compiled as the command line is
+//! parsed. A pattern that does not compile is reported with the position and text of the part of
+//! the pattern at fault, rather than the multi-line report of the regex crate.
+//!
+//! ```
+//! use clap::{arg, value_parser, Command};
+//! use tyg_template::pattern::Pattern;
+//!
+//! let command = Command::new("example")
+//!     .arg(arg!(--filter <REGEX> "Show only the matching lines").value_parser(value_parser!(Pattern)));
+//!
+//! let matches = command.clone().try_get_matches_from(["example", "--filter", "^Cycle [0-9]+$"]).unwrap();
+//! assert!(matches.get_one::<Pattern>("filter").unwrap().regex().is_match("Cycle 4"));
+//!
+//! let error = command.try_get_matches_from(["example", "--filter", "Cycle [9-0]"]).unwrap_err();
+//! assert!(error.to_string().contains("at position 8: '9-0'"));
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::{result_err_bare, Error, Result};
+
+/// A compiled regular expression.
+///
+/// The syntax is that of the [regex](https://docs.rs/regex/latest/regex/#syntax) crate. A syntax
+/// error names the position, counting characters from 1, and the text of the part of the pattern
+/// at fault. The pattern is displayed as it was given.
+///
+/// # Examples
+/// ```
+/// use tyg_template::pattern::Pattern;
+/// use tyg_template::{assert_err_msg, Error};
+///
+/// let pattern: Pattern = "fail(ed)?".parse().unwrap();
+/// assert!(pattern.regex().is_match("failed at cycle 5"));
+/// assert_eq!(pattern.to_string(), "fail(ed)?");
+///
+/// assert_err_msg!("fail(ed".parse::<Pattern>(), Error::Error(_),
+///                 "fail(ed: Unclosed group at position 5: '('");
+/// assert_err_msg!(r"\p{Klingon}".parse::<Pattern>(), Error::Error(_),
+///                 r"\p{Klingon}: Unicode property not found at position 1: '\p{Klingon}'");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    /// The compiled regular expression.
+    pub fn regex(&self) -> &Regex {
+        &self.0
+    }
+}
+
+impl From<Pattern> for Regex {
+    fn from(pattern: Pattern) -> Regex {
+        pattern.0
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Pattern> {
+        // The syntax is checked first, as only the syntax errors locate the fault in the pattern
+        if let Err(e) = regex_syntax::Parser::new().parse(pattern) {
+            let (kind, span) = match &e {
+                regex_syntax::Error::Parse(e) => (e.kind().to_string(), e.span()),
+                regex_syntax::Error::Translate(e) => (e.kind().to_string(), e.span()),
+                _ => return result_err_bare!("{}: {}", pattern, e),
+            };
+            let mut kind = kind.chars();
+            let kind: String = kind
+                .next()
+                .map(|first| first.to_uppercase().chain(kind).collect())
+                .unwrap_or_default();
+            let position = pattern[..span.start.offset].chars().count() + 1;
+            let fault = &pattern[span.start.offset..span.end.offset];
+            return if fault.is_empty() {
+                result_err_bare!("{}: {} at position {}", pattern, kind, position)
+            } else {
+                result_err_bare!(
+                    "{}: {} at position {}: '{}'",
+                    pattern,
+                    kind,
+                    position,
+                    fault
+                )
+            };
+        }
+        match Regex::new(pattern) {
+            Ok(regex) => Ok(Pattern(regex)),
+            Err(regex::Error::CompiledTooBig(limit)) => result_err_bare!(
+                "{}: The compiled pattern is larger than the limit of {} bytes",
+                pattern,
+                limit
+            ),
+            Err(e) => result_err_bare!("{}: {}", pattern, e),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.as_str())
+    }
+}
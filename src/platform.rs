@@ -0,0 +1,97 @@
+//! Support for the differences between the platforms the template runs on.
+//!
+//! The functions in this module do whatever the current platform needs and nothing elsewhere, so
+//! they can be called unconditionally. The Windows specific support is in the `windows` module,
+//! which is only compiled on Windows, and the Linux specific support in the `linux` module, which
+//! is only compiled on Linux.
+
+use std::borrow::Cow;
+use std::env;
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "linux")]
+pub mod linux;
+#[cfg(windows)]
+pub mod windows;
+
+/// Prepare the console for the output of the application.
+///
+/// On Windows this enables the processing of ANSI escape sequences and switches the console to
+/// UTF-8 output, so that styled and non-ASCII text is displayed as it is on other platforms.
+/// Elsewhere this does nothing. Consoles that cannot be configured, such as when the output is
+/// redirected to a file, are left as they are.
+pub fn init_console() {
+    #[cfg(windows)]
+    {
+        windows::enable_ansi();
+        windows::enable_utf8();
+    }
+}
+
+/// Prepare a path for use with the filing system functions.
+///
+/// On Windows the path is made absolute and given the `\\?\` prefix, which lifts the limit of 260
+/// characters on the length of a path. Elsewhere the path is returned as it is. The returned path
+/// is only intended to be passed to the filing system; show the original path to the user.
+///
+/// # Examples
+/// ```
+/// use std::path::Path;
+/// use tyg_template::platform::long_path;
+///
+/// let path = Path::new("src/lib.rs");
+///
+/// if cfg!(windows) {
+///     assert!(long_path(path).starts_with(r"\\?\"));
+/// } else {
+///     assert_eq!(long_path(path), path);
+/// }
+/// ```
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    return windows::long_path(path);
+
+    #[cfg(not(windows))]
+    Cow::Borrowed(path)
+}
+
+/// The directory in which the application keeps state that outlives a run, such as its logs, or
+/// `None` if it is unknown.
+///
+/// This is `$XDG_STATE_HOME/<name>` when `XDG_STATE_HOME` is set, and otherwise
+/// `~/.local/state/<name>`, or `%LOCALAPPDATA%\<name>` on Windows.
+pub fn state_directory() -> Option<PathBuf> {
+    let state = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| {
+            if cfg!(windows) {
+                env::var_os("LOCALAPPDATA").map(PathBuf::from)
+            } else {
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+            }
+        })?;
+    Some(state.join(env!("CARGO_PKG_NAME")))
+}
+
+/// The resident memory of the process in bytes, or `None` where it cannot be measured, which is on
+/// platforms other than Linux and Windows.
+///
+/// # Examples
+/// ```
+/// use tyg_template::platform::resident_memory;
+///
+/// if cfg!(any(target_os = "linux", windows)) {
+///     assert!(resident_memory().unwrap() > 0);
+/// }
+/// ```
+pub fn resident_memory() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    return linux::resident_memory();
+
+    #[cfg(windows)]
+    return windows::resident_memory();
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    None
+}
@@ -0,0 +1,13 @@
+//! Memory support for Linux.
+
+use std::fs;
+
+/// The resident memory of the process in bytes, read from `/proc/self/statm`, or `None` if it
+/// cannot be read.
+pub fn resident_memory() -> Option<u64> {
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: the function takes no pointers
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * u64::try_from(page_size).ok()?)
+}
@@ -0,0 +1,95 @@
+//! Console, path and memory support for Windows.
+
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::path::{self, Component, Path, PathBuf, Prefix};
+
+use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+use windows_sys::Win32::Globalization::CP_UTF8;
+use windows_sys::Win32::System::Console::{
+    GetConsoleMode, GetStdHandle, SetConsoleMode, SetConsoleOutputCP,
+    ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_ERROR_HANDLE, STD_HANDLE, STD_OUTPUT_HANDLE,
+};
+use windows_sys::Win32::System::ProcessStatus::{K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+/// Enable the processing of ANSI escape sequences by the consoles of the standard output and
+/// error streams.
+///
+/// Returns `true` if both streams now process escape sequences. A stream that has been redirected
+/// away from the console is left as it is and causes `false` to be returned.
+pub fn enable_ansi() -> bool {
+    [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE]
+        .into_iter()
+        .fold(true, |enabled, handle| enable_ansi_for(handle) && enabled)
+}
+
+// Enable the processing of ANSI escape sequences for the console of a single stream
+fn enable_ansi_for(std_handle: STD_HANDLE) -> bool {
+    // SAFETY: the handle is checked before use and the mode is written to a local variable
+    unsafe {
+        let console = GetStdHandle(std_handle);
+        if console.is_null() || console == INVALID_HANDLE_VALUE {
+            return false;
+        }
+        let mut mode = 0;
+        if GetConsoleMode(console, &mut mode) == 0 {
+            return false;
+        }
+        mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+            || SetConsoleMode(console, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+/// Switch the console output to the UTF-8 code page.
+///
+/// Returns `true` if the code page was changed.
+pub fn enable_utf8() -> bool {
+    // SAFETY: the function takes no pointers
+    unsafe { SetConsoleOutputCP(CP_UTF8) != 0 }
+}
+
+/// The working set of the process in bytes, which is its resident memory, or `None` if it cannot
+/// be read.
+pub fn resident_memory() -> Option<u64> {
+    let mut counters = PROCESS_MEMORY_COUNTERS::default();
+    let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    counters.cb = size;
+    // SAFETY: the counters are written to a local variable of the size given
+    let read = unsafe { K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) };
+    match read {
+        0 => None,
+        _ => Some(counters.WorkingSetSize as u64),
+    }
+}
+
+/// Make the path absolute and give it the `\\?\` prefix, which lifts the limit of 260 characters on
+/// the length of a path.
+///
+/// Paths that already have the prefix, device paths and paths that cannot be made absolute are
+/// returned as they are. Network paths are given the `\\?\UNC\` prefix.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    // Verbatim paths are not normalized by Windows, so normalize the path before adding the prefix
+    let Ok(absolute) = path::absolute(path) else {
+        return Cow::Borrowed(path);
+    };
+    let prefixed = match absolute.components().next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(_) => {
+                let mut prefixed = OsString::from(r"\\?\");
+                prefixed.push(absolute.as_os_str());
+                prefixed
+            }
+            Prefix::UNC(..) => {
+                // Replace the first \ of the leading \\ of the network path by the prefix
+                let mut wide: Vec<u16> = r"\\?\UNC".encode_utf16().collect();
+                wide.extend(absolute.as_os_str().encode_wide().skip(1));
+                OsString::from_wide(&wide)
+            }
+            _ => return Cow::Borrowed(path),
+        },
+        _ => return Cow::Borrowed(path),
+    };
+    Cow::Owned(PathBuf::from(prefixed))
+}
@@ -0,0 +1,220 @@
+//! Plugins compiled into the application from other crates, adding subcommands and formats of
+//! error reports.
+//!
+//! A plugin implements the [`Plugin`] trait and is given to [`register`] before the command line
+//! is processed, usually at the start of `main`. Its subcommands are then part of the command line
+//! built by the application, with their help, completions and place in scripts, and a subcommand
+//! of a plugin is run by the plugin's [`run`](Plugin::run). The [`ErrorRenderer`]s of a plugin are
+//! selected with `--error-format` by their names, as the formats of the application are.
+//!
+//! A plugin is refused if one of its subcommands or formats has the name, or alias, of one that
+//! the application or a plugin registered earlier already provides, so that a plugin never
+//! replaces a subcommand silently.
+//!
+//! # Examples
+//! ```
+//! use std::io::Write;
+//!
+//! use clap::{arg, ArgMatches, Command};
+//! use tyg_template::outcome::Outcome;
+//! use tyg_template::plugin::{self, Plugin};
+//! use tyg_template::{Result, Streams};
+//!
+//! struct Greeter;
+//!
+//! impl Plugin for Greeter {
+//!     fn name(&self) -> &'static str {
+//!         "greeter"
+//!     }
+//!
+//!     fn commands(&self) -> Vec<Command> {
+//!         vec![Command::new("greet").about("Greet someone").arg(arg!(<NAME> "Who to greet"))]
+//!     }
+//!
+//!     fn run(&self, _subcommand: &str, matches: &ArgMatches, streams: &mut Streams) -> Result<Outcome> {
+//!         let name = matches.get_one::<String>("NAME").map_or("", String::as_str);
+//!         writeln!(streams.out, "Hello {}", name)?;
+//!         Ok(Outcome::Success)
+//!     }
+//! }
+//!
+//! plugin::register(Greeter).unwrap();
+//!
+//! let mut out = Vec::new();
+//! let mut err = Vec::new();
+//! tyg_template::run_with_args(["tyg_template", "greet", "world"], &mut Streams::new(&mut out, &mut err)).unwrap();
+//!
+//! assert_eq!(String::from_utf8_lossy(&out), "Hello world\n");
+//! assert!(plugin::register(Greeter).is_err());
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use clap::{ArgMatches, Command};
+
+use crate::outcome::Outcome;
+use crate::{result_err_bare, ErrorFormat, Result, Streams};
+
+// The plugins registered, in the order of their registration
+static PLUGINS: Mutex<Vec<Arc<dyn Plugin>>> = Mutex::new(Vec::new());
+
+/// An extension of the application compiled in from another crate.
+pub trait Plugin: Send + Sync {
+    /// The name of the plugin, used in the errors about it.
+    fn name(&self) -> &'static str;
+
+    /// The subcommands the plugin adds to the command line of the application.
+    fn commands(&self) -> Vec<Command>;
+
+    /// Run the subcommand of the plugin with the given name, given its matches, writing any output
+    /// to the given streams.
+    fn run(&self, subcommand: &str, matches: &ArgMatches, streams: &mut Streams)
+        -> Result<Outcome>;
+
+    /// The formats of error reports the plugin adds, none by default.
+    fn error_formats(&self) -> Vec<ErrorRenderer> {
+        Vec::new()
+    }
+}
+
+/// A format of error reports provided by a plugin, selected with `--error-format` by its name.
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorRenderer {
+    /// The name of the format, e.g. `json`.
+    pub name: &'static str,
+    /// Render a report given its level, `error` or `warning`, its prefix and its message, which
+    /// may start with the location of the error.
+    pub render: fn(level: &str, prefix: &str, message: &str) -> String,
+}
+
+/// Register a plugin, adding its subcommands and formats to those of the application.
+///
+/// An error is returned, and the plugin is not registered, if the name or an alias of one of its
+/// subcommands, or the name of one of its formats, is already taken by the application or by a
+/// plugin registered earlier, or is used twice by the plugin.
+pub fn register(plugin: impl Plugin + 'static) -> Result<()> {
+    // The command line of the application is built before the lock is taken, as it includes the
+    // subcommands of the plugins. Its help subcommand is only added by clap when it is parsed.
+    let application = crate::cli();
+    let mut plugins = PLUGINS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut taken = Vec::new();
+    for command in plugin.commands() {
+        for name in names(&command) {
+            let provider = provider(
+                &plugins,
+                |other| other.commands().iter().any(|c| names(c).contains(&name)),
+                name == "help"
+                    || application
+                        .get_subcommands()
+                        .any(|c| names(c).contains(&name)),
+                taken.contains(&name),
+            );
+            if let Some(provider) = provider {
+                return result_err_bare!(
+                    "Plugin {}: The subcommand {} is already provided by {}",
+                    plugin.name(),
+                    name,
+                    provider
+                );
+            }
+            taken.push(name);
+        }
+    }
+    let mut formats = Vec::new();
+    for renderer in plugin.error_formats() {
+        let provider = provider(
+            &plugins,
+            |other| {
+                other
+                    .error_formats()
+                    .iter()
+                    .any(|r| r.name == renderer.name)
+            },
+            ErrorFormat::NAMES.contains(&renderer.name),
+            formats.contains(&renderer.name),
+        );
+        if let Some(provider) = provider {
+            return result_err_bare!(
+                "Plugin {}: The error format {} is already provided by {}",
+                plugin.name(),
+                renderer.name,
+                provider
+            );
+        }
+        formats.push(renderer.name);
+    }
+    plugins.push(Arc::new(plugin));
+    Ok(())
+}
+
+/// The plugins registered, in the order of their registration.
+pub fn plugins() -> Vec<Arc<dyn Plugin>> {
+    PLUGINS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// The format of error reports with the given name provided by a plugin, if any.
+pub fn error_format(name: &str) -> Option<ErrorRenderer> {
+    plugins()
+        .iter()
+        .flat_map(|plugin| plugin.error_formats())
+        .find(|renderer| renderer.name == name)
+}
+
+// Add the subcommands of the plugins to the command line of the application
+pub(crate) fn merge(command: Command) -> Command {
+    command.subcommands(plugins().iter().flat_map(|plugin| plugin.commands()))
+}
+
+// Whether the subcommand with the given name is provided by a plugin
+pub(crate) fn provides(subcommand: &str) -> bool {
+    owner(subcommand).is_some()
+}
+
+// Run the subcommand of a plugin
+pub(crate) fn run(
+    subcommand: &str,
+    matches: &ArgMatches,
+    streams: &mut Streams,
+) -> Result<Outcome> {
+    match owner(subcommand) {
+        Some(plugin) => plugin.run(subcommand, matches, streams),
+        None => result_err_bare!("No plugin provides the subcommand {}", subcommand),
+    }
+}
+
+// The plugin providing the subcommand with the given name, which clap gives without its aliases
+fn owner(subcommand: &str) -> Option<Arc<dyn Plugin>> {
+    plugins().into_iter().find(|plugin| {
+        plugin
+            .commands()
+            .iter()
+            .any(|command| command.get_name() == subcommand)
+    })
+}
+
+// What already provides a name, given which plugins registered provide it and whether the
+// application and the plugin being registered do
+fn provider(
+    plugins: &[Arc<dyn Plugin>],
+    provides: impl Fn(&dyn Plugin) -> bool,
+    application: bool,
+    itself: bool,
+) -> Option<String> {
+    if let Some(other) = plugins.iter().find(|other| provides(other.as_ref())) {
+        Some(format!("the {} plugin", other.name()))
+    } else if application {
+        Some("the application".to_string())
+    } else if itself {
+        Some("the plugin itself".to_string())
+    } else {
+        None
+    }
+}
+
+// The name and aliases of a subcommand
+fn names(command: &Command) -> Vec<String> {
+    std::iter::once(command.get_name())
+        .chain(command.get_all_aliases())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,323 @@
+//! A registry of the progress of the tasks running in the application, which can be shown while
+//! the work carries on.
+//!
+//! A long running task registers itself with [`start`], and updates the [`Task`] returned as it
+//! goes, giving the item it is working on and the number of items done. The task leaves the
+//! registry when the `Task` is dropped. The tasks in progress are listed by [`snapshot`] and
+//! written by [`dump`]:
+//!
+//! ```text
+//! tyg_template: progress after 1m4s
+//!   run-script nightly.tyg: 12 of 40 (30%), at nightly.tyg:14, 1m3s elapsed
+//! ```
+//!
+//! On Unix, with the `signals` feature, sending the application the `SIGUSR1` signal, e.g.
+//! `kill -USR1 <pid>`, writes the dump to the error stream without interrupting the work, see
+//! [`dump_on_signal`].
+//!
+//! For wrappers such as CI scripts and graphical frontends, the global `--progress-format json`
+//! option selects the [JSON format](ProgressFormat::Json), in which the registry writes an event
+//! as a JSON line to the error stream whenever a task starts, completes an item, reaches a new
+//! percentage, finishes or [fails](Task::fail), and progress bars such as that of a
+//! [download](crate::download::progress_bar) are replaced by these events:
+//!
+//! ```text
+//! {"event":"task_started","name":"run-script nightly.tyg","task":0,"total":40}
+//! {"done":1,"event":"item_completed","item":"nightly.tyg:1","task":0}
+//! {"done":1,"event":"progress","percent":2,"task":0,"total":40}
+//! {"error":"nightly.tyg:2: 1 problems found","event":"task_failed","task":0}
+//! ```
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::units::HumanDuration;
+
+// The tasks in progress, in the order they started
+static TASKS: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+// The identifier of the next task
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+// The time the registry was first used, which is close to the start of the application
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// The format in which progress is reported, selected with the `--progress-format` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressFormat {
+    /// Progress bars and status lines for people to read, and the dump on `SIGUSR1`.
+    #[default]
+    Human,
+    /// Events written by the registry as JSON lines to the error stream, for programs to read.
+    Json,
+}
+
+impl ProgressFormat {
+    /// The names of the formats as given on the command line.
+    pub const NAMES: [&'static str; 2] = ["human", "json"];
+
+    /// The format with the given name, or the human format if the name is unknown.
+    pub fn from_name(name: &str) -> ProgressFormat {
+        match name {
+            "json" => ProgressFormat::Json,
+            _ => ProgressFormat::Human,
+        }
+    }
+
+    /// The format selected on the command line of the current run, as given by
+    /// [`globals::current`](crate::globals::current), or the human format if there is none.
+    pub fn selected() -> ProgressFormat {
+        crate::globals::current().progress_format
+    }
+}
+
+/// The state of a task in progress.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskState {
+    /// What the task is doing, e.g. `run-script nightly.tyg`.
+    pub name: String,
+    /// The item being worked on, if the task has said.
+    pub item: Option<String>,
+    /// The number of items done.
+    pub done: u64,
+    /// The number of items to do, if it is known.
+    pub total: Option<u64>,
+    /// The time taken so far.
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for TaskState {
+    /// The state on one line, e.g. `run-script nightly.tyg: 12 of 40 (30%), at nightly.tyg:14,
+    /// 1m3s elapsed`. The elapsed time is shown to the second.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.done)?;
+        if let Some(total) = self.total {
+            write!(f, " of {}", total)?;
+            if let Some(percent) = (self.done.min(total) * 100).checked_div(total) {
+                write!(f, " ({}%)", percent)?;
+            }
+        }
+        if let Some(ref item) = self.item {
+            write!(f, ", at {}", item)?;
+        }
+        let elapsed = Duration::from_secs(self.elapsed.as_secs());
+        write!(f, ", {} elapsed", HumanDuration::new(elapsed))
+    }
+}
+
+// A task in the registry
+struct Entry {
+    id: u64,
+    started: Instant,
+    state: TaskState,
+    // The percentage last reported by a progress event
+    percent: Option<u64>,
+}
+
+/// A task registered in the registry, which leaves it when dropped.
+#[derive(Debug)]
+pub struct Task {
+    id: u64,
+    failed: bool,
+}
+
+/// Register a task with its name and the number of items to do, if it is known.
+///
+/// # Examples
+/// ```
+/// use tyg_template::progress;
+///
+/// let task = progress::start("resize photos", Some(40));
+/// task.item("IMG_0012.jpg");
+/// task.advance(12);
+///
+/// let state = progress::snapshot().into_iter().find(|s| s.name == "resize photos").unwrap();
+/// assert_eq!(state.to_string(), "resize photos: 12 of 40 (30%), at IMG_0012.jpg, 0s elapsed");
+///
+/// drop(task);
+/// assert!(progress::snapshot().iter().all(|s| s.name != "resize photos"));
+/// ```
+pub fn start(name: impl fmt::Display, total: Option<u64>) -> Task {
+    STARTED.get_or_init(Instant::now);
+    let task = Task {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        failed: false,
+    };
+    let name = name.to_string();
+    emit(|| json!({ "event": "task_started", "task": task.id, "name": name, "total": total }));
+    tasks().push(Entry {
+        id: task.id,
+        started: Instant::now(),
+        state: TaskState {
+            name,
+            item: None,
+            done: 0,
+            total,
+            elapsed: Duration::ZERO,
+        },
+        percent: None,
+    });
+    task
+}
+
+impl Task {
+    /// Say which item the task is working on.
+    pub fn item(&self, item: impl fmt::Display) {
+        self.update(|state| state.item = Some(item.to_string()));
+    }
+
+    /// Count more items as done. The item the task is working on, if it has said, is reported as
+    /// completed.
+    pub fn advance(&self, done: u64) {
+        let mut completed = None;
+        self.update(|state| {
+            state.done = state.done.saturating_add(done);
+            completed = state.item.clone().map(|item| (item, state.done));
+        });
+        if let Some((item, done)) = completed {
+            emit(
+                || json!({ "event": "item_completed", "task": self.id, "item": item, "done": done }),
+            );
+        }
+        self.report_percent();
+    }
+
+    /// Set the number of items to do, once it is known.
+    pub fn set_total(&self, total: u64) {
+        self.update(|state| state.total = Some(total));
+        self.report_percent();
+    }
+
+    /// Leave the registry because the task failed with the given error, which is reported in
+    /// place of the end of the task.
+    pub fn fail(mut self, error: impl fmt::Display) {
+        let error = error.to_string();
+        emit(|| json!({ "event": "task_failed", "task": self.id, "error": error }));
+        self.failed = true;
+    }
+
+    fn update(&self, change: impl FnOnce(&mut TaskState)) {
+        if let Some(entry) = tasks().iter_mut().find(|entry| entry.id == self.id) {
+            change(&mut entry.state);
+        }
+    }
+
+    // Report the percentage of the items done, if the number to do is known and the percentage
+    // has changed since it was last reported
+    fn report_percent(&self) {
+        if ProgressFormat::selected() != ProgressFormat::Json {
+            return;
+        }
+        let progress = tasks()
+            .iter_mut()
+            .find(|entry| entry.id == self.id)
+            .and_then(|entry| {
+                let total = entry.state.total?;
+                let percent = (entry.state.done.min(total) * 100).checked_div(total)?;
+                (entry.percent != Some(percent)).then(|| {
+                    entry.percent = Some(percent);
+                    (entry.state.done, total, percent)
+                })
+            });
+        if let Some((done, total, percent)) = progress {
+            emit(
+                || json!({ "event": "progress", "task": self.id, "done": done, "total": total, "percent": percent }),
+            );
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        let done = {
+            let mut tasks = tasks();
+            let done = tasks
+                .iter()
+                .find(|entry| entry.id == self.id)
+                .map(|entry| entry.state.done);
+            tasks.retain(|entry| entry.id != self.id);
+            done
+        };
+        if !self.failed {
+            emit(|| json!({ "event": "task_finished", "task": self.id, "done": done }));
+        }
+    }
+}
+
+/// The states of the tasks in progress, in the order they started.
+pub fn snapshot() -> Vec<TaskState> {
+    tasks()
+        .iter()
+        .map(|entry| TaskState {
+            elapsed: entry.started.elapsed(),
+            ..entry.state.clone()
+        })
+        .collect()
+}
+
+/// Write the time since the application started and the states of the tasks in progress.
+///
+/// # Examples
+/// ```
+/// use tyg_template::progress;
+///
+/// let _task = progress::start("index mail", None);
+/// let mut out = Vec::new();
+/// progress::dump(&mut out).unwrap();
+///
+/// assert!(String::from_utf8(out).unwrap().contains("\n  index mail: 0, 0s elapsed\n"));
+/// ```
+pub fn dump(out: &mut dyn Write) -> io::Result<()> {
+    let uptime = STARTED.get().map_or(Duration::ZERO, Instant::elapsed);
+    let uptime = HumanDuration::new(Duration::from_secs(uptime.as_secs()));
+    writeln!(out, "{}: progress after {}", env!("CARGO_PKG_NAME"), uptime)?;
+    let states = snapshot();
+    if states.is_empty() {
+        writeln!(out, "  No tasks in progress")?;
+    }
+    for state in states {
+        writeln!(out, "  {}", state)?;
+    }
+    out.flush()
+}
+
+/// Write the [`dump`] to the error stream whenever the application receives the `SIGUSR1` signal,
+/// from a thread that waits for it, so that the work is not interrupted.
+#[cfg(all(unix, feature = "signals"))]
+pub fn dump_on_signal() -> crate::Result<()> {
+    use signal_hook::consts::SIGUSR1;
+    use signal_hook::iterator::Signals;
+
+    use crate::ResultExt;
+
+    STARTED.get_or_init(Instant::now);
+    let mut signals = Signals::new([SIGUSR1]).bare(|| "Cannot handle SIGUSR1")?;
+    std::thread::Builder::new()
+        .name("progress-dump".to_string())
+        .spawn(move || {
+            for _ in signals.forever() {
+                // There is nowhere to report a failure to write to the error stream
+                let _ = dump(&mut io::stderr().lock());
+            }
+        })
+        .bare(|| "Cannot start the thread waiting for SIGUSR1")?;
+    Ok(())
+}
+
+// Write an event as a JSON line to the error stream if the JSON format is selected. There is
+// nowhere to report a failure to write it.
+fn emit(event: impl FnOnce() -> Value) {
+    if ProgressFormat::selected() == ProgressFormat::Json {
+        let _ = writeln!(io::stderr().lock(), "{}", event());
+    }
+}
+
+// The tasks, even if a panic happened while they were locked
+fn tasks() -> MutexGuard<'static, Vec<Entry>> {
+    TASKS.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,180 @@
+//! Confirmation prompts and the flags that bypass them, and the prompts that recover from the
+//! failures of a batch.
+//!
+//! The global `--yes` flag answers yes to every confirmation prompt, so that the application can
+//! be run unattended, and the global `--force` flag overrides safety checks, such as the refusal
+//! to replace a file that already exists. When a prompt or safety check refuses an operation, the
+//! error names the flag that would allow it.
+//!
+//! With the global `--interactive` flag, when the standard input is a terminal, the failure of an
+//! item of a batch, such as a command of a script, asks whether to retry the item, skip it, abort
+//! the batch or skip every failure from then on. The answer is fed back to the batch by the
+//! recovery hook of its [`ErrorCollection`](crate::ErrorCollection), see [`recovery_hook`].
+//!
+//! ```text
+//! ops.tyg:4: Failed at cycle 5
+//! Retry, skip, abort or always skip? [r/s/a/A]
+//! ```
+//!
+//! ```
+//! use std::io::Cursor;
+//! use tyg_template::prompt::{confirm, Overrides};
+//! use tyg_template::{assert_err_msg, Error};
+//!
+//! let mut out = Vec::new();
+//! let overrides = Overrides::default();
+//!
+//! assert!(confirm("Delete 3 files?", &overrides, &mut Cursor::new("y\n"), &mut out).is_ok());
+//! assert_err_msg!(confirm("Delete 3 files?", &overrides, &mut Cursor::new("n\n"), &mut out),
+//!                 Error::Error(_), "Delete 3 files? Not confirmed, use --yes to answer yes");
+//!
+//! let overrides = Overrides { yes: true, force: false };
+//! assert!(confirm("Delete 3 files?", &overrides, &mut Cursor::new(""), &mut out).is_ok());
+//! ```
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use crate::{globals, result_err_bare, Error, Recovery, Result};
+
+/// The flags that bypass confirmation prompts and safety checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Overrides {
+    /// Assume the answer yes to every confirmation prompt, given by `--yes`.
+    pub yes: bool,
+    /// Override safety checks, given by `--force`.
+    pub force: bool,
+}
+
+impl Overrides {
+    /// The overrides selected on the command line of the current run, as given by
+    /// [`globals::current`], or none if there is none.
+    pub fn selected() -> Overrides {
+        globals::current().overrides
+    }
+
+    /// Check that a path can be written, which it cannot if it already exists, unless `--force`
+    /// is given.
+    ///
+    /// # Examples
+    /// ```
+    /// use tyg_template::prompt::Overrides;
+    /// use tyg_template::{assert_err_msg, Error};
+    ///
+    /// assert_err_msg!(Overrides::default().check_absent("Cargo.toml"), Error::Error(_),
+    ///                 "Cargo.toml: Already exists, use --force to replace it");
+    /// assert!(Overrides { yes: false, force: true }.check_absent("Cargo.toml").is_ok());
+    /// assert!(Overrides::default().check_absent("no_such_file.txt").is_ok());
+    /// ```
+    pub fn check_absent(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if !self.force && path.exists() {
+            return result_err_bare!(
+                "{}: Already exists, use --force to replace it",
+                path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Ask the question and read the answer, succeeding if it is yes, or without asking if `--yes` is
+/// given. The question should end with a question mark.
+pub fn confirm(
+    question: &str,
+    overrides: &Overrides,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if overrides.yes {
+        return Ok(());
+    }
+    write!(out, "{} [y/N] ", question)?;
+    out.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => result_err_bare!("{} Not confirmed, use --yes to answer yes", question),
+    }
+}
+
+/// Ask the question on the terminal, with the overrides selected on the command line, succeeding
+/// if the answer is yes. When the standard input is not a terminal no one can answer, so the
+/// question is refused unless `--yes` is given.
+pub fn confirm_stdin(question: &str, out: &mut dyn Write) -> Result<()> {
+    let overrides = Overrides::selected();
+    if !overrides.yes && !io::stdin().is_terminal() {
+        return result_err_bare!(
+            "{} Cannot ask for confirmation as the input is not a terminal, use --yes to answer yes",
+            question
+        );
+    }
+    confirm(question, &overrides, &mut io::stdin().lock(), out)
+}
+
+/// Whether `--interactive` was given on the command line of the current run, as given by
+/// [`globals::current`].
+pub fn interactive() -> bool {
+    globals::current().interactive
+}
+
+/// Show the failure of an item of a batch and ask what to do about it, asking again until the
+/// answer is one of the choices. The batch is aborted if no answer can be read, e.g. at the end of
+/// the input.
+///
+/// # Examples
+/// ```
+/// use std::io::Cursor;
+/// use tyg_template::prompt::recover;
+/// use tyg_template::{Error, Recovery};
+///
+/// let error = Error::Error("Failed at cycle 5".to_string());
+/// let mut out = Vec::new();
+///
+/// assert_eq!(recover("ops.tyg:4", &error, &mut Cursor::new("maybe\nr\n"), &mut out), Recovery::Retry);
+/// assert_eq!(String::from_utf8(out).unwrap(),
+///            "ops.tyg:4: Failed at cycle 5\n\
+///             Retry, skip, abort or always skip? [r/s/a/A] \
+///             Retry, skip, abort or always skip? [r/s/a/A] ");
+///
+/// let mut out = Vec::new();
+/// assert_eq!(recover("ops.tyg:4", &error, &mut Cursor::new("A\n"), &mut out), Recovery::AlwaysSkip);
+/// assert_eq!(recover("ops.tyg:4", &error, &mut Cursor::new(""), &mut out), Recovery::Abort);
+/// ```
+pub fn recover(
+    context: &str,
+    error: &Error,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Recovery {
+    let _ = writeln!(out, "{}: {}", context, error);
+    loop {
+        let _ = write!(out, "Retry, skip, abort or always skip? [r/s/a/A] ");
+        let _ = out.flush();
+        let mut answer = String::new();
+        match input.read_line(&mut answer) {
+            Ok(0) | Err(_) => return Recovery::Abort,
+            Ok(_) => {}
+        }
+        match answer.trim() {
+            "r" | "retry" => return Recovery::Retry,
+            "s" | "skip" => return Recovery::Skip,
+            "a" | "abort" => return Recovery::Abort,
+            "A" | "always skip" | "always-skip" => return Recovery::AlwaysSkip,
+            _ => continue,
+        }
+    }
+}
+
+/// The recovery hook of a batch, which asks what to do about each failure on the terminal, if
+/// `--interactive` is given and the standard input is a terminal, or otherwise `None`. The
+/// question is written to the error stream, away from the output of the batch.
+pub fn recovery_hook() -> Option<impl FnMut(&str, &Error) -> Recovery + 'static> {
+    if !interactive() || !io::stdin().is_terminal() {
+        return None;
+    }
+    Some(|context: &str, error: &Error| {
+        recover(context, error, &mut io::stdin().lock(), &mut io::stderr())
+    })
+}
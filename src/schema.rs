@@ -0,0 +1,138 @@
+//! A machine-readable description of the command line interface.
+//!
+//! The hidden `dump-cli-schema` subcommand prints the description as JSON for external tools such
+//! as documentation generators and graphical wrappers. The schema is stable: its `schema_version`
+//! is only incremented when a field is removed or its meaning changes, and the keys of each object
+//! are sorted so that the output only changes when the interface does.
+//!
+//! The document holds the version of the schema and the root command. Each command has its
+//! `name`, `about`, `version`, `hidden` and `subcommand_required` fields, its `args` and its
+//! `subcommands`, which are commands in turn. Each argument has the following fields:
+//!
+//! - `id`, `short`, `long` and `help`, with `null` for those that are not set.
+//! - `positional` and `index`, the position of a positional argument starting from 1.
+//! - `type`, one of `flag`, `count`, `string`, `os_string`, `path` or `value`.
+//! - `value_names`, `possible_values` and `default_values`.
+//! - `env`, the environment variable read when the argument is not given, or `null`.
+//! - `required`, `global`, `hidden` and `multiple`.
+//!
+//! The help and version arguments and the help subcommand that clap adds are included, and global
+//! arguments are listed by each subcommand to which they apply.
+
+use std::any::TypeId;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, Command};
+use serde_json::{json, Value};
+
+use crate::{cli, Result};
+
+/// The version of the schema, incremented when a field is removed or its meaning changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The schema of the command line interface.
+///
+/// # Examples
+/// ```
+/// use tyg_template::schema::cli_schema;
+///
+/// let schema = cli_schema();
+/// let command = &schema["command"];
+///
+/// assert_eq!(schema["schema_version"], 1);
+/// assert_eq!(command["name"], "tyg_template");
+///
+/// let fail = command["subcommands"].as_array().unwrap()
+///     .iter()
+///     .find(|sub| sub["name"] == "fail")
+///     .unwrap();
+/// let bare = fail["args"].as_array().unwrap()
+///     .iter()
+///     .find(|arg| arg["id"] == "bare")
+///     .unwrap();
+/// assert_eq!(bare["long"], "bare");
+/// assert_eq!(bare["type"], "flag");
+/// ```
+pub fn cli_schema() -> Value {
+    let mut command = cli();
+    command.build();
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "command": command_schema(&command),
+    })
+}
+
+/// Write the schema of the command line interface as pretty printed JSON.
+pub fn dump(out: &mut dyn Write) -> Result<()> {
+    writeln!(out, "{:#}", cli_schema())?;
+    Ok(())
+}
+
+fn command_schema(command: &Command) -> Value {
+    json!({
+        "name": command.get_name(),
+        "about": command.get_about().map(|about| about.to_string()),
+        "version": command.get_version(),
+        "hidden": command.is_hide_set(),
+        "subcommand_required": command.is_subcommand_required_set(),
+        "args": command.get_arguments().map(arg_schema).collect::<Vec<_>>(),
+        "subcommands": command.get_subcommands().map(command_schema).collect::<Vec<_>>(),
+    })
+}
+
+fn arg_schema(arg: &Arg) -> Value {
+    json!({
+        "id": arg.get_id().as_str(),
+        "short": arg.get_short().map(String::from),
+        "long": arg.get_long(),
+        "help": arg.get_help().map(|help| help.to_string()),
+        "positional": arg.is_positional(),
+        "index": arg.get_index(),
+        "type": value_type(arg),
+        "value_names": arg.get_value_names()
+            .unwrap_or_default()
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>(),
+        "possible_values": arg.get_possible_values()
+            .iter()
+            .map(|value| value.get_name().to_string())
+            .collect::<Vec<_>>(),
+        "default_values": arg.get_default_values()
+            .iter()
+            .map(|value| value.to_string_lossy().into_owned())
+            .collect::<Vec<_>>(),
+        "env": arg.get_env().map(|name| name.to_string_lossy().into_owned()),
+        "required": arg.is_required_set(),
+        "global": arg.is_global_set(),
+        "hidden": arg.is_hide_set(),
+        "multiple": arg.get_num_args().is_some_and(|range| range.max_values() > 1)
+            || matches!(arg.get_action(), ArgAction::Append),
+    })
+}
+
+// The type of the value taken by the argument, named independently of the Rust type
+fn value_type(arg: &Arg) -> &'static str {
+    match arg.get_action() {
+        ArgAction::SetTrue
+        | ArgAction::SetFalse
+        | ArgAction::Help
+        | ArgAction::HelpShort
+        | ArgAction::HelpLong
+        | ArgAction::Version => return "flag",
+        ArgAction::Count => return "count",
+        _ => (),
+    }
+    let id = arg.get_value_parser().type_id();
+    if id == TypeId::of::<String>() {
+        "string"
+    } else if id == TypeId::of::<OsString>() {
+        "os_string"
+    } else if id == TypeId::of::<PathBuf>() {
+        "path"
+    } else {
+        "value"
+    }
+}
@@ -0,0 +1,117 @@
+//! The `run-script` subcommand, which runs a batch of subcommands read from a file.
+//!
+//! Each line of the script is a command line of the application without its name, split into
+//! words as a POSIX shell would (see the [`shellwords`](crate::shellwords) module). Blank lines and
+//! comments starting with `#` are skipped. A line may give global options for its command, such
+//! as `--yes` or `--sandbox`, on top of those given to `run-script`, but not the options of the
+//! whole run, such as `--summary` or `--max-runtime`. The commands are run in order, writing to the
+//! same output streams. By default the script stops at the first command that fails, with its error
+//! attributed to the file and line of the command. With `--keep-going` the rest of the commands
+//! are run, and the script fails at the end with a summary of the commands that failed. With the
+//! global `--interactive` flag, on a terminal, each failure instead asks whether to run the command
+//! again, skip it or abort the script (see the [`prompt`](crate::prompt) module).
+//!
+//! ```text
+//! # Nightly checks
+//! file_fail --better data/input.txt
+//! fail --bare
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use crate::platform::long_path;
+use crate::{
+    limits, progress, prompt, result_err_bare, shellwords, summary, ErrorCollection, Recovery,
+    Result, Streams,
+};
+
+/// Run the commands of the script in the file with the given function, which is passed the words
+/// of each command and the streams.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use tyg_template::testkit::TempProject;
+/// use tyg_template::{assert_err_msg, result_err_bare, script, Error, Streams};
+///
+/// let _project = TempProject::builder()
+///     .file("ops.tyg", "# Checks\nfirst\n\nsecond 'two words'\nthird\n")
+///     .create()
+///     .unwrap();
+/// let mut run = |words: &[String], _: &mut Streams| match words[0].as_str() {
+///     "second" => result_err_bare!("{} failed", words[1]),
+///     _ => Ok(()),
+/// };
+/// let (mut out, mut err) = (Vec::new(), Vec::new());
+/// let mut streams = Streams::new(&mut out, &mut err);
+///
+/// assert_err_msg!(script::run("ops.tyg", false, &mut streams, &mut run), Error::Error(_),
+///                 "ops.tyg:4: *two words failed");
+/// assert_err_msg!(script::run("ops.tyg", true, &mut streams, &mut run), Error::Error(_),
+///                 "ops.tyg: 1 of 3 failed:\n  ops.tyg:4: *two words failed");
+/// ```
+pub fn run(
+    path: impl AsRef<Path>,
+    keep_going: bool,
+    streams: &mut Streams,
+    run_command: &mut dyn FnMut(&[String], &mut Streams) -> Result<()>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(long_path(path))
+        .or_else(|e| result_err_bare!("{}: {}", path.display(), e))?;
+    // Without a hook the failures are kept, and the script carries on
+    let mut errors = match prompt::recovery_hook() {
+        Some(hook) => ErrorCollection::with_recovery(hook),
+        None if keep_going => ErrorCollection::new(),
+        None => ErrorCollection::with_recovery(|_, _| Recovery::Abort),
+    };
+    let commands = text
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .count();
+    let task = progress::start(
+        format!("run-script {}", path.display()),
+        Some(commands as u64),
+    );
+    match run_commands(path, &text, &task, &mut errors, streams, run_command) {
+        Err(e) => {
+            task.fail(&e);
+            Err(e)
+        }
+        result => result,
+    }
+}
+
+// Run the commands of the script, reporting them as the items of the task
+fn run_commands(
+    path: &Path,
+    text: &str,
+    task: &progress::Task,
+    errors: &mut ErrorCollection,
+    streams: &mut Streams,
+    run_command: &mut dyn FnMut(&[String], &mut Streams) -> Result<()>,
+) -> Result<()> {
+    for (number, line) in text.lines().enumerate() {
+        let context = format!("{}:{}", path.display(), number + 1);
+        let words = match shellwords::split(line) {
+            Ok(words) if words.is_empty() => continue,
+            Ok(words) => words,
+            Err(e) => return result_err_bare!("{}: {}", context, e),
+        };
+        // A run that has exceeded a limit on its resources stops before the next command
+        limits::check()?;
+        task.item(&context);
+        let result = errors.attempt(&context, || run_command(&words, streams));
+        task.advance(1);
+        if let Err(e) = result {
+            return result_err_bare!("{}: {}", context, e);
+        }
+    }
+    summary::stat("commands", errors.attempted());
+    summary::stat("failed", errors.failed());
+    match errors.is_empty() {
+        true => Ok(()),
+        false => result_err_bare!("{}: {}", path.display(), errors),
+    }
+}
@@ -0,0 +1,174 @@
+//! Storage of secrets, such as API tokens, in the keyring of the platform.
+//!
+//! The secrets are stored by service and account, e.g. the name of the application and `token`,
+//! in the macOS Keychain, the Windows Credential Manager or the Linux kernel keyring, when the
+//! `keyring` feature is enabled, which adds a dependency on [keyring](https://docs.rs/keyring).
+//! An environment variable named after the service and account, e.g. `TYG_TEMPLATE_TOKEN`, takes
+//! the place of the keyring in headless environments such as CI, where there is no keyring, or
+//! builds without the feature. The variable takes precedence over the keyring when it is set.
+//!
+//! ```
+//! use std::env;
+//! use tyg_template::secrets;
+//!
+//! env::set_var("EXAMPLE_SERVICE_TOKEN", "s3cr3t");
+//!
+//! assert_eq!(secrets::get("example-service", "token").unwrap(), "s3cr3t");
+//! ```
+
+use std::env;
+use std::io::Write;
+
+use crate::Result;
+
+/// The service under which the `config auth` subcommand stores its token.
+pub const SERVICE: &str = env!("CARGO_PKG_NAME");
+
+/// The account under which the `config auth` subcommand stores its token.
+pub const ACCOUNT: &str = "token";
+
+/// The environment variable that takes the place of the keyring for the account of the service,
+/// named in upper case with anything other than letters and digits replaced by underscores.
+///
+/// # Examples
+/// ```
+/// use tyg_template::secrets::env_var;
+///
+/// assert_eq!(env_var("tyg_template", "token"), "TYG_TEMPLATE_TOKEN");
+/// assert_eq!(env_var("api.example.com", "ci-bot"), "API_EXAMPLE_COM_CI_BOT");
+/// ```
+pub fn env_var(service: &str, account: &str) -> String {
+    format!("{}_{}", service, account)
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect()
+}
+
+/// Get the secret of the account of the service, from its environment variable when it is set,
+/// or from the keyring.
+///
+/// # Examples
+/// ```
+/// use tyg_template::{assert_err_msg, secrets, Error};
+///
+/// assert_err_msg!(secrets::get("example-service", "nobody"), Error::Error(_),
+///                 "nobody of example-service: * set EXAMPLE_SERVICE_NOBODY*");
+/// ```
+pub fn get(service: &str, account: &str) -> Result<String> {
+    let var = env_var(service, account);
+    match env::var(&var) {
+        Ok(secret) if !secret.is_empty() => Ok(secret),
+        _ => store::get(service, account, &var),
+    }
+}
+
+/// Store the secret of the account of the service in the keyring, replacing any stored before.
+pub fn set(service: &str, account: &str, secret: &str) -> Result<()> {
+    store::set(service, account, secret, &env_var(service, account))
+}
+
+/// Delete the secret of the account of the service from the keyring. The environment variable,
+/// which belongs to the environment of the caller, is left as it is.
+pub fn delete(service: &str, account: &str) -> Result<()> {
+    store::delete(service, account, &env_var(service, account))
+}
+
+/// The `config auth` subcommand, which stores the token when one is given, deletes it, or
+/// otherwise shows the start of the token in use so that it can be told from others.
+pub fn auth(token: Option<&str>, delete: bool, out: &mut dyn Write) -> Result<()> {
+    if let Some(token) = token {
+        set(SERVICE, ACCOUNT, token)?;
+        writeln!(out, "The token has been stored in the keyring")?;
+    } else if delete {
+        self::delete(SERVICE, ACCOUNT)?;
+        writeln!(out, "The token has been deleted from the keyring")?;
+    } else {
+        let token = get(SERVICE, ACCOUNT)?;
+        let start: String = token.chars().take(4).collect();
+        writeln!(out, "Token: {}****", start)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "keyring")]
+mod store {
+    use keyring::{Entry, Error as KeyringError};
+
+    use crate::{result_err_bare, Result};
+
+    pub fn get(service: &str, account: &str, var: &str) -> Result<String> {
+        match entry(service, account, var)?.get_password() {
+            Ok(secret) => Ok(secret),
+            Err(e) => failure(service, account, var, e),
+        }
+    }
+
+    pub fn set(service: &str, account: &str, secret: &str, var: &str) -> Result<()> {
+        match entry(service, account, var)?.set_password(secret) {
+            Ok(()) => Ok(()),
+            Err(e) => failure(service, account, var, e),
+        }
+    }
+
+    pub fn delete(service: &str, account: &str, var: &str) -> Result<()> {
+        match entry(service, account, var)?.delete_credential() {
+            Ok(()) => Ok(()),
+            Err(e) => failure(service, account, var, e),
+        }
+    }
+
+    fn entry(service: &str, account: &str, var: &str) -> Result<Entry> {
+        Entry::new(service, account).or_else(|e| failure(service, account, var, e))
+    }
+
+    fn failure<T>(service: &str, account: &str, var: &str, e: KeyringError) -> Result<T> {
+        match e {
+            KeyringError::NoEntry => result_err_bare!(
+                "{} of {}: Not found in the keyring, store it or set {}",
+                account,
+                service,
+                var
+            ),
+            KeyringError::NoStorageAccess(e) | KeyringError::PlatformFailure(e) => {
+                result_err_bare!(
+                    "{} of {}: The keyring cannot be used ({}), set {} instead",
+                    account,
+                    service,
+                    e,
+                    var
+                )
+            }
+            e => result_err_bare!("{} of {}: {}", account, service, e),
+        }
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+mod store {
+    use crate::{result_err_bare, Result};
+
+    pub fn get(service: &str, account: &str, var: &str) -> Result<String> {
+        unsupported(service, account, var)
+    }
+
+    pub fn set(service: &str, account: &str, _secret: &str, var: &str) -> Result<()> {
+        unsupported(service, account, var)
+    }
+
+    pub fn delete(service: &str, account: &str, var: &str) -> Result<()> {
+        unsupported(service, account, var)
+    }
+
+    fn unsupported<T>(service: &str, account: &str, var: &str) -> Result<T> {
+        result_err_bare!(
+            "{} of {}: The keyring is not supported by this build, rebuild it with \
+             --features=keyring, or set {}",
+            account,
+            service,
+            var
+        )
+    }
+}
@@ -0,0 +1,168 @@
+//! Quoting of words for the shell, and splitting of command lines into words.
+//!
+//! Commands that print a suggested invocation, or compose a command line for `sh -c`, must quote
+//! each word so that the shell passes it on unchanged, whatever characters it holds. [`quote`]
+//! and [`join`] quote for POSIX shells, and [`quote_powershell`] for PowerShell. [`split`] does
+//! the reverse, splitting a line into words by the rules of a POSIX shell, for commands read from
+//! a file or typed at a prompt.
+//!
+//! ```
+//! use tyg_template::shellwords::{join, split};
+//!
+//! let words = ["file_fail", "--better", "my notes.txt", "it's"];
+//! let line = join(words);
+//!
+//! assert_eq!(line, r#"file_fail --better 'my notes.txt' 'it'\''s'"#);
+//! assert_eq!(split(&line).unwrap(), words);
+//! ```
+
+use std::borrow::Cow;
+
+use crate::{result_err_bare, Result};
+
+// Whether the character never needs quoting, in either shell
+fn plain(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_-+=@%:,./".contains(c)
+}
+
+/// Quote a word for a POSIX shell. Words made only of letters, digits and `_-+=@%:,./` are left
+/// as they are, and any other word is put in single quotes.
+///
+/// # Examples
+/// ```
+/// use tyg_template::shellwords::quote;
+///
+/// assert_eq!(quote("notes.txt"), "notes.txt");
+/// assert_eq!(quote("$HOME/*"), "'$HOME/*'");
+/// assert_eq!(quote("it's"), r"'it'\''s'");
+/// assert_eq!(quote(""), "''");
+/// ```
+pub fn quote(word: &str) -> Cow<'_, str> {
+    if !word.is_empty() && word.chars().all(plain) {
+        return Cow::Borrowed(word);
+    }
+    // A single quote cannot appear within single quotes, so it is escaped between them
+    Cow::Owned(format!("'{}'", word.replace('\'', r"'\''")))
+}
+
+/// Quote the words for a POSIX shell and join them with spaces.
+pub fn join<I, S>(words: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    words
+        .into_iter()
+        .map(|word| quote(word.as_ref()).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quote a word for PowerShell. Words made only of letters, digits and `_-+=@%:,./` are left as
+/// they are, apart from a leading `@` or `-`, which PowerShell would take for a splat or a
+/// parameter, and any other word is put in single quotes. PowerShell takes the typographic single
+/// quotes for quotes too, so they are doubled along with the ASCII quote.
+///
+/// # Examples
+/// ```
+/// use tyg_template::shellwords::quote_powershell;
+///
+/// assert_eq!(quote_powershell("notes.txt"), "notes.txt");
+/// assert_eq!(quote_powershell("$env:PATH"), "'$env:PATH'");
+/// assert_eq!(quote_powershell("it's"), "'it''s'");
+/// assert_eq!(quote_powershell("it\u{2019}s"), "'it\u{2019}\u{2019}s'");
+/// assert_eq!(quote_powershell("@args"), "'@args'");
+/// ```
+pub fn quote_powershell(word: &str) -> Cow<'_, str> {
+    if !word.is_empty() && !word.starts_with(['@', '-']) && word.chars().all(plain) {
+        return Cow::Borrowed(word);
+    }
+    let mut quoted = String::with_capacity(word.len() + 2);
+    quoted.push('\'');
+    for c in word.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    Cow::Owned(quoted)
+}
+
+/// Split a line into words by the rules of a POSIX shell.
+///
+/// Words are separated by white space. Within single quotes every character is taken as it is.
+/// Within double quotes a backslash only escapes `$`, `` ` ``, `"`, `\` and a new line. Elsewhere a
+/// backslash escapes the character after it, and a `#` at the start of a word starts a comment.
+/// A backslash before a new line joins the lines. No expansion is done, so `$HOME` and `*` are
+/// taken as they are.
+///
+/// # Examples
+/// ```
+/// use tyg_template::shellwords::split;
+/// use tyg_template::{assert_err_msg, Error};
+///
+/// assert_eq!(split(r#"fail --bare  "two words" 'it''s' a\ b # comment"#).unwrap(),
+///            ["fail", "--bare", "two words", "its", "a b"]);
+/// assert_eq!(split("''").unwrap(), [""]);
+/// assert!(split("   ").unwrap().is_empty());
+///
+/// assert_err_msg!(split("fail 'unclosed"), Error::Error(_),
+///                 "fail 'unclosed: The ' at position 6 is not closed");
+/// ```
+pub fn split(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    // The word being read, or None between words
+    let mut word: Option<String> = None;
+    let mut chars = line.chars().enumerate().peekable();
+    while let Some((position, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+            }
+            '#' if word.is_none() => break,
+            '\\' => match chars.next() {
+                Some((_, '\n')) => {}
+                Some((_, c)) => word.get_or_insert_with(String::new).push(c),
+                None => return result_err_bare!("{}: The line ends with a \\", line),
+            },
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some((_, '\'')) => break,
+                        Some((_, c)) => word.push(c),
+                        None => return unclosed(line, '\'', position),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next_if(|(_, c)| "$`\"\\\n".contains(*c)) {
+                            Some((_, '\n')) => {}
+                            Some((_, c)) => word.push(c),
+                            None => word.push('\\'),
+                        },
+                        Some((_, c)) => word.push(c),
+                        None => return unclosed(line, '"', position),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+fn unclosed(line: &str, quote: char, position: usize) -> Result<Vec<String>> {
+    result_err_bare!(
+        "{}: The {} at position {} is not closed",
+        line,
+        quote,
+        position + 1
+    )
+}
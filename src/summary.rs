@@ -0,0 +1,133 @@
+//! The summary of a run written for automation by the global `--summary json` option.
+//!
+//! After the subcommand completes, successfully or not, a single JSON object is written to the
+//! error stream, or to the file named by `--summary-file`, so that it never mixes with output
+//! piped from the standard output. It holds the status, the exit code, the subcommand, the time
+//! taken, the numbers of errors, findings and warnings, the message and [kind](crate::ErrorKind) of the error
+//! of a failed run and the key statistics recorded by the subcommand with [`stat`]:
+//!
+//! ```text
+//! {"duration_ms":42,"error":null,"error_kind":null,"errors":0,"exit_code":0,"findings":0,"stats":{"commands":3},"status":"success","subcommand":"run-script","warnings":0}
+//! ```
+//!
+//! A failure to write the summary is reported as a warning and never changes the result of the
+//! command.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::outcome::Outcome;
+use crate::platform::long_path;
+use crate::{result_err_bare, Error, ErrorFormat};
+
+/// The names of the formats of the summary.
+pub const FORMATS: [&str; 1] = ["json"];
+
+// The key statistics recorded by the subcommand
+static STATS: Mutex<BTreeMap<String, Value>> = Mutex::new(BTreeMap::new());
+
+/// Record a key statistic of the run, such as the number of items processed, to be included in
+/// the summary. A statistic recorded again replaces the earlier value.
+///
+/// # Examples
+/// ```
+/// use tyg_template::summary;
+///
+/// summary::stat("files_written", 3);
+/// summary::stat("target", "release");
+/// ```
+pub fn stat(name: &str, value: impl Into<Value>) {
+    let mut stats = STATS.lock().unwrap_or_else(|e| e.into_inner());
+    stats.insert(name.to_string(), value.into());
+}
+
+/// The outcome of a run of the application, to be summarised.
+#[derive(Debug)]
+pub struct Summary<'a> {
+    /// The name of the subcommand run.
+    pub subcommand: &'a str,
+    /// The time taken to run it.
+    pub duration: Duration,
+    /// The number of warnings shown.
+    pub warnings: usize,
+    /// The outcome of the run.
+    pub outcome: &'a Outcome,
+}
+
+impl Summary<'_> {
+    /// The summary as a JSON object, with the statistics given.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::time::Duration;
+    /// use tyg_template::outcome::Outcome;
+    /// use tyg_template::summary::Summary;
+    /// use tyg_template::{Error, ErrorKind};
+    ///
+    /// let error = Error::Error("No such file".to_string()).with_kind(ErrorKind::NotFound);
+    /// let summary = Summary {
+    ///     subcommand: "file_fail",
+    ///     duration: Duration::from_millis(42),
+    ///     warnings: 1,
+    ///     outcome: &Outcome::Err(error),
+    /// };
+    /// let stats = BTreeMap::from([("files".to_string(), 0.into())]);
+    ///
+    /// assert_eq!(summary.to_json(&stats),
+    ///            r#"{"duration_ms":42,"error":"No such file","error_kind":"not_found","#.to_string()
+    ///            + r#""errors":1,"exit_code":66,"findings":0,"stats":{"files":0},"status":"failure","#
+    ///            + r#""subcommand":"file_fail","warnings":1}"#);
+    /// ```
+    pub fn to_json(&self, stats: &BTreeMap<String, Value>) -> String {
+        let error = self.outcome.error();
+        let (status, findings) = match *self.outcome {
+            Outcome::Success | Outcome::Findings(0) => ("success", 0),
+            Outcome::Findings(n) => ("findings", n),
+            Outcome::Err(_) => ("failure", 0),
+        };
+        json!({
+            "status": status,
+            "exit_code": self.outcome.exit_code(),
+            "subcommand": self.subcommand,
+            "duration_ms": u64::try_from(self.duration.as_millis()).unwrap_or(u64::MAX),
+            "errors": usize::from(error.is_some()),
+            "findings": findings,
+            "warnings": self.warnings,
+            "error": error.map(Error::to_string),
+            "error_kind": error.map(|e| e.kind().name()),
+            "stats": stats,
+        })
+        .to_string()
+    }
+}
+
+/// Write the summary, with the statistics recorded, in the format given to the file, or to the
+/// error stream if no file is given. A failure is written to the error stream as a warning, so
+/// that it cannot mask the result of the command.
+pub fn write(summary: &Summary, format: &str, file: Option<&Path>, err: &mut dyn Write) {
+    let stats = STATS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let text = match format {
+        "json" => summary.to_json(&stats),
+        _ => return,
+    };
+    let result = match file {
+        Some(path) => fs::write(long_path(path), format!("{}\n", text))
+            .or_else(|e| result_err_bare!("{}: {}", path.display(), e)),
+        None => writeln!(err, "{}", text).map_err(Error::from),
+    };
+    if let Err(e) = result {
+        let message = format!("The summary was not written: {}", e);
+        let _ = writeln!(
+            err,
+            "{}",
+            ErrorFormat::selected().warning(env!("CARGO_PKG_NAME"), &message)
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Integration with systemd for applications run as services.
+//!
+//! This module is available when the `systemd` feature is enabled. It lets the service manager
+//! know when the application is ready and when it is stopping, using the `sd_notify` protocol, and
+//! sends errors to the journal with their priority and disclosed source location as separate
+//! fields. Everything here does nothing unless the application is run by systemd, and nothing
+//! outside Unix.
+//!
+//! [`run`](crate::run) sends the notifications, and the main program reports errors with
+//! [`report_error`] when the feature is enabled.
+
+use std::env;
+use std::io;
+
+use crate::{Error, Location};
+
+// The socket on which journald receives entries using its native protocol
+#[cfg(unix)]
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+// The syslog priority of errors
+const PRIORITY_ERROR: &str = "3";
+
+/// Send a state change, e.g. `READY=1` or `STOPPING=1`, to the service manager.
+///
+/// Returns `Ok(false)` without doing anything if the application was not started by a service
+/// manager expecting notifications, i.e. if the `NOTIFY_SOCKET` environment variable is not set.
+///
+/// # Examples
+/// ```
+/// # #[cfg(unix)] {
+/// use std::os::unix::net::UnixDatagram;
+/// use tyg_template::systemd::notify;
+/// use tyg_template::testkit::TempProject;
+///
+/// let project = TempProject::builder().create().unwrap();
+/// let socket = UnixDatagram::bind(project.path("notify")).unwrap();
+///
+/// std::env::set_var("NOTIFY_SOCKET", project.path("notify"));
+/// assert!(notify("READY=1").unwrap());
+/// std::env::remove_var("NOTIFY_SOCKET");
+/// assert!(!notify("STOPPING=1").unwrap());
+///
+/// let mut buffer = [0; 64];
+/// let length = socket.recv(&mut buffer).unwrap();
+/// assert_eq!(&buffer[..length], b"READY=1");
+/// # }
+/// ```
+pub fn notify(state: &str) -> io::Result<bool> {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return Ok(false);
+    };
+    send(&path, state.as_bytes())?;
+    Ok(true)
+}
+
+/// Check whether the standard error stream is connected to the journal.
+///
+/// systemd sets the `JOURNAL_STREAM` environment variable to the device and inode numbers of the
+/// stream it connects to the journal, which are compared with those of the standard error stream.
+pub fn journal_connected() -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let Ok(stream) = env::var("JOURNAL_STREAM") else {
+            return false;
+        };
+        let Ok(stderr) = std::fs::metadata("/dev/stderr") else {
+            return false;
+        };
+        stream == format!("{}:{}", stderr.dev(), stderr.ino())
+    }
+    #[cfg(not(unix))]
+    false
+}
+
+/// Send an entry made up of the given fields to the journal.
+///
+/// The field names must be in upper case, e.g. `MESSAGE` or `PRIORITY`. Values may contain any
+/// text, including newlines.
+pub fn journal_send(fields: &[(&str, &str)]) -> io::Result<()> {
+    let mut entry = Vec::new();
+    for (name, value) in fields {
+        entry.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            // Values containing newlines are sent with their length instead of after an =
+            entry.push(b'\n');
+            entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            entry.push(b'=');
+        }
+        entry.extend_from_slice(value.as_bytes());
+        entry.push(b'\n');
+    }
+    #[cfg(unix)]
+    return send(JOURNAL_SOCKET.as_ref(), &entry);
+    #[cfg(not(unix))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "There is no journal on this platform",
+    ))
+}
+
+/// Report an error to the journal if the standard error stream is connected to it.
+///
+/// The entry has the priority of an error and, if the error discloses its location, the source
+/// file, line and column of the error as the `CODE_FILE`, `CODE_LINE` and `CODE_COLUMN` fields.
+/// The message is the error shown after the prefix, without the location. Errors from the
+/// operating system also carry their error number in the `ERRNO` field.
+///
+/// Returns `true` if the error was sent to the journal, in which case it should not also be
+/// written to the standard error stream.
+pub fn report_error(prefix: &str, error: &Error) -> bool {
+    if !journal_connected() {
+        return false;
+    }
+    let text = error.to_string();
+    let (location, message) = match Location::split(&text) {
+        Some((location, message)) => (Some(location), message),
+        None => (None, text.as_str()),
+    };
+    let message = format!("{}: {}", prefix, message);
+    let (line, column) = location.map_or((String::new(), String::new()), |l| {
+        (l.line.to_string(), l.column.to_string())
+    });
+    let errno = match error {
+        Error::File(e) => e.raw_os_error().map(|n| n.to_string()),
+        _ => None,
+    };
+
+    let mut fields = vec![
+        ("MESSAGE", message.as_str()),
+        ("PRIORITY", PRIORITY_ERROR),
+        ("SYSLOG_IDENTIFIER", env!("CARGO_PKG_NAME")),
+    ];
+    if let Some(location) = location {
+        fields.extend([
+            ("CODE_FILE", location.file),
+            ("CODE_LINE", line.as_str()),
+            ("CODE_COLUMN", column.as_str()),
+        ]);
+    }
+    if let Some(ref errno) = errno {
+        fields.push(("ERRNO", errno));
+    }
+    journal_send(&fields).is_ok()
+}
+
+// Send a datagram to the socket with the given path, where a leading @ names an abstract socket
+#[cfg(unix)]
+fn send(path: &std::ffi::OsStr, data: &[u8]) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound()?;
+    match path.as_bytes().strip_prefix(b"@") {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Some(name) => {
+            #[cfg(target_os = "android")]
+            use std::os::android::net::SocketAddrExt;
+            #[cfg(target_os = "linux")]
+            use std::os::linux::net::SocketAddrExt;
+
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(data, &address)?;
+        }
+        _ => {
+            socket.send_to(data, path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send(_path: &std::ffi::OsStr, _data: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "There is no service manager on this platform",
+    ))
+}
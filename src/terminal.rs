@@ -0,0 +1,87 @@
+//! The capabilities of the terminal, or of the log, that the output is written to.
+//!
+//! Colors, cursor movement and animations such as a progress bar redrawn in place only work on a
+//! capable terminal. A dumb terminal, e.g. the shell of an editor with `TERM=dumb`, shows their
+//! escape sequences as garbage, and the log of a continuous integration (CI) service keeps every
+//! redraw of a progress bar as a line of its own. The output is therefore [plain](plain) when:
+//!
+//! - the `TERM` environment variable is `dumb`,
+//! - the application is running in CI, as told by the `CI` environment variable, unless it is
+//!   `false` or `0`, or by the variables of common CI services, such as `GITHUB_ACTIONS`,
+//! - or the [accessible mode](crate::accessibility) is on.
+//!
+//! Plain output has no colors or cursor movement, and progress is reported by plain status lines
+//! written every so often, one after another, in place of animations. Colors are also left out
+//! when the output is not a terminal or the `NO_COLOR` environment variable is set, see
+//! [`styled`].
+
+use std::env;
+
+use crate::accessibility;
+
+/// The environment variables set by common CI services, any of which tells that the application
+/// is running in CI.
+pub const CI_VARS: [&str; 10] = [
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "TRAVIS",
+    "APPVEYOR",
+    "JENKINS_URL",
+    "TEAMCITY_VERSION",
+    "TF_BUILD",
+    "BITBUCKET_BUILD_NUMBER",
+];
+
+/// Whether the terminal is dumb, i.e. the `TERM` environment variable is `dumb`.
+///
+/// # Examples
+/// ```
+/// use std::env;
+/// use tyg_template::terminal;
+///
+/// env::set_var("TERM", "dumb");
+/// assert!(terminal::dumb());
+/// env::set_var("TERM", "xterm-256color");
+/// assert!(!terminal::dumb());
+/// ```
+pub fn dumb() -> bool {
+    env::var_os("TERM").is_some_and(|term| term == "dumb")
+}
+
+/// Whether the application is running in CI.
+///
+/// # Examples
+/// ```
+/// use std::env;
+/// use tyg_template::terminal;
+///
+/// env::set_var("CI", "true");
+/// assert!(terminal::ci());
+/// env::set_var("CI", "false");
+/// for name in terminal::CI_VARS {
+///     env::remove_var(name);
+/// }
+/// assert!(!terminal::ci());
+/// env::set_var("GITLAB_CI", "true");
+/// assert!(terminal::ci());
+/// ```
+pub fn ci() -> bool {
+    let ci = env::var_os("CI").is_some_and(|value| {
+        !value.is_empty() && value != "0" && !value.eq_ignore_ascii_case("false")
+    });
+    ci || CI_VARS.iter().any(|name| env::var_os(name).is_some())
+}
+
+/// Whether the output should be plain, without colors, cursor movement or animations, as the
+/// terminal is dumb, the application is running in CI or the accessible mode is on.
+pub fn plain() -> bool {
+    accessibility::enabled() || dumb() || ci()
+}
+
+/// Whether output to a stream should be styled, which it should only be when the stream is a
+/// terminal, the output is not [plain](plain) and the `NO_COLOR` environment variable is not set.
+pub fn styled(is_terminal: bool) -> bool {
+    is_terminal && !plain() && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
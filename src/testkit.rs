@@ -0,0 +1,1100 @@
+//! Helpers for testing applications built from the template.
+//!
+//! Errors created by the template macros may or may not carry the location of their origin
+//! depending on the macro used and whether the `disclose` feature is enabled. The helpers in this
+//! module remove that variability so that assertions made in tests hold for every build.
+//!
+//! The module also provides [`capture`] for testing the subcommand handlers in-process,
+//! [`Snapshot`] for locking in the exact output of each subcommand against a checked-in snapshot
+//! file, [`Coverage`] for reporting which subcommands and arguments the tests exercise,
+//! [`Examples`] for checking that the documented example command lines still parse and
+//! [`TempProject`] for running tests in an isolated temporary directory and [`FakeClock`] for
+//! advancing the time of day by hand. The `strategy` module provides proptest strategies for
+//! property testing.
+//!
+//! The module is only compiled with the `testing` feature enabled, which the package enables for
+//! its own tests and doc examples.
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, SystemTime};
+
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command};
+
+use crate::clock::Clock;
+use crate::outcome::Outcome;
+use crate::{cli, globals, result_err, run_with_globals, ErrorFormat, Location, Result, Streams};
+
+#[cfg(feature = "testing")]
+pub mod strategy;
+
+// The environment variable that switches snapshot tests into update mode
+const UPDATE_VAR: &str = "TYG_UPDATE_SNAPSHOTS";
+
+// A function used to normalize a rendered snapshot
+type Redaction = Box<dyn Fn(&str) -> String>;
+
+//  assert_err macro
+/// Macro to assert that a `Result` is an error matching the given [`Error`](crate::Error) variant
+/// pattern, and optionally that it has the given [kind](crate::ErrorKind).
+///
+/// The pattern is matched against the error and against its [inner](crate::Error::inner) error,
+/// so that an error given a kind when it was created matches the variant it holds.
+///
+/// The macro panics with a descriptive message if the result is `Ok` or if the error does not
+/// match the pattern or the kind.
+///
+/// # Examples
+/// ```
+/// use tyg_template::{Result, Error, ErrorKind, assert_err, result_err};
+///
+/// fn generate_error() -> Result<()> {
+///     result_err!("This is a test error message")
+/// }
+///
+/// fn generate_config_error() -> Result<()> {
+///     result_err!(kind = ErrorKind::Config, "Invalid port")
+/// }
+///
+/// assert_err!(generate_error(), Error::Error(_));
+/// assert_err!(generate_config_error(), Error::Error(_), kind = ErrorKind::Config);
+/// ```
+///
+/// An `Ok` value, a different variant or a different kind causes a panic.
+/// ```should_panic
+/// use tyg_template::{Result, Error, assert_err};
+///
+/// let result: Result<u32> = Ok(42);
+///
+/// assert_err!(result, Error::Error(_));
+/// ```
+/// ```should_panic
+/// use tyg_template::{Result, Error, ErrorKind, assert_err, result_err};
+///
+/// let result: Result<u32> = result_err!("Not a configuration error");
+///
+/// assert_err!(result, Error::Error(_), kind = ErrorKind::Config);
+/// ```
+#[macro_export]
+macro_rules! assert_err {
+    ( $result:expr, $pattern:pat, kind = $kind:expr ) => {{
+        let result = $result;
+        $crate::testkit::assert_kind(&result, $kind);
+        $crate::assert_err!(result, $pattern)
+    }};
+    ( $result:expr, $pattern:pat ) => {{
+        match $result {
+            Err(ref e) if matches!(e, $pattern) || matches!(e.inner(), $pattern) => (),
+            Err(ref e) => panic!(
+                "assertion failed: expected error `{}`, found error: {}",
+                stringify!($pattern),
+                e
+            ),
+            Ok(_) => panic!(
+                "assertion failed: expected error `{}`, found Ok",
+                stringify!($pattern)
+            ),
+        }
+    }};
+}
+
+//  assert_err_msg macro
+/// Macro to assert that a `Result` is an error matching the given [`Error`](crate::Error) variant
+/// pattern, optionally with the given [kind](crate::ErrorKind), and that its message matches the
+/// expected text. The pattern and the kind are checked as [`assert_err!`] checks them.
+///
+/// The location of the error, if it was disclosed, is removed from the message before the
+/// comparison is made, so the assertion behaves the same whichever macro created the error and
+/// whether or not the `disclose` feature is enabled. The expected text may contain `*` wildcards
+/// that match any sequence of characters.
+///
+/// # Examples
+/// ```
+/// use tyg_template::{Result, Error, ErrorKind, assert_err_msg, result_err, result_err_bare};
+///
+/// fn generate_error(bare: bool) -> Result<()> {
+///     if bare {
+///         result_err_bare!("Unable to open {}", "config.toml")
+///     } else {
+///         result_err!("Unable to open {}", "config.toml")
+///     }
+/// }
+///
+/// assert_err_msg!(generate_error(false), Error::Error(_), "Unable to open config.toml");
+/// assert_err_msg!(generate_error(true), Error::Error(_), "Unable to open config.toml");
+/// assert_err_msg!(generate_error(true), Error::Error(_), "Unable to open *.toml");
+///
+/// let result: Result<()> = result_err_bare!(kind = ErrorKind::Config, "Invalid port {}", "http");
+/// assert_err_msg!(result, Error::Error(_), kind = ErrorKind::Config, "Invalid port http");
+/// ```
+#[macro_export]
+macro_rules! assert_err_msg {
+    ( $result:expr, $pattern:pat, kind = $kind:expr, $expected:expr ) => {{
+        let result = $result;
+        $crate::testkit::assert_kind(&result, $kind);
+        $crate::assert_err_msg!(result, $pattern, $expected)
+    }};
+    ( $result:expr, $pattern:pat, $expected:expr ) => {{
+        match $result {
+            Err(ref e) if matches!(e, $pattern) || matches!(e.inner(), $pattern) => {
+                let message = e.to_string();
+                let actual = $crate::testkit::strip_location(&message);
+                if !$crate::testkit::matches_pattern(actual, $expected) {
+                    panic!(
+                        "assertion failed: error message `{}` does not match `{}`",
+                        actual, $expected
+                    );
+                }
+            }
+            Err(ref e) => panic!(
+                "assertion failed: expected error `{}`, found error: {}",
+                stringify!($pattern),
+                e
+            ),
+            Ok(_) => panic!(
+                "assertion failed: expected error `{}`, found Ok",
+                stringify!($pattern)
+            ),
+        }
+    }};
+}
+
+// Check the kind of the error of a result for the assertion macros. An Ok value is reported by the
+// check of the pattern that follows.
+#[doc(hidden)]
+pub fn assert_kind<T>(result: &Result<T>, kind: crate::ErrorKind) {
+    if let Err(e) = result {
+        if e.kind() != kind {
+            panic!(
+                "assertion failed: expected an error of kind `{}`, found kind `{}`: {}",
+                kind,
+                e.kind(),
+                e
+            );
+        }
+    }
+}
+
+/// Remove a disclosed location (`file:line:column: `) from the start of an error message.
+///
+/// Messages without a location are returned unchanged.
+///
+/// # Examples
+/// ```
+/// use tyg_template::testkit::strip_location;
+///
+/// assert_eq!(strip_location("src/lib.rs:122:9: Something failed"), "Something failed");
+/// assert_eq!(strip_location("Something failed"), "Something failed");
+/// assert_eq!(strip_location("Ratio 1:2: too small"), "Ratio 1:2: too small");
+/// ```
+pub fn strip_location(message: &str) -> &str {
+    match location_length(message) {
+        Some(length) => &message[length..],
+        None => message,
+    }
+}
+
+/// Remove every disclosed location (`file:line:column: `) from the text.
+///
+/// Unlike [`strip_location`], the locations may appear anywhere in the text, for example after the
+/// name of the application in the output of the main program. Use this to normalize output so that
+/// the same assertions pass whether or not the `disclose` feature is enabled.
+///
+/// # Examples
+/// ```
+/// use tyg_template::testkit::strip_locations;
+///
+/// let output = "tyg_template: src/lib.rs:122:9: Something failed\n\
+///               Caused by: src/error.rs:10:5: Something else failed\n";
+///
+/// assert_eq!(strip_locations(output), "tyg_template: Something failed\n\
+///                                      Caused by: Something else failed\n");
+/// ```
+pub fn strip_locations(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut word_start = true;
+    while let Some(c) = rest.chars().next() {
+        if word_start {
+            if let Some(length) = location_length(rest) {
+                rest = &rest[length..];
+                continue;
+            }
+        }
+        word_start = c.is_whitespace();
+        result.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    result
+}
+
+/// Check whether the text matches a pattern in which `*` matches any sequence of characters.
+///
+/// # Examples
+/// ```
+/// use tyg_template::testkit::matches_pattern;
+///
+/// assert!(matches_pattern("Failed at cycle 5", "Failed at cycle 5"));
+/// assert!(matches_pattern("Failed at cycle 5", "Failed at *"));
+/// assert!(matches_pattern("Failed at cycle 5", "*cycle*"));
+/// assert!(!matches_pattern("Failed at cycle 5", "Failed"));
+/// ```
+pub fn matches_pattern(text: &str, pattern: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // The split always yields at least one part
+    let first = parts.next().unwrap_or("");
+    let mut rest = match text.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    match parts.split_last() {
+        None => rest.is_empty(),
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(index) => rest = &rest[index + part.len()..],
+                    None => return false,
+                }
+            }
+            rest.ends_with(last)
+        }
+    }
+}
+
+// Return the length of the disclosed location (`file:line:column: `) at the start of the text, if
+// there is one
+fn location_length(text: &str) -> Option<usize> {
+    Location::split(text).map(|(_, rest)| text.len() - rest.len())
+}
+
+/// Split raw bytes into command line arguments at each NUL byte.
+///
+/// This is used by the fuzz targets to turn arbitrary input into an argument vector. On Unix the
+/// arguments keep their exact bytes, so they are frequently not valid UTF-8. On other platforms
+/// invalid UTF-8 is converted lossily.
+///
+/// # Examples
+/// ```
+/// use std::ffi::OsString;
+/// use tyg_template::testkit::args_from_bytes;
+///
+/// let args = args_from_bytes(b"file_fail\0--better\0missing.txt");
+///
+/// assert_eq!(args, ["file_fail", "--better", "missing.txt"].map(OsString::from));
+/// assert!(args_from_bytes(b"").is_empty());
+/// ```
+pub fn args_from_bytes(data: &[u8]) -> Vec<OsString> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    data.split(|&b| b == 0).map(os_string_from_bytes).collect()
+}
+
+#[cfg(unix)]
+fn os_string_from_bytes(bytes: &[u8]) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+
+    OsString::from_vec(bytes.to_vec())
+}
+
+#[cfg(not(unix))]
+fn os_string_from_bytes(bytes: &[u8]) -> OsString {
+    OsString::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// The output captured by [`capture`] along with the result of the captured function.
+#[derive(Debug)]
+pub struct Captured<T> {
+    /// The text written to the output stream.
+    pub stdout: String,
+    /// The text written to the diagnostic stream.
+    pub stderr: String,
+    /// The result returned by the captured function.
+    pub result: Result<T>,
+}
+
+/// Call the function with a set of in-memory output streams and return the captured output along
+/// with the result of the function.
+///
+/// This allows the subcommand handlers to be tested in-process without spawning the application.
+/// Any output that is not valid UTF-8 is converted lossily. Outside a [`TempProject`], the function
+/// is called once no project exists in another thread, so that it never runs in the working
+/// directory and environment of another test.
+///
+/// # Examples
+/// ```
+/// use tyg_template::{Error, assert_err_msg, run_with_args};
+/// use tyg_template::testkit::capture;
+///
+/// let captured = capture(|streams| run_with_args(["tyg_template", "recursive_fail"], streams));
+///
+/// assert!(captured.stdout.starts_with("We need to fail at cycle 5\nCycle 1\n"));
+/// assert!(captured.stderr.is_empty());
+/// assert_err_msg!(captured.result, Error::Error(_), "Failed at cycle 5");
+/// ```
+pub fn capture<T>(f: impl FnOnce(&mut Streams) -> Result<T>) -> Captured<T> {
+    let _shared = share_environment();
+    let mut out = Vec::new();
+    let mut err = Vec::new();
+    let result = f(&mut Streams::new(&mut out, &mut err));
+
+    Captured {
+        stdout: String::from_utf8_lossy(&out).into_owned(),
+        stderr: String::from_utf8_lossy(&err).into_owned(),
+        result,
+    }
+}
+
+/// A snapshot (golden-file) test of the output produced by a command line.
+///
+/// The command line is processed by [`run_with_args`](crate::run_with_args) with the output
+/// streams captured. The captured output and the result are rendered as text, with an error
+/// reported as the application reports it, in the error format selected by the command line.
+/// Volatile details are normalized using the configured redactions and the text is compared
+/// against the snapshot file `<name>.snap` in the snapshot directory, which defaults to
+/// `tests/snapshots` in the package directory.
+///
+/// When the `TYG_UPDATE_SNAPSHOTS` environment variable is set to anything other than `0`, the
+/// snapshot files are written instead of compared:
+///
+/// ```text
+/// $ TYG_UPDATE_SNAPSHOTS=1 cargo test
+/// ```
+///
+/// By default the name and version of the package are replaced by `[NAME]` and `[VERSION]`, so the
+/// snapshots survive renaming the project, and disclosed error locations are removed using
+/// [`strip_locations`], so the snapshots are the same whether or not the `disclose` feature is
+/// enabled.
+///
+/// # Examples
+/// ```
+/// use tyg_template::testkit::Snapshot;
+///
+/// let snapshot = Snapshot::new("version")
+///     .redact("--version", "[FLAG]")
+///     .render(["example_bin", "--version"]);
+///
+/// assert_eq!(snapshot, "args: example_bin [FLAG]\n\
+///                       --- stdout ---\n\
+///                       [NAME] [VERSION]\n\
+///                       --- stderr ---\n\
+///                       --- result ---\n\
+///                       Ok\n");
+/// ```
+pub struct Snapshot {
+    name: String,
+    directory: PathBuf,
+    redactions: Vec<Redaction>,
+}
+
+impl Snapshot {
+    /// Create a snapshot test with the given name and the default redactions.
+    pub fn new(name: &str) -> Snapshot {
+        let root = env::var_os("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        Snapshot {
+            name: name.to_string(),
+            directory: root.join("tests").join("snapshots"),
+            redactions: Vec::new(),
+        }
+        .redact(env!("CARGO_PKG_VERSION"), "[VERSION]")
+        .redact(env!("CARGO_PKG_NAME"), "[NAME]")
+        .redact_with(strip_locations)
+    }
+
+    /// Use the given directory to hold the snapshot file.
+    pub fn directory(mut self, directory: impl Into<PathBuf>) -> Snapshot {
+        self.directory = directory.into();
+        self
+    }
+
+    /// Replace every occurrence of the given text with the replacement.
+    pub fn redact(self, text: &str, replacement: &str) -> Snapshot {
+        let text = text.to_string();
+        let replacement = replacement.to_string();
+        self.redact_with(move |snapshot| snapshot.replace(&text, &replacement))
+    }
+
+    /// Normalize the rendered snapshot using the given function.
+    pub fn redact_with(mut self, redaction: impl Fn(&str) -> String + 'static) -> Snapshot {
+        self.redactions.push(Box::new(redaction));
+        self
+    }
+
+    /// Process the command line and render the redacted snapshot text.
+    pub fn render<I, T>(&self, args: I) -> String
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let mut globals = None;
+        let captured = capture(|streams| {
+            let (run, result) = run_with_globals(args.clone(), streams);
+            globals = Some(run);
+            result
+        });
+
+        let args: Vec<_> = args.iter().map(|arg| arg.to_string_lossy()).collect();
+        let mut snapshot = format!("args: {}\n", args.join(" "));
+        for (title, text) in [("stdout", &captured.stdout), ("stderr", &captured.stderr)] {
+            snapshot.push_str(&format!("--- {} ---\n", title));
+            snapshot.push_str(text);
+            if !snapshot.ends_with('\n') {
+                snapshot.push('\n');
+            }
+        }
+        snapshot.push_str("--- result ---\n");
+        match captured.result {
+            // The error is reported as the application reports it, with the options of the run
+            Err(e) => {
+                let _entered = globals.map(globals::enter);
+                let report = ErrorFormat::selected().error(env!("CARGO_PKG_NAME"), &e);
+                snapshot.push_str(&format!("Err: {}\n", report));
+            }
+            result => snapshot.push_str(&format!("{}\n", Outcome::from(result))),
+        }
+
+        self.redactions
+            .iter()
+            .fold(snapshot, |snapshot, redaction| redaction(&snapshot))
+    }
+
+    /// Process the command line and compare the rendered snapshot against the snapshot file, or
+    /// update the snapshot file when in update mode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the snapshot differs from the snapshot file, if the snapshot file does not exist
+    /// or if the snapshot file cannot be read or written.
+    pub fn assert<I, T>(&self, args: I)
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let actual = self.render(args);
+        let path = self.directory.join(format!("{}.snap", self.name));
+
+        if env::var_os(UPDATE_VAR).is_some_and(|value| value != "0") {
+            fs::create_dir_all(&self.directory)
+                .and_then(|_| fs::write(&path, &actual))
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            return;
+        }
+
+        let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+            panic!(
+                "{}: {} (run with {}=1 to create it)",
+                path.display(),
+                e,
+                UPDATE_VAR
+            )
+        });
+        if actual != expected {
+            panic!(
+                "snapshot `{}` does not match {} (run with {}=1 to update it)\n\
+                 --- expected ---\n{}--- actual ---\n{}",
+                self.name,
+                path.display(),
+                UPDATE_VAR,
+                expected,
+                actual
+            );
+        }
+    }
+}
+
+/// A record of the subcommands and arguments of the command line interface that have been
+/// exercised by a set of command lines.
+///
+/// Each subcommand and each argument of the full command line definition is a coverage item,
+/// except for the automatically generated help and version options. An item is named by its
+/// subcommand path followed by `--long`, `-s` or `<NAME>` for arguments, for example
+/// `file_fail --better`. An item is exercised when a recorded command line selects the subcommand
+/// or explicitly sets the argument. An item whose effect a test cannot observe in-process, such as
+/// an option acting on the whole process, can be excluded with the reason, which the report lists.
+///
+/// # Examples
+/// ```
+/// use tyg_template::testkit::Coverage;
+///
+/// let mut coverage = Coverage::new();
+/// coverage.record(["tyg_template", "fail", "--bare"]);
+/// coverage.record(["tyg_template", "file_fail", "missing.txt"]);
+/// coverage.exclude("--max-runtime", "enforced on the whole process");
+///
+/// assert!(coverage.missing().contains(&"recursive_fail"));
+/// assert!(!coverage.missing().contains(&"fail --bare"));
+/// assert!(!coverage.missing().contains(&"--max-runtime"));
+/// assert!(coverage.percent() < 100.0);
+/// println!("{}", coverage);
+/// ```
+pub struct Coverage {
+    items: BTreeSet<String>,
+    exercised: BTreeSet<String>,
+    excluded: BTreeMap<String, String>,
+}
+
+impl Coverage {
+    /// Create an empty coverage record for the full command line interface.
+    pub fn new() -> Coverage {
+        let mut items = BTreeSet::new();
+        collect_items(&cli(), "", &mut items);
+        Coverage {
+            items,
+            exercised: BTreeSet::new(),
+            excluded: BTreeMap::new(),
+        }
+    }
+
+    /// Record the subcommands and arguments exercised by the command line. The first argument is
+    /// taken to be the name of the binary. Command lines that fail to parse exercise nothing.
+    pub fn record<I, T>(&mut self, args: I)
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        if let Ok(matches) = cli().try_get_matches_from(args) {
+            collect_exercised(&cli(), &matches, "", &mut self.exercised);
+        }
+    }
+
+    /// Leave the item out of the coverage, for the given reason. An item that is not part of the
+    /// command line interface is an error, so that an exclusion does not outlive its item.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such item.
+    pub fn exclude(&mut self, item: &str, reason: &str) {
+        if !self.items.remove(item) {
+            panic!("cannot exclude `{}` from the coverage: no such item", item);
+        }
+        self.excluded.insert(item.to_string(), reason.to_string());
+    }
+
+    /// The items that have not been exercised, in alphabetical order.
+    pub fn missing(&self) -> Vec<&str> {
+        self.items
+            .difference(&self.exercised)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// The percentage of the items that have been exercised.
+    pub fn percent(&self) -> f64 {
+        if self.items.is_empty() {
+            return 100.0;
+        }
+        let covered = self.items.intersection(&self.exercised).count();
+        100.0 * covered as f64 / self.items.len() as f64
+    }
+
+    /// Assert that at least the given percentage of the items have been exercised.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the coverage report if the coverage is below the threshold.
+    pub fn assert_at_least(&self, threshold: f64) {
+        if self.percent() < threshold {
+            panic!("command line coverage is below {:.1}%\n{}", threshold, self);
+        }
+    }
+}
+
+impl Default for Coverage {
+    fn default() -> Coverage {
+        Coverage::new()
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let missing = self.missing();
+        writeln!(
+            f,
+            "command line coverage: {:.1}% ({} of {} items)",
+            self.percent(),
+            self.items.len() - missing.len(),
+            self.items.len()
+        )?;
+        for item in missing {
+            writeln!(f, "    not exercised: {}", item)?;
+        }
+        for (item, reason) in &self.excluded {
+            writeln!(f, "    excluded: {} ({})", item, reason)?;
+        }
+        Ok(())
+    }
+}
+
+// Collect the coverage items of the command and its subcommands
+fn collect_items(command: &Command, path: &str, items: &mut BTreeSet<String>) {
+    for arg in command.get_arguments() {
+        if !arg.is_hide_set()
+            && !is_builtin(arg.get_id().as_str())
+            && (path.is_empty() || !arg.is_global_set())
+        {
+            items.insert(item_name(path, arg));
+        }
+    }
+    for sub in command.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        let sub_path = join_path(path, sub.get_name());
+        collect_items(sub, &sub_path, items);
+        items.insert(sub_path);
+    }
+}
+
+// Collect the coverage items exercised by the matches of the command and its subcommands
+fn collect_exercised(
+    command: &Command,
+    matches: &ArgMatches,
+    path: &str,
+    exercised: &mut BTreeSet<String>,
+) {
+    for arg in command.get_arguments() {
+        if matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+            let owner = if arg.is_global_set() { "" } else { path };
+            exercised.insert(item_name(owner, arg));
+        }
+    }
+    if let Some((name, sub_matches)) = matches.subcommand() {
+        if let Some(sub) = command.find_subcommand(name) {
+            let sub_path = join_path(path, name);
+            collect_exercised(sub, sub_matches, &sub_path, exercised);
+            exercised.insert(sub_path);
+        }
+    }
+}
+
+fn is_builtin(id: &str) -> bool {
+    id == "help" || id == "version"
+}
+
+fn join_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{} {}", path, name)
+    }
+}
+
+fn item_name(path: &str, arg: &clap::Arg) -> String {
+    let name = match (arg.get_long(), arg.get_short()) {
+        (Some(long), _) => format!("--{}", long),
+        (None, Some(short)) => format!("-{}", short),
+        (None, None) => format!("<{}>", arg.get_id()),
+    };
+    join_path(path, &name)
+}
+
+/// A command line given as an example in the help or in a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Example {
+    /// Where the example was found, e.g. `help of fail` or `README.md:36`.
+    pub source: String,
+    /// The arguments of the example, starting with the name of the binary.
+    pub args: Vec<String>,
+}
+
+impl fmt::Display for Example {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.source, self.args.join(" "))
+    }
+}
+
+/// The example command lines of the application, which can be checked against the command line
+/// definition so that examples that no longer parse fail the tests.
+///
+/// The examples are the lines of the EXAMPLES sections of the help of the application and its
+/// subcommands that start with the name of the application, and the `cargo run` command lines of
+/// any documents added with [`document`](Examples::document), whose arguments follow the `--`
+/// separator. The arguments are separated by whitespace; quotes are not interpreted. An example
+/// parses if clap accepts it, which includes requests for the help or the version.
+///
+/// # Examples
+/// ```
+/// use tyg_template::testkit::Examples;
+///
+/// let examples = Examples::new().document("NOTES.md", "$ cargo run -- fail --bare\n\
+///                                                       $ cargo run -- fail --no-such-flag\n");
+///
+/// assert!(examples.examples().iter().any(|example| example.source == "help"));
+/// assert_eq!(examples.failures().len(), 1);
+/// assert_eq!(examples.failures()[0].0.source, "NOTES.md:2");
+/// ```
+pub struct Examples {
+    examples: Vec<Example>,
+}
+
+impl Examples {
+    /// Collect the examples in the help of the full command line interface.
+    pub fn new() -> Examples {
+        let command = cli();
+        let mut examples = Vec::new();
+        collect_examples(&command, command.get_name(), "", &mut examples);
+        Examples { examples }
+    }
+
+    /// Add the `cargo run` command lines of a document, such as the README, naming each example
+    /// after the document and its line number.
+    pub fn document(mut self, name: &str, text: &str) -> Examples {
+        let bin = cli().get_name().to_string();
+        for (number, line) in text.lines().enumerate() {
+            let Some(command_line) = line.trim_start().strip_prefix("$ ") else {
+                continue;
+            };
+            let mut words = command_line.split_whitespace();
+            if !words.any(|word| word == "cargo") || words.next() != Some("run") {
+                continue;
+            }
+            let mut args = vec![bin.clone()];
+            args.extend(
+                words
+                    .skip_while(|word| *word != "--")
+                    .skip(1)
+                    .map(String::from),
+            );
+            self.examples.push(Example {
+                source: format!("{}:{}", name, number + 1),
+                args,
+            });
+        }
+        self
+    }
+
+    /// The examples, in the order in which they were found.
+    pub fn examples(&self) -> &[Example] {
+        &self.examples
+    }
+
+    /// The examples that fail to parse, each with the error reported by clap.
+    pub fn failures(&self) -> Vec<(&Example, String)> {
+        let command = cli();
+        self.examples
+            .iter()
+            .filter_map(|example| {
+                let error = command.clone().try_get_matches_from(&example.args).err()?;
+                match error.kind() {
+                    ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => None,
+                    _ => Some((example, error.to_string())),
+                }
+            })
+            .collect()
+    }
+
+    /// Assert that every example parses.
+    ///
+    /// # Panics
+    ///
+    /// Panics with each example that fails to parse and the error reported for it.
+    pub fn assert_parse(&self) {
+        let failures = self.failures();
+        if !failures.is_empty() {
+            let report: Vec<String> = failures
+                .iter()
+                .map(|(example, error)| format!("{}\n{}", example, error.trim_end()))
+                .collect();
+            panic!(
+                "{} of {} examples fail to parse\n\n{}",
+                failures.len(),
+                self.examples.len(),
+                report.join("\n\n")
+            );
+        }
+    }
+}
+
+impl Default for Examples {
+    fn default() -> Examples {
+        Examples::new()
+    }
+}
+
+// Collect the examples in the help of the command and its subcommands
+fn collect_examples(command: &Command, bin: &str, path: &str, examples: &mut Vec<Example>) {
+    if let Some(help) = command.get_after_help() {
+        let source = if path.is_empty() {
+            "help".to_string()
+        } else {
+            format!("help of {}", path)
+        };
+        for line in help.to_string().lines() {
+            let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+            if args.first().map(String::as_str) == Some(bin) {
+                examples.push(Example {
+                    source: source.clone(),
+                    args,
+                });
+            }
+        }
+    }
+    for sub in command.get_subcommands() {
+        collect_examples(sub, bin, &join_path(path, sub.get_name()), examples);
+    }
+}
+
+// Guards the working directory and environment, which are shared by every thread in the process:
+// a temporary project holds it exclusively while it exists and a captured run outside a project
+// shares it
+static PROJECT_LOCK: RwLock<()> = RwLock::new(());
+
+thread_local! {
+    // Whether the thread holds a temporary project, whose lock already covers its runs
+    static IN_PROJECT: Cell<bool> = const { Cell::new(false) };
+}
+
+// Share the working directory and environment unless the thread holds a temporary project
+fn share_environment() -> Option<RwLockReadGuard<'static, ()>> {
+    match IN_PROJECT.with(Cell::get) {
+        true => None,
+        false => Some(PROJECT_LOCK.read().unwrap_or_else(|e| e.into_inner())),
+    }
+}
+
+// Distinguishes the temporary projects created by this process
+static PROJECT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// The environment variables redirected into a temporary project, relative to its home directory
+const PROJECT_ENV: [(&str, &str); 5] = [
+    ("HOME", ""),
+    ("XDG_CONFIG_HOME", ".config"),
+    ("XDG_CACHE_HOME", ".cache"),
+    ("XDG_DATA_HOME", ".local/share"),
+    ("XDG_STATE_HOME", ".local/state"),
+];
+
+/// A temporary directory tree used as an isolated sandbox for a test.
+///
+/// While the project exists it is the current working directory, and the `HOME`,
+/// `XDG_CONFIG_HOME`, `XDG_CACHE_HOME`, `XDG_DATA_HOME` and `XDG_STATE_HOME` environment variables
+/// point inside its `home` directory. When the project is dropped, even as the result of a panic,
+/// the previous working directory and environment are restored and the tree is removed.
+///
+/// Only one project can exist at a time. Creating a project blocks until any project that
+/// exists in another thread has been dropped and any run [captured](capture) outside a project has
+/// returned.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use std::path::Path;
+/// use tyg_template::testkit::TempProject;
+///
+/// let project = TempProject::builder()
+///     .file("data/input.txt", "Hello")
+///     .dir("output")
+///     .create()
+///     .unwrap();
+/// let root = project.root().to_path_buf();
+///
+/// assert_eq!(fs::read_to_string("data/input.txt").unwrap(), "Hello");
+/// assert!(Path::new("output").is_dir());
+/// assert_eq!(std::env::var_os("HOME").unwrap(), project.home());
+///
+/// drop(project);
+///
+/// assert!(!root.exists());
+/// ```
+pub struct TempProject {
+    root: PathBuf,
+    previous_dir: Option<PathBuf>,
+    previous_env: Vec<(&'static str, Option<OsString>)>,
+    _lock: RwLockWriteGuard<'static, ()>,
+}
+
+impl TempProject {
+    /// Start the description of a temporary project.
+    pub fn builder() -> TempProjectBuilder {
+        TempProjectBuilder {
+            entries: Vec::new(),
+        }
+    }
+
+    /// The root directory of the project.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The home directory of the project.
+    pub fn home(&self) -> PathBuf {
+        self.root.join("home")
+    }
+
+    /// The absolute path of the given path relative to the root directory of the project.
+    pub fn path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.root.join(path)
+    }
+}
+
+impl Drop for TempProject {
+    fn drop(&mut self) {
+        IN_PROJECT.with(|in_project| in_project.set(false));
+        if let Some(ref dir) = self.previous_dir {
+            let _ = env::set_current_dir(dir);
+        }
+        for (name, value) in self.previous_env.drain(..) {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+// An entry in the description of a temporary project
+enum Entry {
+    File(PathBuf, Vec<u8>),
+    Dir(PathBuf),
+}
+
+/// The description of a [`TempProject`] to be created.
+pub struct TempProjectBuilder {
+    entries: Vec<Entry>,
+}
+
+impl TempProjectBuilder {
+    /// Add a file with the given contents. Any missing parent directories are also created.
+    pub fn file(
+        mut self,
+        path: impl AsRef<Path>,
+        contents: impl AsRef<[u8]>,
+    ) -> TempProjectBuilder {
+        self.entries.push(Entry::File(
+            path.as_ref().to_path_buf(),
+            contents.as_ref().to_vec(),
+        ));
+        self
+    }
+
+    /// Add an empty directory. Any missing parent directories are also created.
+    pub fn dir(mut self, path: impl AsRef<Path>) -> TempProjectBuilder {
+        self.entries.push(Entry::Dir(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Create the project and make it the current working directory and home directory.
+    ///
+    /// An error is returned if a path in the description is not relative to the project root or
+    /// if the tree cannot be created.
+    pub fn create(self) -> Result<TempProject> {
+        for entry in &self.entries {
+            let (Entry::File(path, _) | Entry::Dir(path)) = entry;
+            if !path
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                return result_err!(
+                    "{}: Path is not relative to the project root",
+                    path.display()
+                );
+            }
+        }
+
+        let lock = PROJECT_LOCK.write().unwrap_or_else(|e| e.into_inner());
+        IN_PROJECT.with(|in_project| in_project.set(true));
+        let root = env::temp_dir().join(format!(
+            "tyg_template-{}-{}",
+            process::id(),
+            PROJECT_COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        // From here on the project cleans up after itself if creation fails
+        let mut project = TempProject {
+            root,
+            previous_dir: None,
+            previous_env: Vec::new(),
+            _lock: lock,
+        };
+
+        if project.root.exists() {
+            fs::remove_dir_all(&project.root)?;
+        }
+        fs::create_dir_all(project.home())?;
+        for entry in self.entries {
+            match entry {
+                Entry::File(path, contents) => {
+                    let path = project.root.join(path);
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(path, contents)?;
+                }
+                Entry::Dir(path) => fs::create_dir_all(project.root.join(path))?,
+            }
+        }
+
+        project.previous_dir = Some(env::current_dir()?);
+        env::set_current_dir(&project.root)?;
+        for (name, relative) in PROJECT_ENV {
+            let dir = match relative {
+                "" => project.home(),
+                relative => project.home().join(relative),
+            };
+            fs::create_dir_all(&dir)?;
+            project.previous_env.push((name, env::var_os(name)));
+            env::set_var(name, dir);
+        }
+
+        Ok(project)
+    }
+}
+
+/// A [`Clock`] that stands still until the test advances it, to drive the behavior that depends
+/// on the time of day, such as the expiry of a cache entry, without waiting for it. The clock is
+/// read once it has been [entered](crate::clock::enter) on the thread of the test.
+///
+/// # Examples
+/// ```
+/// use std::sync::Arc;
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use tyg_template::{cache, clock};
+/// use tyg_template::testkit::{FakeClock, TempProject};
+///
+/// let _project = TempProject::builder().create().unwrap();
+/// let clock = Arc::new(FakeClock::new(UNIX_EPOCH + Duration::from_secs(1_704_110_400)));
+/// let _entered = clock::enter(clock.clone());
+/// let day = Duration::from_secs(24 * 60 * 60);
+///
+/// let _: u32 = cache::get_or_compute("answer", day, || Ok(42)).unwrap();
+/// clock.advance(day - Duration::from_secs(1));
+/// assert_eq!(cache::get::<u32>("answer", day), Some(42));
+///
+/// clock.advance(Duration::from_secs(1));
+/// assert_eq!(cache::get::<u32>("answer", day), None);
+/// ```
+#[derive(Debug)]
+pub struct FakeClock {
+    now: Mutex<SystemTime>,
+}
+
+impl FakeClock {
+    /// Create a clock showing the given time.
+    pub fn new(time: SystemTime) -> FakeClock {
+        FakeClock {
+            now: Mutex::new(time),
+        }
+    }
+
+    /// Move the clock forward by the duration.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += duration;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
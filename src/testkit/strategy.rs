@@ -0,0 +1,57 @@
+//! Proptest strategies for generating the values handled by the template.
+//!
+//! These strategies are available when the `testing` feature is enabled. The strategies for errors,
+//! along with the [`Arbitrary`] implementation for [`Error`](crate::Error), come from `tyg_errors::strategy`
+//! and are re-exported here. They deliberately favour awkward input such as control characters,
+//! newlines, colons that resemble error locations and paths that are not valid UTF-8.
+//!
+//! # Examples
+//! ```
+//! use proptest::prelude::*;
+//! use proptest::test_runner::TestRunner;
+//! use tyg_template::Error;
+//! use tyg_template::testkit::strategy;
+//!
+//! let mut runner = TestRunner::default();
+//!
+//! runner.run(&any::<Error>(), |error| {
+//!     prop_assert_eq!(format!("{}", error), format!("{:?}", error));
+//!     Ok(())
+//! }).unwrap();
+//!
+//! runner.run(&strategy::message(), |message| {
+//!     prop_assert_eq!(Error::Error(message.clone()).to_string(), message);
+//!     Ok(())
+//! }).unwrap();
+//! ```
+
+use std::ffi::OsString;
+
+use proptest::prelude::*;
+
+pub use tyg_errors::strategy::{cli_error, io_error, kind, message};
+
+/// A strategy generating relative file paths, which on Unix and Windows are frequently not valid
+/// UTF-8.
+#[cfg(unix)]
+pub fn os_path() -> impl Strategy<Value = OsString> {
+    use std::os::unix::ffi::OsStringExt;
+
+    prop::collection::vec(1u8.., 1..32).prop_map(OsString::from_vec)
+}
+
+/// A strategy generating relative file paths, which on Unix and Windows are frequently not valid
+/// UTF-8.
+#[cfg(windows)]
+pub fn os_path() -> impl Strategy<Value = OsString> {
+    use std::os::windows::ffi::OsStringExt;
+
+    prop::collection::vec(1u16.., 1..32).prop_map(|wide| OsString::from_wide(&wide))
+}
+
+/// A strategy generating relative file paths, which on Unix and Windows are frequently not valid
+/// UTF-8.
+#[cfg(not(any(unix, windows)))]
+pub fn os_path() -> impl Strategy<Value = OsString> {
+    "\\PC{1,32}".prop_map(OsString::from)
+}
@@ -0,0 +1,329 @@
+//! Values given on the command line in human units.
+//!
+//! Each type implements `FromStr`, so it can be used as the value of an argument with
+//! `value_parser!`, and `Display`, so that the value can be echoed back to the user in the same
+//! form. Parsing errors are reported without a location, as they are the user's to correct.
+//!
+//! ```
+//! use clap::{arg, value_parser, Command};
+//! use tyg_template::units::{ByteSize, HumanDuration};
+//!
+//! let command = Command::new("example")
+//!     .arg(arg!(--"max-size" <SIZE> "The largest file to keep").value_parser(value_parser!(ByteSize)))
+//!     .arg(arg!(--timeout <DURATION> "How long to wait").value_parser(value_parser!(HumanDuration)));
+//!
+//! let matches = command.clone().try_get_matches_from(["example", "--max-size", "1.5GiB"]).unwrap();
+//! assert_eq!(matches.get_one::<ByteSize>("max-size").unwrap().bytes(), 1_610_612_736);
+//!
+//! let error = command.clone().try_get_matches_from(["example", "--max-size", "10M"]).unwrap_err();
+//! assert!(error.to_string().contains("Ambiguous unit"));
+//!
+//! let matches = command.try_get_matches_from(["example", "--timeout", "1m30s"]).unwrap();
+//! assert_eq!(matches.get_one::<HumanDuration>("timeout").unwrap().duration().as_secs(), 90);
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::{result_err_bare, Error, Result};
+
+// The units of a byte size, from the largest, with the number of bytes in each
+const BYTE_UNITS: [(&str, u64); 11] = [
+    ("PiB", 1 << 50),
+    ("PB", 1_000_000_000_000_000),
+    ("TiB", 1 << 40),
+    ("TB", 1_000_000_000_000),
+    ("GiB", 1 << 30),
+    ("GB", 1_000_000_000),
+    ("MiB", 1 << 20),
+    ("MB", 1_000_000),
+    ("KiB", 1 << 10),
+    ("kB", 1_000),
+    ("B", 1),
+];
+
+// The forms accepted for a byte size, shown when one cannot be parsed
+const BYTE_FORMS: &str =
+    "a number of bytes optionally followed by B, a decimal unit (kB, MB, GB, TB, PB) \
+     or a binary unit (KiB, MiB, GiB, TiB, PiB), e.g. 512, 10MB or 1.5GiB";
+
+/// A number of bytes, given with a decimal or binary unit such as `10MB` or `1.5GiB`.
+///
+/// The number may have a fraction as long as the size is a whole number of bytes, and may be
+/// separated from the unit by spaces. `KB` is accepted for `kB`. A unit of a single letter such
+/// as `M`, or a lower case unit such as `mb`, is rejected as ambiguous, since it may mean either
+/// the decimal or the binary unit, or bits rather than bytes.
+///
+/// The size is displayed with the largest unit that divides it exactly.
+///
+/// # Examples
+/// ```
+/// use tyg_template::units::ByteSize;
+///
+/// let size: ByteSize = "1.5 GiB".parse().unwrap();
+///
+/// assert_eq!(size.bytes(), 1_610_612_736);
+/// assert_eq!(size.to_string(), "1536 MiB");
+/// assert_eq!("10MB".parse::<ByteSize>().unwrap().to_string(), "10 MB");
+/// assert_eq!("512".parse::<ByteSize>().unwrap(), ByteSize::new(512));
+/// assert!("1.5B".parse::<ByteSize>().is_err());
+/// assert!("10mb".parse::<ByteSize>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    /// A size of the given number of bytes.
+    pub const fn new(bytes: u64) -> ByteSize {
+        ByteSize(bytes)
+    }
+
+    /// The number of bytes.
+    pub const fn bytes(self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<ByteSize> {
+        let text = text.trim();
+        let split = text
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(text.len());
+        let (number, unit) = (&text[..split], text[split..].trim_start());
+
+        if number.is_empty() || number == "." || number.matches('.').count() > 1 {
+            return result_err_bare!("{}: Invalid size, give {}", text, BYTE_FORMS);
+        }
+
+        let unit = if unit == "KB" { "kB" } else { unit };
+        let multiplier = match BYTE_UNITS.iter().find(|(name, _)| *name == unit) {
+            Some((_, multiplier)) => *multiplier,
+            None if unit.is_empty() => 1,
+            None => {
+                // A single letter or a unit in the wrong case names a decimal and a binary unit
+                let prefix = unit.chars().next().unwrap_or_default().to_ascii_uppercase();
+                let ambiguous = (unit.len() == 1 && "KMGTP".contains(prefix))
+                    || BYTE_UNITS
+                        .iter()
+                        .any(|(name, _)| name.eq_ignore_ascii_case(unit));
+                return match BYTE_UNITS.iter().find(|(name, _)| name.starts_with(prefix)) {
+                    Some((binary, _)) if ambiguous && prefix != 'B' => result_err_bare!(
+                        "{}: Ambiguous unit '{}', use {}B for the decimal unit or {} for the binary unit",
+                        text,
+                        unit,
+                        if prefix == 'K' { 'k' } else { prefix },
+                        binary
+                    ),
+                    _ => result_err_bare!("{}: Unknown unit '{}', give {}", text, unit, BYTE_FORMS),
+                };
+            }
+        };
+
+        match scale(number, u128::from(multiplier)) {
+            Scaled::Whole(bytes) if bytes <= u128::from(u64::MAX) => Ok(ByteSize(bytes as u64)),
+            Scaled::Fraction => {
+                result_err_bare!("{}: The size is not a whole number of bytes", text)
+            }
+            _ => result_err_bare!("{}: The size is too large", text),
+        }
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, multiplier) = BYTE_UNITS
+            .iter()
+            .find(|(_, multiplier)| self.0.is_multiple_of(*multiplier) && self.0 >= *multiplier)
+            .unwrap_or(&("B", 1));
+        write!(f, "{} {}", self.0 / multiplier, name)
+    }
+}
+
+// The units of a duration, from the largest, with the number of nanoseconds in each
+const DURATION_UNITS: [(&str, u64); 8] = [
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ns", 1),
+];
+
+// The units accepted for a duration, shown when one cannot be parsed
+const DURATION_FORMS: &str = "use d, h, m, s, ms, us or ns, e.g. 5m30s or 250ms";
+
+/// A duration given as a number and unit, or several of them, such as `250ms`, `1.5h` or
+/// `1h 30m`.
+///
+/// The units are `d`, `h`, `m`, `s`, `ms`, `us` (or `µs`) and `ns`. The parts may be separated
+/// by spaces, and each number may have a fraction as long as the duration is a whole number of
+/// nanoseconds. An error names the position, counting characters from 1, of the part that cannot
+/// be parsed.
+///
+/// The duration is displayed in the compound form, e.g. `1h30m`.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use tyg_template::units::HumanDuration;
+/// use tyg_template::{assert_err_msg, Error};
+///
+/// let duration: HumanDuration = "5m30s".parse().unwrap();
+///
+/// assert_eq!(duration.duration(), Duration::from_secs(330));
+/// assert_eq!("1.5h".parse::<HumanDuration>().unwrap().to_string(), "1h30m");
+/// assert_eq!("250ms".parse::<HumanDuration>().unwrap().to_string(), "250ms");
+///
+/// assert_err_msg!("1hr".parse::<HumanDuration>(), Error::Error(_),
+///                 "1hr: Unknown unit 'hr' at position 2, use d, h, m, s, ms, us or ns, e.g. 5m30s or 250ms");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct HumanDuration(Duration);
+
+impl HumanDuration {
+    /// A human duration of the given duration.
+    pub const fn new(duration: Duration) -> HumanDuration {
+        HumanDuration(duration)
+    }
+
+    /// The duration.
+    pub const fn duration(self) -> Duration {
+        self.0
+    }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(duration: HumanDuration) -> Duration {
+        duration.0
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<HumanDuration> {
+        let text = text.trim();
+        if text.is_empty() {
+            return result_err_bare!("Empty duration, {}", DURATION_FORMS);
+        }
+        // The position of a byte offset in characters, counting from 1
+        let position = |offset: usize| text[..offset].chars().count() + 1;
+
+        let mut nanos: u128 = 0;
+        let mut rest = text;
+        while !rest.is_empty() {
+            let start = text.len() - rest.len();
+            let number_end = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let number = &rest[..number_end];
+            if number.is_empty() || number == "." || number.matches('.').count() > 1 {
+                return result_err_bare!(
+                    "{}: Expected a number at position {}",
+                    text,
+                    position(start)
+                );
+            }
+            let unit_end = rest[number_end..]
+                .find(|c: char| !c.is_alphabetic())
+                .map_or(rest.len(), |end| number_end + end);
+            let unit = &rest[number_end..unit_end];
+            if unit.is_empty() {
+                return result_err_bare!(
+                    "{}: Missing unit after '{}' at position {}, {}",
+                    text,
+                    number,
+                    position(start),
+                    DURATION_FORMS
+                );
+            }
+            let Some((_, multiplier)) = DURATION_UNITS.iter().find(|(name, _)| *name == unit)
+            else {
+                return result_err_bare!(
+                    "{}: Unknown unit '{}' at position {}, {}",
+                    text,
+                    unit,
+                    position(start + number_end),
+                    DURATION_FORMS
+                );
+            };
+            match scale(number, u128::from(*multiplier)) {
+                Scaled::Whole(part) => nanos = nanos.saturating_add(part),
+                Scaled::Fraction => {
+                    return result_err_bare!(
+                        "{}: '{}{}' at position {} is not a whole number of nanoseconds",
+                        text,
+                        number,
+                        unit,
+                        position(start)
+                    )
+                }
+                Scaled::Overflow => nanos = u128::MAX,
+            }
+            rest = rest[unit_end..].trim_start();
+        }
+
+        let seconds = nanos / 1_000_000_000;
+        match u64::try_from(seconds) {
+            Ok(seconds) => Ok(HumanDuration(Duration::new(
+                seconds,
+                (nanos % 1_000_000_000) as u32,
+            ))),
+            Err(_) => result_err_bare!("{}: The duration is too long", text),
+        }
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut nanos = self.0.as_nanos();
+        if nanos == 0 {
+            return write!(f, "0s");
+        }
+        for (name, multiplier) in DURATION_UNITS.iter().filter(|(name, _)| *name != "µs") {
+            let multiplier = u128::from(*multiplier);
+            if nanos >= multiplier {
+                write!(f, "{}{}", nanos / multiplier, name)?;
+                nanos %= multiplier;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The result of multiplying a decimal number by the number of base units in its unit
+enum Scaled {
+    Whole(u128),
+    Fraction,
+    Overflow,
+}
+
+// Multiply a decimal number, made of digits and at most one decimal point, by the multiplier
+// exactly, as a float would lose precision. No unit is large enough to make a whole number from
+// more than 18 decimal places.
+fn scale(number: &str, multiplier: u128) -> Scaled {
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > 18 {
+        return Scaled::Fraction;
+    }
+    let scaled = format!("0{}{}", whole, fraction)
+        .parse::<u128>()
+        .ok()
+        .and_then(|digits| digits.checked_mul(multiplier));
+    let Some(scaled) = scaled else {
+        return Scaled::Overflow;
+    };
+    let divisor = 10u128.pow(fraction.len() as u32);
+    if scaled.is_multiple_of(divisor) {
+        Scaled::Whole(scaled / divisor)
+    } else {
+        Scaled::Fraction
+    }
+}
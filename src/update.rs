@@ -0,0 +1,211 @@
+//! An opt-in check for a newer release of the application.
+//!
+//! The check is turned on by setting the `TYG_UPDATE_CHECK` environment variable to anything other
+//! than `0`, and needs the `http` feature. The latest version is kept in the
+//! [cache](crate::cache) for a day. When the cached answer is missing or older, the application
+//! starts a copy of itself in the background with the hidden `__update-check` subcommand, which
+//! asks the release endpoint and caches the answer, and carries on without waiting for it. The
+//! command is therefore never delayed or failed by the check. When the command succeeds and the
+//! cached answer is newer than the running version, a single notice is written to the error
+//! stream,
+//!
+//! ```text
+//! tyg_template: A newer version (1.4.0) is available, you have 0.1.2
+//! ```
+//!
+//! and otherwise nothing is written. The endpoint is [`RELEASE_URL`], which answers in the form
+//! of the GitHub releases API, unless the `TYG_UPDATE_URL` environment variable names another. Its
+//! answer is a JSON object with the version in `tag_name` or `version`, optionally after a `v`.
+//! The background run is neither recorded in the audit log nor counted in the metrics.
+
+use std::env;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+
+use clap::builder::styling::{AnsiColor, Style};
+
+use crate::{cache, terminal};
+
+/// The environment variable that turns the check on when it is set, unless it is `0`.
+pub const ENV_VAR: &str = "TYG_UPDATE_CHECK";
+
+/// The environment variable naming a release endpoint to use instead of [`RELEASE_URL`].
+pub const URL_VAR: &str = "TYG_UPDATE_URL";
+
+/// The endpoint that gives the latest release. Change it to that of your own application.
+pub const RELEASE_URL: &str =
+    "https://api.github.com/repos/converse99/tyg_template/releases/latest";
+
+// The key of the latest version in the cache, and how long it is kept
+const CACHE_KEY: &str = "update-check";
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Whether the check is turned on by the environment variable.
+pub fn enabled() -> bool {
+    env::var_os(ENV_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// The check for a newer release made by a run of the application.
+#[derive(Debug, Default)]
+pub struct Check {
+    latest: Option<String>,
+}
+
+impl Check {
+    /// Read the latest version from the cache, if the check is turned on and the application was
+    /// built with the `http` feature, refreshing it in the background when it is missing or a day
+    /// old. The hidden subcommands, which are run by the application itself or by the completion
+    /// scripts, make no check.
+    pub fn start(subcommand: Option<&str>) -> Check {
+        if !cfg!(feature = "http")
+            || !enabled()
+            || subcommand.is_some_and(|name| name.starts_with("__"))
+        {
+            return Check::default();
+        }
+        match cache::get::<Option<String>>(CACHE_KEY, CACHE_TTL) {
+            Some(latest) => Check { latest },
+            None => {
+                spawn_refresh();
+                Check::default()
+            }
+        }
+    }
+
+    /// Write the notice if the latest version is newer than the running version.
+    pub fn finish(self, err: &mut dyn Write) {
+        if let Some(latest) = self.latest {
+            if newer(&latest, env!("CARGO_PKG_VERSION")) {
+                let _ = writeln!(
+                    err,
+                    "{}",
+                    notice(&latest, terminal::styled(std::io::stderr().is_terminal()))
+                );
+            }
+        }
+    }
+}
+
+/// The `__update-check` subcommand, which asks the release endpoint for the latest version and
+/// caches the answer. A failure to ask is cached too, as no version, so that an unreachable
+/// endpoint is asked at most once a day.
+pub fn refresh() -> crate::Result<()> {
+    cache::get_or_compute(CACHE_KEY, Duration::ZERO, || Ok(latest()))?;
+    Ok(())
+}
+
+/// Whether the version is newer than the current version. Versions are compared by their
+/// numbers, and a version with a pre-release, such as `2.0.0-beta.1`, is never newer, so that
+/// users are only told of stable releases.
+///
+/// # Examples
+/// ```
+/// use tyg_template::update::newer;
+///
+/// assert!(newer("1.4.0", "1.3.9"));
+/// assert!(newer("v1.10.0", "1.9.0"));
+/// assert!(!newer("1.3.0", "1.3.0"));
+/// assert!(!newer("2.0.0-beta.1", "1.3.0"));
+/// assert!(!newer("not a version", "1.3.0"));
+/// ```
+pub fn newer(version: &str, current: &str) -> bool {
+    match (numbers(version), numbers(current)) {
+        (Some(version), Some(current)) => version > current,
+        _ => false,
+    }
+}
+
+/// The notice of a newer release, in bold when styled. It is prefixed like the error messages,
+/// with [`ERROR_PREFIX`](crate::ERROR_PREFIX).
+///
+/// # Examples
+/// ```
+/// use tyg_template::update::notice;
+///
+/// assert_eq!(notice("1.4.0", false),
+///            format!("{}: A newer version (1.4.0) is available, you have {}",
+///                    tyg_template::ERROR_PREFIX, env!("CARGO_PKG_VERSION")));
+/// ```
+pub fn notice(latest: &str, styled: bool) -> String {
+    let style = match styled {
+        true => Style::new().bold().fg_color(Some(AnsiColor::Yellow.into())),
+        false => Style::new(),
+    };
+    format!(
+        "{}: {style}A newer version ({}) is available, you have {}{style:#}",
+        crate::ERROR_PREFIX,
+        latest.trim_start_matches('v'),
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// The latest version in the answer of a release endpoint, if it has one.
+///
+/// # Examples
+/// ```
+/// use tyg_template::update::parse_release;
+///
+/// assert_eq!(parse_release(r#"{"tag_name":"v1.4.0","name":"Release 1.4.0"}"#).unwrap(), "1.4.0");
+/// assert_eq!(parse_release(r#"{"version":"1.4.0"}"#).unwrap(), "1.4.0");
+/// assert!(parse_release("<html>").is_none());
+/// ```
+pub fn parse_release(text: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    let version = value["tag_name"].as_str().or(value["version"].as_str())?;
+    Some(version.trim_start_matches('v').to_string())
+}
+
+// The major, minor and patch numbers of a version without a pre-release
+fn numbers(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split('+').next()?;
+    let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+    let numbers = (
+        parts.next()??,
+        parts.next()??,
+        parts.next().unwrap_or(Some(0))?,
+    );
+    parts.next().is_none().then_some(numbers)
+}
+
+// Start a copy of the application that refreshes the cached answer, without waiting for it
+fn spawn_refresh() {
+    use std::process::{Command, Stdio};
+
+    if let Ok(exe) = env::current_exe() {
+        let _ = Command::new(exe)
+            .arg("__update-check")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+    }
+}
+
+#[cfg(feature = "http")]
+fn latest() -> Option<String> {
+    let url = env::var(URL_VAR).unwrap_or_else(|_| RELEASE_URL.to_string());
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(5)))
+        .user_agent(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .build()
+        .into();
+    let text = agent
+        .get(&url)
+        .header("Accept", "application/json")
+        .call()
+        .ok()?
+        .body_mut()
+        .read_to_string()
+        .ok()?;
+    parse_release(&text)
+}
+
+#[cfg(not(feature = "http"))]
+fn latest() -> Option<String> {
+    None
+}
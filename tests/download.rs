@@ -0,0 +1,156 @@
+// Checks downloads against a local server, including resumed downloads and failed checksums.
+#![cfg(feature = "http")]
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use tyg_template::download::{download, partial_path, Progress, Sha256};
+use tyg_template::fetch::HttpUrl;
+use tyg_template::testkit::TempProject;
+use tyg_template::{assert_err, Error};
+
+const BODY: &[u8] = b"The quick brown fox jumps over the lazy dog\n";
+
+// Serve one request for /tool with the body, honouring a Range header if ranges are supported,
+// and answer /missing with 404. The Range header of the request, if any, is sent back.
+fn serve(ranges: bool) -> (HttpUrl, Receiver<Option<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut range = None;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("range") {
+                    range = Some(value.trim().to_string());
+                }
+            }
+        }
+        let start: Option<usize> = range
+            .as_deref()
+            .and_then(|range| range.strip_prefix("bytes="))
+            .and_then(|range| range.trim_end_matches('-').parse().ok())
+            .filter(|_| ranges);
+        let (status, body, extra) = match (request_line.contains("/missing"), start) {
+            (true, _) => ("404 Not Found", &b""[..], String::new()),
+            (false, Some(start)) => (
+                "206 Partial Content",
+                &BODY[start..],
+                format!(
+                    "Content-Range: bytes {}-{}/{}\r\n",
+                    start,
+                    BODY.len() - 1,
+                    BODY.len()
+                ),
+            ),
+            (false, None) => ("200 OK", BODY, String::new()),
+        };
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n",
+            status,
+            body.len(),
+            extra
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+        sender.send(range).unwrap();
+    });
+    let url = format!("http://{}/tool", address).parse().unwrap();
+    (url, receiver)
+}
+
+#[test]
+fn downloads_and_verifies() {
+    let _project = TempProject::builder().create().unwrap();
+    let (url, requests) = serve(true);
+    let mut reports = Vec::new();
+
+    let size = download(&url, "tool", Some(&Sha256::digest(BODY)), &mut |progress| {
+        reports.push(progress)
+    })
+    .unwrap();
+
+    assert_eq!(size, BODY.len() as u64);
+    assert_eq!(fs::read("tool").unwrap(), BODY);
+    assert!(!partial_path("tool").exists());
+    assert_eq!(requests.recv().unwrap(), None);
+    let last = reports.last().unwrap();
+    assert_eq!(
+        *last,
+        Progress {
+            done: size,
+            total: Some(size),
+            finished: true
+        }
+    );
+}
+
+#[test]
+fn resumes_partial_download() {
+    let _project = TempProject::builder()
+        .file("tool.part", &BODY[..10])
+        .create()
+        .unwrap();
+    let (url, requests) = serve(true);
+    let mut first = None;
+
+    download(&url, "tool", Some(&Sha256::digest(BODY)), &mut |progress| {
+        first.get_or_insert(progress);
+    })
+    .unwrap();
+
+    assert_eq!(fs::read("tool").unwrap(), BODY);
+    assert_eq!(requests.recv().unwrap().as_deref(), Some("bytes=10-"));
+    assert_eq!(first.unwrap().done, 10);
+}
+
+#[test]
+fn restarts_when_ranges_are_not_supported() {
+    let _project = TempProject::builder()
+        .file("tool.part", "garbage")
+        .create()
+        .unwrap();
+    let (url, _requests) = serve(false);
+
+    download(&url, "tool", Some(&Sha256::digest(BODY)), &mut |_| {}).unwrap();
+
+    assert_eq!(fs::read("tool").unwrap(), BODY);
+}
+
+#[test]
+fn removes_file_with_wrong_checksum() {
+    let _project = TempProject::builder().create().unwrap();
+    let (url, _requests) = serve(true);
+
+    let result = download(&url, "tool", Some(&Sha256::digest(b"")), &mut |_| {});
+
+    assert_err!(result, Error::Verification(_));
+    assert!(!fs::exists("tool").unwrap());
+    assert!(!partial_path("tool").exists());
+}
+
+#[test]
+fn reports_missing_file() {
+    let _project = TempProject::builder().create().unwrap();
+    let (url, _requests) = serve(true);
+    let url: HttpUrl = url.as_str().replace("/tool", "/missing").parse().unwrap();
+
+    let result = download(&url, "tool", None, &mut |_| {});
+
+    assert_err!(result, Error::Http(_));
+    assert!(!fs::exists("tool").unwrap());
+}
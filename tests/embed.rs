@@ -0,0 +1,54 @@
+// Checks the events of a run embedded in a host application while its work is in progress.
+#![cfg(unix)]
+
+use std::fs::OpenOptions;
+use std::process::Command;
+use std::time::Duration;
+
+use tyg_template::embed::{self, Event};
+use tyg_template::testkit::TempProject;
+
+#[test]
+fn progress_is_sent_while_a_script_runs() {
+    // The second command of the script waits for the gate, a named pipe, until it is opened for
+    // writing
+    let _project = TempProject::builder()
+        .file("ops.tyg", "check ops.tyg\nfile_fail gate\n")
+        .create()
+        .unwrap();
+    assert!(Command::new("mkfifo")
+        .arg("gate")
+        .status()
+        .unwrap()
+        .success());
+
+    let events = embed::spawn(["tyg_template", "run-script", "ops.tyg"]).unwrap();
+    let mut output = Vec::new();
+    let states = loop {
+        match events.recv_timeout(Duration::from_secs(10)).unwrap() {
+            Event::Output(line) => output.push(line),
+            // The first command may still be in progress at the first poll
+            Event::Progress(states) if states[0].done == 0 => {}
+            Event::Progress(states) => break states,
+            event => panic!("Unexpected event {:?}", event),
+        }
+    };
+    assert_eq!(output, ["0 of 1 files failed the check"]);
+    assert_eq!(states.len(), 1);
+    assert_eq!(
+        states[0].to_string(),
+        "run-script ops.tyg: 1 of 2 (50%), at ops.tyg:2, 0s elapsed"
+    );
+
+    drop(OpenOptions::new().write(true).open("gate").unwrap());
+    let rest: Vec<Event> = events.iter().collect();
+    let last_progress = rest.iter().rev().find_map(|event| match event {
+        Event::Progress(states) => Some(states),
+        _ => None,
+    });
+    assert!(last_progress.unwrap().is_empty());
+    assert!(matches!(
+        rest.last(),
+        Some(Event::Finished { exit_code: 0, .. })
+    ));
+}
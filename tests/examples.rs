@@ -0,0 +1,24 @@
+// Checks that the example command lines in the help and the README still parse, so that they are
+// updated along with the command line definition.
+
+use std::fs;
+use std::path::Path;
+
+use tyg_template::testkit::Examples;
+
+#[test]
+fn examples_parse() {
+    let readme =
+        fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("README.md")).unwrap();
+    let examples = Examples::new().document("README.md", &readme);
+
+    assert!(examples
+        .examples()
+        .iter()
+        .any(|example| example.source == "help"));
+    assert!(examples
+        .examples()
+        .iter()
+        .any(|example| example.source.starts_with("README.md:")));
+    examples.assert_parse();
+}
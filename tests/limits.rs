@@ -0,0 +1,64 @@
+// Checks that a run which exceeds its runtime or memory limit is stopped with a clean error.
+#![cfg(unix)]
+
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use tyg_template::testkit::TempProject;
+
+#[test]
+fn max_runtime_stops_a_blocked_run() {
+    // The command of the script waits for the gate, a named pipe, which is never opened for writing
+    let _project = TempProject::builder()
+        .file("ops.tyg", "# Waits for the gate\nfile_fail gate\n")
+        .create()
+        .unwrap();
+    assert!(Command::new("mkfifo")
+        .arg("gate")
+        .status()
+        .unwrap()
+        .success());
+
+    let started = Instant::now();
+    let output = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["--max-runtime", "500ms", "run-script", "ops.tyg"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(75));
+    assert!(
+        stderr.ends_with("Exceeded the runtime limit of 500ms given by --max-runtime\n"),
+        "{}",
+        stderr
+    );
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn max_memory_stops_a_blocked_run() {
+    // The process uses more than the limit from the start, so the first poll exceeds it
+    let _project = TempProject::builder()
+        .file("ops.tyg", "# Waits for the gate\nfile_fail gate\n")
+        .create()
+        .unwrap();
+    assert!(Command::new("mkfifo")
+        .arg("gate")
+        .status()
+        .unwrap()
+        .success());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["--max-memory", "1MiB", "run-script", "ops.tyg"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(75));
+    assert!(
+        stderr.contains("Exceeded the memory limit of 1 MiB given by --max-memory, using "),
+        "{}",
+        stderr
+    );
+}
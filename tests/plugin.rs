@@ -0,0 +1,159 @@
+// Checks that the subcommands and error formats of a plugin are merged with those of the
+// application.
+
+use std::io::Write;
+use std::sync::Once;
+
+use clap::{arg, ArgMatches, Command};
+use tyg_template::outcome::Outcome;
+use tyg_template::plugin::{self, ErrorRenderer, Plugin};
+use tyg_template::testkit::{capture, strip_locations, TempProject};
+use tyg_template::{Error, ErrorFormat, Result, Streams};
+
+struct Greeter;
+
+impl Plugin for Greeter {
+    fn name(&self) -> &'static str {
+        "greeter"
+    }
+
+    fn commands(&self) -> Vec<Command> {
+        vec![
+            Command::new("greet")
+                .about("Greet someone")
+                .alias("hello")
+                .arg(arg!(<NAME> "Who to greet")),
+            Command::new("shout")
+                .about("Greet someone loudly")
+                .arg(arg!(<NAME> "Who to greet")),
+        ]
+    }
+
+    fn run(
+        &self,
+        subcommand: &str,
+        matches: &ArgMatches,
+        streams: &mut Streams,
+    ) -> Result<Outcome> {
+        let name = matches.get_one::<String>("NAME").map_or("", String::as_str);
+        match subcommand {
+            "shout" => writeln!(streams.out, "HELLO {}!", name.to_uppercase())?,
+            _ => writeln!(streams.out, "Hello {}", name)?,
+        }
+        Ok(Outcome::Success)
+    }
+
+    fn error_formats(&self) -> Vec<ErrorRenderer> {
+        vec![ErrorRenderer {
+            name: "brief",
+            render: |level, _prefix, message| format!("{}! {}", level, message),
+        }]
+    }
+}
+
+// A plugin with a subcommand of the given name
+struct Clashing(&'static str);
+
+impl Plugin for Clashing {
+    fn name(&self) -> &'static str {
+        "clashing"
+    }
+
+    fn commands(&self) -> Vec<Command> {
+        vec![Command::new(self.0)]
+    }
+
+    fn run(
+        &self,
+        _subcommand: &str,
+        _matches: &ArgMatches,
+        _streams: &mut Streams,
+    ) -> Result<Outcome> {
+        Ok(Outcome::Success)
+    }
+}
+
+fn register_greeter() {
+    static REGISTERED: Once = Once::new();
+    REGISTERED.call_once(|| plugin::register(Greeter).unwrap());
+}
+
+fn run(args: &[&str]) -> (Result<Outcome>, String) {
+    let captured = capture(|streams| tyg_template::run_with_args(args, streams));
+    (captured.result, captured.stdout)
+}
+
+#[test]
+fn plugin_subcommands_are_dispatched() {
+    register_greeter();
+
+    let (answer, out) = run(&["tyg_template", "greet", "world"]);
+    assert!(matches!(answer, Ok(Outcome::Success)));
+    assert_eq!(out, "Hello world\n");
+
+    let (_, out) = run(&["tyg_template", "hello", "world"]);
+    assert_eq!(out, "Hello world\n");
+
+    let (_, out) = run(&["tyg_template", "shout", "world"]);
+    assert_eq!(out, "HELLO WORLD!\n");
+
+    let (_, out) = run(&["tyg_template", "--help"]);
+    assert!(out.contains("greet"), "{}", out);
+}
+
+#[test]
+fn plugin_subcommands_run_in_scripts() {
+    register_greeter();
+    let _project = TempProject::builder()
+        .file("ops.tyg", "greet world\nshout world\n")
+        .create()
+        .unwrap();
+
+    let (answer, out) = run(&["tyg_template", "run-script", "ops.tyg"]);
+    assert!(answer.is_ok());
+    assert_eq!(out, "Hello world\nHELLO WORLD!\n");
+}
+
+#[test]
+fn plugin_error_formats_render_reports() {
+    register_greeter();
+
+    let (answer, _) = run(&["tyg_template", "--error-format", "brief", "greet", "world"]);
+    assert!(answer.is_ok());
+
+    let format = ErrorFormat::from_name("brief");
+    assert_eq!(format, ErrorFormat::Plugin("brief"));
+    assert_eq!(
+        format.error("my_tool", &Error::Error("Something failed".to_string())),
+        "error! Something failed"
+    );
+    assert_eq!(
+        format.warning("my_tool", "Deprecated"),
+        "warning! Deprecated"
+    );
+    assert!(ErrorFormat::names().contains(&"brief"));
+}
+
+#[test]
+fn conflicting_plugins_are_refused() {
+    register_greeter();
+
+    for (name, provider) in [
+        ("check", "the application"),
+        ("help", "the application"),
+        ("greet", "the greeter plugin"),
+        ("hello", "the greeter plugin"),
+    ] {
+        let error = plugin::register(Clashing(name)).unwrap_err();
+        assert_eq!(
+            strip_locations(&error.to_string()),
+            format!(
+                "Plugin clashing: The subcommand {} is already provided by {}",
+                name, provider
+            )
+        );
+    }
+    assert!(plugin::plugins()
+        .iter()
+        .all(|plugin| plugin.name() != "clashing"));
+}
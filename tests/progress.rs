@@ -0,0 +1,46 @@
+// Checks the progress events written as JSON lines with --progress-format json.
+
+use std::process::Command;
+
+use serde_json::{json, Value};
+use tyg_template::testkit::{strip_locations, TempProject};
+
+#[test]
+fn json_progress_events_of_a_failing_script() {
+    let _project = TempProject::builder()
+        .file("full.txt", "data")
+        .file("ops.tyg", "check full.txt\ncheck missing.txt\n")
+        .create()
+        .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["--progress-format", "json", "run-script", "ops.tyg"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // The events are followed by the error report
+    let events: Vec<Value> = stderr
+        .lines()
+        .map_while(|line| serde_json::from_str::<Value>(line).ok())
+        .map(|mut event| {
+            // The locations disclosed with the disclose feature are left out of the error
+            if let Some(error) = event["error"].as_str() {
+                event["error"] = strip_locations(error).into();
+            }
+            event
+        })
+        .collect();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        events,
+        [
+            json!({ "event": "task_started", "task": 0, "name": "run-script ops.tyg", "total": 2 }),
+            json!({ "event": "item_completed", "task": 0, "item": "ops.tyg:1", "done": 1 }),
+            json!({ "event": "progress", "task": 0, "done": 1, "total": 2, "percent": 50 }),
+            json!({ "event": "item_completed", "task": 0, "item": "ops.tyg:2", "done": 2 }),
+            json!({ "event": "progress", "task": 0, "done": 2, "total": 2, "percent": 100 }),
+            json!({ "event": "task_failed", "task": 0, "error": "ops.tyg:2: 1 problems found" }),
+        ]
+    );
+}
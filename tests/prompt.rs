@@ -0,0 +1,70 @@
+// Checks that a confirmation prompt is refused when no one can answer it, unless --yes is given, on
+// the command line or on the line of a script.
+
+use std::fs;
+use std::process::{Command, Stdio};
+
+use tyg_template::testkit::{strip_locations, TempProject};
+
+const MANIFEST: &str = "[package]\nname = \"demo_app\"\nversion = \"0.1.0\"\n";
+
+#[test]
+fn prompts_are_refused_without_a_terminal() {
+    let _project = TempProject::builder()
+        .file("Cargo.toml", MANIFEST)
+        .create()
+        .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["init", "--name", "renamed_app", "--path", "."])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        strip_locations(&String::from_utf8(output.stderr).unwrap()),
+        "tyg_template: Rename the project in . to renamed_app? Cannot ask for confirmation as the \
+         input is not a terminal, use --yes to answer yes\n"
+    );
+    assert_eq!(fs::read_to_string("Cargo.toml").unwrap(), MANIFEST);
+}
+
+#[test]
+fn a_script_line_can_answer_yes() {
+    let _project = TempProject::builder()
+        .file("Cargo.toml", MANIFEST)
+        .file("ops.tyg", "--yes init --name renamed_app --path .\n")
+        .create()
+        .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["run-script", "ops.tyg"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(fs::read_to_string("Cargo.toml")
+        .unwrap()
+        .contains("name = \"renamed_app\""));
+}
+
+#[test]
+fn a_script_line_cannot_set_the_options_of_the_run() {
+    let _project = TempProject::builder()
+        .file("ops.tyg", "--summary json fail\n")
+        .create()
+        .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["run-script", "ops.tyg"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        strip_locations(&String::from_utf8(output.stderr).unwrap()),
+        "tyg_template: ops.tyg:1: --summary applies to the whole run, give it before run-script\n"
+    );
+}
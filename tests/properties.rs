@@ -0,0 +1,78 @@
+// Property tests of the error formatting.
+
+use proptest::prelude::*;
+use std::ffi::OsString;
+
+use tyg_template::testkit::{capture, matches_pattern, strategy, strip_location};
+use tyg_template::{file_fail_demo, option_err, option_err_bare, run_with_args, Error};
+
+proptest! {
+    #[test]
+    fn display_matches_debug(error in any::<Error>()) {
+        prop_assert_eq!(format!("{}", error), format!("{:?}", error));
+    }
+
+    #[test]
+    fn disclosed_location_strips_to_message(message in strategy::message()) {
+        let error = option_err!("{}", message);
+        let text = error.to_string();
+
+        prop_assert_eq!(strip_location(&text), message.as_str());
+    }
+
+    #[test]
+    fn bare_message_is_only_disclosed_by_feature(message in strategy::message()) {
+        let error = option_err_bare!("{}", message);
+        let text = error.to_string();
+
+        if cfg!(feature = "disclose") {
+            prop_assert_eq!(strip_location(&text), message.as_str());
+        } else {
+            prop_assert_eq!(text, message);
+        }
+    }
+
+    #[test]
+    fn kind_is_kept_with_the_message(error in any::<Error>(), kind in strategy::kind()) {
+        let text = error.to_string();
+        let error = error.with_kind(kind);
+
+        prop_assert_eq!(error.kind(), kind);
+        prop_assert_eq!(error.to_string(), text);
+    }
+
+    #[test]
+    fn file_error_names_path(path in strategy::os_path()) {
+        if let Err(error) = file_fail_demo(true, &path) {
+            let text = error.to_string();
+            let expected = path.to_string_lossy();
+
+            prop_assert!(text.contains(expected.as_ref()));
+        }
+    }
+
+    #[test]
+    fn command_line_never_panics(
+        args in prop::collection::vec(
+            prop_oneof![
+                prop::sample::select(vec!["fail", "recursive_fail", "file_fail", "--bare",
+                                          "--better", "--debug", "--help", "-"])
+                    .prop_map(OsString::from),
+                strategy::os_path(),
+            ],
+            0..6,
+        )
+    ) {
+        let args = std::iter::once(OsString::from("tyg_template")).chain(args);
+
+        let _ = capture(|streams| run_with_args(args, streams));
+    }
+
+    #[test]
+    fn pattern_matches_itself(text in strategy::message()) {
+        prop_assume!(!text.contains('*'));
+
+        prop_assert!(matches_pattern(&text, &text));
+        prop_assert!(matches_pattern(&text, "*"));
+    }
+}
@@ -0,0 +1,137 @@
+// Checks that --sandbox refuses the changes to files outside its directories, whichever subcommand
+// makes them, and logs them.
+
+use std::fs;
+use std::process::Command;
+
+use tyg_template::testkit::{strip_locations, TempProject};
+
+const SETTINGS: &str = "keep_going = true\n";
+
+#[test]
+fn changes_outside_the_sandbox_are_refused() {
+    let project = TempProject::builder()
+        .file("config.toml", SETTINGS)
+        .file("allowed/.keep", "")
+        .create()
+        .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["--sandbox", "allowed", "config", "migrate", "config.toml"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let backup = fs::canonicalize(project.root())
+        .unwrap()
+        .join("config.toml.v1.bak");
+
+    assert_eq!(output.status.code(), Some(77));
+    assert_eq!(
+        stderr,
+        format!(
+            "tyg_template: warning: Would write {0}, which is outside the sandbox\n\
+             tyg_template: {0}: Outside the sandbox, the change was not made\n",
+            backup.display()
+        )
+    );
+    assert!(String::from_utf8(output.stdout).unwrap().is_empty());
+    assert_eq!(fs::read_to_string("config.toml").unwrap(), SETTINGS);
+    assert!(!backup.exists());
+}
+
+#[test]
+fn refusals_are_reported_in_the_error_format() {
+    let project = TempProject::builder()
+        .file("config.toml", SETTINGS)
+        .file("allowed/.keep", "")
+        .create()
+        .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["--error-format", "github", "--sandbox", "allowed"])
+        .args(["config", "migrate", "config.toml"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let backup = fs::canonicalize(project.root())
+        .unwrap()
+        .join("config.toml.v1.bak");
+
+    assert_eq!(output.status.code(), Some(77));
+    assert_eq!(
+        stderr,
+        format!(
+            "::warning title=tyg_template::Would write {0}, which is outside the sandbox\n\
+             ::error title=tyg_template::{0}: Outside the sandbox, the change was not made\n",
+            backup.display()
+        )
+    );
+}
+
+#[test]
+fn changes_inside_the_sandbox_are_made() {
+    let _project = TempProject::builder()
+        .file("config.toml", SETTINGS)
+        .create()
+        .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["--sandbox", ".", "config", "migrate", "config.toml"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(fs::read_to_string("config.toml")
+        .unwrap()
+        .starts_with("config_version = 3\n"));
+    assert_eq!(fs::read_to_string("config.toml.v1.bak").unwrap(), SETTINGS);
+}
+
+#[test]
+fn init_outside_the_sandbox_is_refused() {
+    let manifest = "[package]\nname = \"demo_app\"\n";
+    let _project = TempProject::builder()
+        .file("Cargo.toml", manifest)
+        .file("allowed/.keep", "")
+        .create()
+        .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["--sandbox", "allowed", "--yes"])
+        .args(["init", "--name", "renamed_app", "--path", "."])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(77));
+    assert!(
+        stderr.ends_with("Cargo.toml: Outside the sandbox, the change was not made\n"),
+        "{}",
+        stderr
+    );
+    assert_eq!(fs::read_to_string("Cargo.toml").unwrap(), manifest);
+}
+
+#[test]
+fn a_script_line_cannot_leave_the_sandbox() {
+    let _project = TempProject::builder()
+        .file("config.toml", SETTINGS)
+        .file("allowed/.keep", "")
+        .file("ops.tyg", "--sandbox . config migrate config.toml\n")
+        .create()
+        .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["--sandbox", "allowed", "run-script", "ops.tyg"])
+        .output()
+        .unwrap();
+    let stderr = strip_locations(&String::from_utf8(output.stderr).unwrap());
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr.ends_with("ops.tyg:1: --sandbox: Not within the sandbox of the run\n"),
+        "{}",
+        stderr
+    );
+    assert_eq!(fs::read_to_string("config.toml").unwrap(), SETTINGS);
+}
@@ -0,0 +1,99 @@
+// Checks the quoting and splitting of shell words with words that are awkward to quote.
+
+use tyg_template::shellwords::{join, quote, quote_powershell, split};
+
+// Words holding the characters that the shells treat specially
+const AWKWARD: &[&str] = &[
+    "",
+    " ",
+    "'",
+    "''",
+    "\"",
+    "\\",
+    "\\'",
+    "'\\''",
+    "#not a comment",
+    "a#b",
+    "$HOME",
+    "${IFS}",
+    "$(rm -rf /)",
+    "`id`",
+    "*?[a-z]",
+    "~",
+    "a\tb",
+    "line\nbreak",
+    "trailing\\",
+    "-rf",
+    "@args",
+    ";|&<>(){}!",
+    "caf\u{e9} \u{1F980}",
+    "it\u{2019}s",
+];
+
+#[test]
+fn quoted_words_split_back() {
+    for word in AWKWARD {
+        assert_eq!(split(&quote(word)).unwrap(), [*word], "word {:?}", word);
+    }
+    let line = join(AWKWARD);
+    assert_eq!(split(&line).unwrap(), AWKWARD, "line {:?}", line);
+}
+
+#[test]
+fn powershell_quotes_awkward_words() {
+    for word in AWKWARD {
+        let quoted = quote_powershell(word);
+        if quoted != *word {
+            assert!(
+                quoted.starts_with('\'') && quoted.ends_with('\''),
+                "{:?}",
+                quoted
+            );
+            // Within the quotes every quote character is doubled, so no run of them is odd
+            let inner = &quoted[1..quoted.len() - 1];
+            for quote in ['\'', '\u{2018}', '\u{2019}', '\u{201A}', '\u{201B}'] {
+                let mut runs = inner.split(|c| c != quote);
+                assert!(
+                    runs.all(|run| run.chars().count().is_multiple_of(2)),
+                    "{:?}",
+                    quoted
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn split_handles_awkward_lines() {
+    assert!(split("").unwrap().is_empty());
+    assert!(split("# only a comment").unwrap().is_empty());
+    assert_eq!(split("a\\\nb").unwrap(), ["ab"]);
+    assert_eq!(split("\"a\\\nb\"").unwrap(), ["ab"]);
+    assert_eq!(split(r#""\a\$\"""#).unwrap(), [r#"\a$""#]);
+    assert_eq!(split("''\"\"").unwrap(), [""]);
+    assert_eq!(split("a''b").unwrap(), ["ab"]);
+    assert_eq!(split(" \t a \n b \t ").unwrap(), ["a", "b"]);
+    assert!(split("\"unclosed").is_err());
+    assert!(split("trailing\\").is_err());
+}
+
+// The shell itself gives the words back unchanged
+#[cfg(unix)]
+#[test]
+fn shell_receives_quoted_words() {
+    use std::process::Command;
+
+    let words: Vec<&str> = AWKWARD
+        .iter()
+        .copied()
+        .filter(|word| !word.contains('\n'))
+        .collect();
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!("printf '%s\\n' {}", join(&words)))
+        .output()
+        .unwrap();
+
+    let printed = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(printed.lines().collect::<Vec<_>>(), words);
+}
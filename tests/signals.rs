@@ -0,0 +1,91 @@
+// Checks that the progress of the tasks in progress is written when SIGUSR1 is received, and that
+// the changes of an open journal are rolled back when the process is asked to stop.
+#![cfg(unix)]
+
+use std::fs;
+#[cfg(feature = "signals")]
+use std::fs::OpenOptions;
+#[cfg(feature = "signals")]
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use tyg_template::testkit::TempProject;
+
+#[cfg(feature = "signals")]
+#[test]
+fn sigusr1_dumps_progress() {
+    // The command of the script waits for the gate, a named pipe, to be opened for writing
+    let _project = TempProject::builder()
+        .file("ops.tyg", "# Waits for the gate\nfile_fail gate\n")
+        .create()
+        .unwrap();
+    assert!(Command::new("mkfifo")
+        .arg("gate")
+        .status()
+        .unwrap()
+        .success());
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["run-script", "ops.tyg"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+
+    // Give the application time to install the handler and reach the gate
+    thread::sleep(Duration::from_secs(1));
+    let kill = Command::new("kill")
+        .args(["-USR1", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(kill.success());
+    let mut lines = Vec::new();
+    for _ in 0..2 {
+        let mut line = String::new();
+        stderr.read_line(&mut line).unwrap();
+        lines.push(line);
+    }
+    drop(OpenOptions::new().write(true).open("gate").unwrap());
+
+    assert!(child.wait().unwrap().success());
+    assert!(lines[0].starts_with("tyg_template: progress after "));
+    assert!(
+        lines[1].starts_with("  run-script ops.tyg: 0 of 1 (0%), at ops.tyg:2, "),
+        "{}",
+        lines[1]
+    );
+}
+
+#[test]
+fn sigterm_rolls_back_an_open_journal() {
+    // The manifest is rewritten before the gate, a named pipe read as a source file, blocks init
+    let manifest = "[package]\nname = \"demo_app\"\n";
+    let _project = TempProject::builder()
+        .file("Cargo.toml", manifest)
+        .create()
+        .unwrap();
+    assert!(Command::new("mkfifo")
+        .arg("gate.rs")
+        .status()
+        .unwrap()
+        .success());
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(["--yes", "init", "--name", "renamed_app", "--path", "."])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Give the application time to install the handler and reach the gate
+    thread::sleep(Duration::from_secs(1));
+    let kill = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(kill.success());
+
+    assert_eq!(child.wait().unwrap().code(), Some(128 + 15));
+    assert_eq!(fs::read_to_string("Cargo.toml").unwrap(), manifest);
+}
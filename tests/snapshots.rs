@@ -0,0 +1,501 @@
+// Snapshot tests locking in the output of each subcommand. Run with TYG_UPDATE_SNAPSHOTS=1 to
+// regenerate the snapshot files after an intentional change to the output.
+
+use tyg_template::testkit::{Coverage, Snapshot, TempProject};
+
+// The name of the binary given as the first argument of each command line
+const BIN: &str = env!("CARGO_PKG_NAME");
+
+// Each case generates a test named after its snapshot and contributes its command line to the
+// coverage check
+macro_rules! snapshot_tests {
+    ( $( $name:ident: [ $( $arg:expr ),* ] ),* $(,)? ) => {
+        $(
+            #[test]
+            fn $name() {
+                Snapshot::new(stringify!($name)).assert([BIN, $( $arg ),*]);
+            }
+        )*
+
+        const CASES: &[&[&str]] = &[ $( &[BIN, $( $arg ),*] ),* ];
+    };
+}
+
+snapshot_tests! {
+    help: ["--help"],
+    version: ["--version"],
+    unknown_subcommand: ["no_such_command"],
+    fail: ["fail"],
+    fail_bare: ["fail", "--bare"],
+    recursive_fail: ["recursive_fail"],
+    file_fail: ["file_fail", "no_such_file.txt"],
+    file_fail_better: ["file_fail", "--better", "no_such_file.txt"],
+    parallel_fail: ["parallel_fail"],
+    check: ["check", "Cargo.toml", "no_such_file.txt"],
+    accessible: ["--accessible", "fail"],
+    completions_fish: ["completions", "fish"],
+    dump_cli_schema: ["dump-cli-schema"],
+    deprecated_flag: ["fail", "--no-location"],
+    fetch_invalid_url: ["fetch", "ftp://example.com"],
+    config_auth_conflict: ["config", "auth", "--token", "t0k3n", "--delete"],
+    list_applets: ["--list-applets"],
+}
+
+// The .env file of a temporary project sets a variable for the run, which --debug reports, unless
+// --no-dotenv leaves the file alone
+const DEBUG: &[&str] = &[BIN, "--debug", "fail", "--bare"];
+const NO_DOTENV: &[&str] = &[BIN, "--no-dotenv", "--debug", "fail", "--bare"];
+
+#[test]
+fn debug() {
+    let project = TempProject::builder()
+        .file(".env", "TYG_SNAPSHOT_DEBUG=loaded\n")
+        .create()
+        .unwrap();
+    // The name has already been redacted from the root by the time this redaction is applied
+    let root = project.root().display().to_string().replace(BIN, "[NAME]");
+
+    Snapshot::new("debug").redact(&root, "[ROOT]").assert(DEBUG);
+}
+
+#[test]
+fn no_dotenv() {
+    let _project = TempProject::builder()
+        .file(".env", "TYG_SNAPSHOT_NO_DOTENV=loaded\n")
+        .create()
+        .unwrap();
+
+    Snapshot::new("no_dotenv").assert(NO_DOTENV);
+    assert!(std::env::var_os("TYG_SNAPSHOT_NO_DOTENV").is_none());
+}
+
+// The summary is written to a file in a temporary project rather than to the error stream. Its
+// duration and statistics vary, so only the other fields are checked.
+const SUMMARY_FILE: &[&str] = &[
+    BIN,
+    "--summary",
+    "json",
+    "--summary-file",
+    "summary.json",
+    "fail",
+    "--bare",
+];
+
+#[test]
+fn summary_file() {
+    let _project = TempProject::builder().create().unwrap();
+
+    Snapshot::new("summary_file").assert(SUMMARY_FILE);
+    let text = std::fs::read_to_string("summary.json").unwrap();
+    let summary: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(summary["status"], "failure");
+    assert_eq!(summary["exit_code"], 1);
+    assert_eq!(summary["subcommand"], "fail");
+    assert_eq!(summary["errors"], 1);
+    assert_eq!(summary["warnings"], 0);
+    assert_eq!(summary["error_kind"], "general");
+    assert!(summary["error"]
+        .as_str()
+        .unwrap()
+        .ends_with("Error thrown to demonstrate the error handling process"));
+    assert!(summary["duration_ms"].is_u64());
+}
+
+// The GitHub format reports the warning about the deprecated flag and the error as workflow
+// commands. The location the disclose feature adds to the bare error is removed from its title.
+const ERROR_FORMAT_GITHUB: &[&str] = &[BIN, "--error-format", "github", "fail", "--no-location"];
+
+#[test]
+fn error_format_github() {
+    Snapshot::new("error_format_github")
+        .redact_with(|snapshot| match snapshot.find(",file=") {
+            Some(start) => {
+                let end = start + snapshot[start..].find("::").unwrap_or(0);
+                format!("{}{}", &snapshot[..start], &snapshot[end..])
+            }
+            None => snapshot.to_string(),
+        })
+        .assert(ERROR_FORMAT_GITHUB);
+}
+
+// The init subcommand needs a project to rename, so it is run in a temporary one
+const INIT: &[&str] = &[
+    BIN,
+    "init",
+    "--name",
+    "renamed_app",
+    "--dry-run",
+    "--path",
+    ".",
+];
+
+// Renaming the project asks for confirmation, which --yes gives
+const INIT_YES: &[&str] = &[BIN, "--yes", "init", "--name", "renamed_app", "--path", "."];
+
+fn demo_project() -> TempProject {
+    TempProject::builder()
+        .file(
+            "Cargo.toml",
+            "[package]\nname = \"demo_app\"\nversion = \"0.1.0\"\n",
+        )
+        .file(
+            "src/main.rs",
+            "fn main() {\n    demo_app::run().unwrap();\n}\n",
+        )
+        .file(
+            "README.md",
+            "# demo_app\n\nSee https://example.com/demo_app\n",
+        )
+        .create()
+        .unwrap()
+}
+
+#[test]
+fn init_dry_run() {
+    let _project = demo_project();
+
+    Snapshot::new("init_dry_run").assert(INIT);
+}
+
+#[test]
+fn yes() {
+    let _project = demo_project();
+
+    Snapshot::new("yes").assert(INIT_YES);
+    assert!(std::fs::read_to_string("Cargo.toml")
+        .unwrap()
+        .contains("name = \"renamed_app\""));
+}
+
+// The generator edits the error handler of a temporary project, which starts as a copy of this one
+const GENERATE: &[&str] = &[
+    BIN,
+    "generate",
+    "error-variant",
+    "Json",
+    "--wraps",
+    "serde_json::Error",
+    "--path",
+    ".",
+];
+
+#[test]
+fn generate_error_variant() {
+    let _project = TempProject::builder()
+        .file(
+            "tyg_errors/src/lib.rs",
+            include_str!("../tyg_errors/src/lib.rs"),
+        )
+        .create()
+        .unwrap();
+
+    Snapshot::new("generate_error_variant").assert(GENERATE);
+}
+
+#[test]
+fn generate_error_variant_diverged() {
+    let _project = TempProject::builder()
+        .file(
+            "tyg_errors/src/lib.rs",
+            include_str!("../tyg_errors/src/lib.rs").replace("Error::File(ref e) => Some(e),", ""),
+        )
+        .create()
+        .unwrap();
+
+    Snapshot::new("generate_error_variant_diverged").assert(GENERATE);
+}
+
+// The help is translated by a locale bundle in the data directory of a temporary project
+const TRANSLATED: &[&str] = &[BIN, "--lang", "fr", "fail", "--help"];
+const UNTRANSLATED: &[&str] = &[BIN, "--lang", "xx", "fail"];
+
+#[test]
+fn translated_help() {
+    let bundle = "\
+# French
+fail = Montrer comment renvoyer une erreur avec le gestionnaire d'erreurs
+fail.bare = Afficher l'erreur sans le fichier source ni le numéro de ligne
+fail.examples = Exemple :\\n  {bin} fail --bare
+";
+    let _project = TempProject::builder()
+        .file(format!("home/.local/share/{}/locales/fr.txt", BIN), bundle)
+        .create()
+        .unwrap();
+
+    Snapshot::new("translated_help").assert(TRANSLATED);
+}
+
+#[test]
+fn missing_locale_bundle() {
+    let project = TempProject::builder().create().unwrap();
+    // The name has already been redacted from the root by the time this redaction is applied
+    let root = project.root().display().to_string().replace(BIN, "[NAME]");
+
+    Snapshot::new("missing_locale_bundle")
+        .redact(&root, "[ROOT]")
+        .assert(UNTRANSLATED);
+}
+
+// Completions are installed in the home directory of a temporary project
+const INSTALL_COMPLETIONS: &[&str] = &[BIN, "completions", "--install", "nushell"];
+
+#[test]
+fn install_completions() {
+    let project = TempProject::builder().create().unwrap();
+    // The name has already been redacted from the root by the time this redaction is applied
+    let root = project.root().display().to_string().replace(BIN, "[NAME]");
+
+    Snapshot::new("install_completions")
+        .redact(&root, "[ROOT]")
+        .assert(INSTALL_COMPLETIONS);
+    assert!(project
+        .home()
+        .join(format!(".config/nushell/completions/{}.nu", BIN))
+        .is_file());
+}
+
+// The cache clear subcommand removes the entries in the cache directory of a temporary project
+const CACHE_CLEAR: &[&str] = &[BIN, "cache", "clear"];
+
+#[test]
+fn cache_clear() {
+    let project = TempProject::builder()
+        .file(
+            format!("home/.cache/{}/0123456789abcdef.json", BIN),
+            r#"{"created":1704110400,"key":"releases","value":["1.0.0"]}"#,
+        )
+        .create()
+        .unwrap();
+    // The name has already been redacted from the root by the time this redaction is applied
+    let root = project.root().display().to_string().replace(BIN, "[NAME]");
+
+    Snapshot::new("cache_clear")
+        .redact(&root, "[ROOT]")
+        .assert(CACHE_CLEAR);
+}
+
+// The history subcommand reads the audit log in the state directory of a temporary project
+const HISTORY: &[&str] = &[
+    BIN,
+    "history",
+    "--subcommand",
+    "fail",
+    "--failed",
+    "--limit",
+    "2",
+];
+
+#[test]
+fn history() {
+    let _project = TempProject::builder()
+        .file(
+            format!("home/.local/state/{}/audit.log", BIN),
+            concat!(
+                r#"{"time":1704110400,"user":"alice","subcommand":"fail","args":["[NAME]","fail"],"duration_ms":3,"status":1}"#,
+                "\n",
+                r#"{"time":1704110460,"user":"bob","subcommand":"init","args":["[NAME]","init","--name","x"],"duration_ms":40,"status":0}"#,
+                "\n",
+                "not an entry\n",
+                r#"{"time":1704110520,"user":"alice","subcommand":"fail","args":["[NAME]","fail","--bare"],"duration_ms":2,"status":1}"#,
+                "\n",
+                r#"{"time":1704110580,"user":"bob","subcommand":"fail","args":["[NAME]","fail","--bare"],"duration_ms":2,"status":0}"#,
+                "\n",
+            ),
+        )
+        .create()
+        .unwrap();
+
+    Snapshot::new("history").assert(HISTORY);
+}
+
+// The run-script subcommand runs the commands of a script in a temporary project, keeping going
+// after the failures
+const RUN_SCRIPT: &[&str] = &[BIN, "run-script", "--keep-going", "ops.tyg"];
+
+#[test]
+fn run_script() {
+    let _project = TempProject::builder()
+        .file(
+            "ops.tyg",
+            "# Checks\nfail --bare\n\nrecursive_fail\nno_such_command\nrun-script ops.tyg\n",
+        )
+        .create()
+        .unwrap();
+
+    Snapshot::new("run_script").assert(RUN_SCRIPT);
+}
+
+// The config validate subcommand checks a configuration file in a temporary project
+const CONFIG_VALIDATE: &[&str] = &[BIN, "config", "validate", "settings.toml"];
+
+#[test]
+fn config_validate() {
+    let _project = TempProject::builder()
+        .file(
+            "settings.toml",
+            "acessible = true\n\n[fetch]\ntimeout = 30\nretries = 3\n\n[updates]\ncheck = false\n",
+        )
+        .create()
+        .unwrap();
+
+    Snapshot::new("config_validate").assert(CONFIG_VALIDATE);
+}
+
+// The config migrate subcommand previews the upgrade of a configuration file written for the first
+// version in a temporary project
+const CONFIG_MIGRATE: &[&str] = &[BIN, "config", "migrate", "--dry-run", "settings.toml"];
+
+#[test]
+fn config_migrate_dry_run() {
+    let _project = TempProject::builder()
+        .file(
+            "settings.toml",
+            "# Settings\naccessibility = true\nkeep_going = true\n\n[fetch]\ntimeout = 30\nuser_agent = \"demo\"\n",
+        )
+        .create()
+        .unwrap();
+
+    Snapshot::new("config_migrate_dry_run").assert(CONFIG_MIGRATE);
+}
+
+// Migrating a configuration file whose backup is already there from an earlier migration replaces
+// the backup only with --force
+const FORCE: &[&str] = &[BIN, "--force", "config", "migrate", "settings.toml"];
+const FORCE_REFUSED: &[&str] = &[BIN, "config", "migrate", "settings.toml"];
+
+fn migrated_project() -> TempProject {
+    TempProject::builder()
+        .file("settings.toml", "keep_going = true\n")
+        .file("settings.toml.v1.bak", "# An earlier backup\n")
+        .create()
+        .unwrap()
+}
+
+#[test]
+fn force() {
+    let _project = migrated_project();
+
+    Snapshot::new("force").assert(FORCE);
+    assert_eq!(
+        std::fs::read_to_string("settings.toml.v1.bak").unwrap(),
+        "keep_going = true\n"
+    );
+}
+
+#[test]
+fn force_refused() {
+    let _project = migrated_project();
+
+    Snapshot::new("force_refused").assert(FORCE_REFUSED);
+    assert_eq!(
+        std::fs::read_to_string("settings.toml").unwrap(),
+        "keep_going = true\n"
+    );
+}
+
+// Confined to a directory of a temporary project, the migration of a configuration file outside it
+// is refused. The warning naming the change is written to the error stream of the process, and is
+// checked by tests/sandbox.rs.
+const SANDBOX: &[&str] = &[
+    BIN,
+    "--sandbox",
+    "out",
+    "config",
+    "migrate",
+    "settings.toml",
+];
+
+#[test]
+fn sandbox() {
+    let project = TempProject::builder()
+        .file("settings.toml", "keep_going = true\n")
+        .file("out/.keep", "")
+        .create()
+        .unwrap();
+    // The paths are resolved, and the name has already been redacted from the root by the time this
+    // redaction is applied
+    let root = std::fs::canonicalize(project.root()).unwrap();
+    let root = root.display().to_string().replace(BIN, "[NAME]");
+
+    Snapshot::new("sandbox")
+        .redact(&root, "[ROOT]")
+        .assert(SANDBOX);
+    assert!(!std::path::Path::new("settings.toml.v1.bak").exists());
+}
+
+// A link named after an applet runs its subcommand, so the applet is given in place of the binary
+#[test]
+fn applet_check() {
+    Snapshot::new("applet_check").assert(["tyg-check", "Cargo.toml", "no_such_file.txt"]);
+}
+
+// The outcome of a request depends on the http feature and the network, so the options of the fetch
+// subcommand are only checked for coverage
+const FETCH: &[&str] = &[
+    BIN,
+    "fetch",
+    "--timeout",
+    "5s",
+    "--max-size",
+    "1MiB",
+    "http://127.0.0.1:9/",
+];
+
+// The outcome of storing a token depends on the keyring feature and the keyring of the platform,
+// which the tests must not change, so the options of config auth are only checked for coverage
+const AUTH: &[&str] = &[BIN, "config", "auth", "--token", "t0k3n"];
+const AUTH_DELETE: &[&str] = &[BIN, "config", "auth", "--delete"];
+
+// The changelog changes with every release, so the options of the changelog subcommand are only
+// checked for coverage
+const CHANGELOG: &[&str] = &[BIN, "changelog", "--since", "0.1.0"];
+
+// Fails when a subcommand or argument is added without a snapshot test that exercises it
+#[test]
+fn coverage() {
+    let mut coverage = Coverage::new();
+    for args in CASES {
+        coverage.record(args.iter());
+    }
+    coverage.record(SUMMARY_FILE);
+    coverage.record(DEBUG);
+    coverage.record(NO_DOTENV);
+    coverage.record(ERROR_FORMAT_GITHUB);
+    coverage.record(INIT);
+    coverage.record(INIT_YES);
+    coverage.record(FORCE);
+    coverage.record(GENERATE);
+    coverage.record(UNTRANSLATED);
+    coverage.record(INSTALL_COMPLETIONS);
+    coverage.record(CACHE_CLEAR);
+    coverage.record(HISTORY);
+    coverage.record(CHANGELOG);
+    coverage.record(RUN_SCRIPT);
+    coverage.record(CONFIG_VALIDATE);
+    coverage.record(CONFIG_MIGRATE);
+    coverage.record(FETCH);
+    coverage.record(AUTH);
+    coverage.record(AUTH_DELETE);
+    coverage.record(SANDBOX);
+    // The limits are enforced on the whole process by run, with a watchdog thread that ends it, so
+    // they are checked by tests/limits.rs, which runs the binary
+    coverage.exclude(
+        "--max-memory",
+        "enforced on the process, see tests/limits.rs",
+    );
+    coverage.exclude(
+        "--max-runtime",
+        "enforced on the process, see tests/limits.rs",
+    );
+    // The recovery prompts of --interactive are only asked on a terminal, which the tests do not
+    // have, so they are checked by the examples of prompt::recover
+    coverage.exclude("--interactive", "asks on a terminal, see prompt::recover");
+    // The progress events are written by the registry shared by the process to its error stream,
+    // which a snapshot does not capture, so they are checked by tests/progress.rs
+    coverage.exclude(
+        "--progress-format",
+        "written to the process's error stream, see tests/progress.rs",
+    );
+
+    coverage.assert_at_least(100.0);
+}
@@ -0,0 +1,21 @@
+// Checks that the manifest used by cargo-generate stays in step with the template's own manifest.
+
+use std::fs;
+use std::path::Path;
+
+// Return the part of the manifest from the dependencies onwards
+fn tail(manifest: &str) -> &str {
+    let start = manifest
+        .find("\n[dependencies]")
+        .expect("manifest has no dependencies");
+    &manifest[start..]
+}
+
+#[test]
+fn generated_manifest_matches() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let manifest = fs::read_to_string(root.join("Cargo.toml")).unwrap();
+    let generated = fs::read_to_string(root.join("template").join("Cargo.toml")).unwrap();
+
+    assert_eq!(tail(&generated), tail(&manifest));
+}
@@ -0,0 +1,27 @@
+// Checks that the background check for a newer release leaves no trace in the audit log.
+
+use std::process::Command;
+
+use tyg_template::testkit::TempProject;
+
+// Run the application with the audit log turned on, asking an endpoint that cannot answer
+fn run(args: &[&str]) {
+    Command::new(env!("CARGO_BIN_EXE_tyg_template"))
+        .args(args)
+        .env("TYG_AUDIT_LOG", "1")
+        .env("TYG_UPDATE_URL", "http://127.0.0.1:9/releases/latest")
+        .output()
+        .unwrap();
+}
+
+#[test]
+fn the_update_check_is_not_audited() {
+    let project = TempProject::builder().create().unwrap();
+    let log = project.path("home/.local/state/tyg_template/audit.log");
+
+    run(&["__update-check"]);
+    assert!(!log.exists());
+
+    run(&["fail", "--bare"]);
+    assert!(log.exists());
+}
@@ -0,0 +1,204 @@
+//! The collection of the errors of a batch of operations that carries on in spite of them.
+
+use std::fmt;
+
+use crate::{Error, Result};
+
+/// The errors of a batch of operations, each with the context in which it happened, e.g. the
+/// file and line of a command or the name of a thread, and the number of operations attempted.
+///
+/// The collection is turned into a single error summarising the failures with
+/// [`into_result`](ErrorCollection::into_result) once the batch is finished.
+///
+/// # Examples
+/// ```
+/// use tyg_errors::{Error, ErrorCollection, Result};
+///
+/// fn check(n: u32) -> Result<u32> {
+///     if n % 2 == 0 { Ok(n) } else { Err(Error::Error(format!("{} is odd", n))) }
+/// }
+///
+/// let mut errors = ErrorCollection::new();
+/// let even: Vec<u32> = (1..=4)
+///     .filter_map(|n| errors.record(format!("item {}", n), check(n)))
+///     .collect();
+///
+/// assert_eq!(even, [2, 4]);
+/// assert_eq!(errors.to_string(), "2 of 4 failed:\n  item 1: 1 is odd\n  item 3: 3 is odd");
+/// assert!(errors.into_result().is_err());
+/// assert!(ErrorCollection::new().into_result().is_ok());
+/// ```
+///
+/// A collection made [`with_recovery`](ErrorCollection::with_recovery) asks a hook what to do
+/// about each failure of an operation run with [`attempt`](ErrorCollection::attempt), so that the
+/// operation can be retried or the batch aborted, e.g. on the answer to a prompt.
+#[derive(Default)]
+pub struct ErrorCollection {
+    errors: Vec<(String, Error)>,
+    attempted: usize,
+    recovery: Option<Box<RecoveryHook>>,
+    always_skip: bool,
+}
+
+// The hook deciding what to do about a failure, given its context and error
+type RecoveryHook = dyn FnMut(&str, &Error) -> Recovery;
+
+/// What to do about the failure of an operation of a batch, as decided by the recovery hook of an
+/// [`ErrorCollection`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// Run the operation again.
+    Retry,
+    /// Keep the error and carry on with the next operation.
+    Skip,
+    /// Stop the batch with the error.
+    Abort,
+    /// Keep the error and carry on, and do the same for the failures that follow without asking.
+    AlwaysSkip,
+}
+
+impl ErrorCollection {
+    /// Start an empty collection.
+    pub fn new() -> ErrorCollection {
+        ErrorCollection::default()
+    }
+
+    /// Start an empty collection that asks the hook what to do about each failure of an operation
+    /// run with [`attempt`](ErrorCollection::attempt).
+    pub fn with_recovery(hook: impl FnMut(&str, &Error) -> Recovery + 'static) -> ErrorCollection {
+        ErrorCollection {
+            recovery: Some(Box::new(hook)),
+            ..ErrorCollection::default()
+        }
+    }
+
+    /// Run an operation and count it, asking the recovery hook what to do if it fails: run it
+    /// again, keep its error and carry on, or abort. Returns the value of the operation if it
+    /// succeeded, `None` if its error was kept, or the error if the batch is to be aborted. Without
+    /// a hook the error is kept, as it is by [`record`](ErrorCollection::record).
+    ///
+    /// # Examples
+    /// ```
+    /// use std::cell::Cell;
+    /// use tyg_errors::{Error, ErrorCollection, Recovery};
+    ///
+    /// // Retry the first failure of each item, then skip it
+    /// let mut retried = false;
+    /// let mut errors = ErrorCollection::with_recovery(move |_, _| {
+    ///     retried = !retried;
+    ///     if retried { Recovery::Retry } else { Recovery::Skip }
+    /// });
+    /// let runs = Cell::new(0);
+    /// let failing = || { runs.set(runs.get() + 1); Err::<u32, _>(Error::Error("Offline".to_string())) };
+    ///
+    /// assert_eq!(errors.attempt("item 1", || Ok(1)).unwrap(), Some(1));
+    /// assert_eq!(errors.attempt("item 2", failing).unwrap(), None);
+    /// assert_eq!(runs.get(), 2);
+    /// assert_eq!(errors.to_string(), "1 of 2 failed:\n  item 2: Offline");
+    ///
+    /// let mut errors = ErrorCollection::with_recovery(|_, _| Recovery::Abort);
+    /// assert_eq!(errors.attempt("item 1", failing).unwrap_err().to_string(), "Offline");
+    /// ```
+    pub fn attempt<T>(
+        &mut self,
+        context: impl fmt::Display,
+        mut operation: impl FnMut() -> Result<T>,
+    ) -> Result<Option<T>> {
+        let context = context.to_string();
+        loop {
+            let error = match operation() {
+                Ok(value) => {
+                    self.attempted += 1;
+                    return Ok(Some(value));
+                }
+                Err(error) => error,
+            };
+            let recovery = match self.recovery {
+                Some(ref mut hook) if !self.always_skip => hook(&context, &error),
+                _ => Recovery::Skip,
+            };
+            match recovery {
+                Recovery::Retry => continue,
+                Recovery::Abort => {
+                    self.attempted += 1;
+                    return Err(error);
+                }
+                Recovery::Skip | Recovery::AlwaysSkip => {
+                    self.always_skip |= recovery == Recovery::AlwaysSkip;
+                    self.push(context, error);
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Count an operation and keep its error, if it failed, returning its value if it succeeded.
+    pub fn record<T>(&mut self, context: impl fmt::Display, result: Result<T>) -> Option<T> {
+        self.attempted += 1;
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.errors.push((context.to_string(), error));
+                None
+            }
+        }
+    }
+
+    /// Count a failed operation and keep its error.
+    pub fn push(&mut self, context: impl fmt::Display, error: Error) {
+        self.attempted += 1;
+        self.errors.push((context.to_string(), error));
+    }
+
+    /// The number of operations counted.
+    pub fn attempted(&self) -> usize {
+        self.attempted
+    }
+
+    /// The number of operations that failed.
+    pub fn failed(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Whether no operation failed.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// The errors, each with its context, in the order they happened.
+    pub fn errors(&self) -> &[(String, Error)] {
+        &self.errors
+    }
+
+    /// Succeed if no operation failed, or fail with an [`Error::Error`] whose message is the
+    /// summary of the failures.
+    pub fn into_result(self) -> Result<()> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(Error::Error(self.to_string())),
+        }
+    }
+}
+
+impl fmt::Debug for ErrorCollection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ErrorCollection")
+            .field("errors", &self.errors)
+            .field("attempted", &self.attempted)
+            .field("recovery", &self.recovery.is_some())
+            .field("always_skip", &self.always_skip)
+            .finish()
+    }
+}
+
+impl fmt::Display for ErrorCollection {
+    /// The summary of the failures: the number that failed, then each error after its context on
+    /// a line of its own.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} of {} failed:", self.failed(), self.attempted)?;
+        for (context, error) in &self.errors {
+            write!(f, "\n  {}: {}", context, error)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+//! Extension traits that convert other types into the template's [`Error`] without the `or_else`
+//! and `ok_or_else` closures the macros need, and that process iterators over results.
+//!
+//! The location of an error is captured where the method is called, using `#[track_caller]`, and
+//! is disclosed at the start of the message in the same form as the macros use, so
+//! [`Location::split`](crate::Location::split) finds it.
+
+use std::fmt;
+use std::panic;
+
+use crate::{Error, ErrorKind, Result, DISCLOSE};
+
+/// Methods adding a message or a location to the error of any `Result` whose error converts into
+/// an [`Error`].
+///
+/// The error is replaced by an [`Error::Error`] whose message is made of the location, if it is
+/// disclosed, the message, if one is given, and the original error, separated by `: `. It keeps
+/// the [kind](ErrorKind) of the original error, so a file that is not found is still classified as
+/// [`ErrorKind::NotFound`].
+///
+/// # Examples
+/// ```
+/// use std::fs::File;
+/// use std::path::Path;
+/// use tyg_errors::{ErrorKind, Location, Result, ResultExt};
+///
+/// fn open(path: &Path) -> Result<File> {
+///     File::open(path).msg(|| path.display())
+/// }
+///
+/// let message = open(Path::new("no_such_file.txt")).unwrap_err().to_string();
+/// let (location, rest) = Location::split(&message).unwrap();
+///
+/// assert!(location.file.ends_with(".rs"));
+/// assert!(rest.starts_with("no_such_file.txt: "));
+/// assert_eq!(open(Path::new("no_such_file.txt")).unwrap_err().kind(), ErrorKind::NotFound);
+/// ```
+pub trait ResultExt<T> {
+    /// Prefix the error with a message and disclose the location, as
+    /// [`result_err!`](crate::result_err) does.
+    ///
+    /// The message is only created if there is an error.
+    fn msg<M: fmt::Display>(self, message: impl FnOnce() -> M) -> Result<T>;
+
+    /// Prefix the error with a message without disclosing the location, unless the `disclose`
+    /// feature is enabled, as [`result_err_bare!`](crate::result_err_bare) does.
+    ///
+    /// The message is only created if there is an error.
+    fn bare<M: fmt::Display>(self, message: impl FnOnce() -> M) -> Result<T>;
+
+    /// Disclose the location of the error without adding a message.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io;
+    /// use tyg_errors::{Location, ResultExt};
+    ///
+    /// let result: Result<(), io::Error> = Err(io::Error::new(io::ErrorKind::NotFound, "Missing"));
+    /// let message = result.loc().unwrap_err().to_string();
+    /// let (_, rest) = Location::split(&message).unwrap();
+    ///
+    /// assert_eq!(rest, "Missing");
+    /// ```
+    fn loc(self) -> Result<T>;
+
+    /// Give the error a [kind](ErrorKind), as the error macros do when a kind is given. It follows
+    /// the methods above, which disclose the location where they are called.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::fs;
+    /// use tyg_errors::{ErrorKind, ResultExt};
+    ///
+    /// let result = fs::read_to_string("no_such_settings.toml")
+    ///     .msg(|| "no_such_settings.toml")
+    ///     .with_kind(ErrorKind::Config);
+    ///
+    /// assert_eq!(result.unwrap_err().kind(), ErrorKind::Config);
+    /// ```
+    fn with_kind(self, kind: ErrorKind) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for std::result::Result<T, E> {
+    #[track_caller]
+    fn msg<M: fmt::Display>(self, message: impl FnOnce() -> M) -> Result<T> {
+        let caller = panic::Location::caller();
+        self.map_err(|e| {
+            let e = e.into();
+            keep(&e, located(caller, format_args!("{}: {}", message(), e)))
+        })
+    }
+
+    #[track_caller]
+    fn bare<M: fmt::Display>(self, message: impl FnOnce() -> M) -> Result<T> {
+        let caller = panic::Location::caller();
+        self.map_err(|e| {
+            let e = e.into();
+            let error = if DISCLOSE {
+                located(caller, format_args!("{}: {}", message(), e))
+            } else {
+                Error::Error(format!("{}: {}", message(), e)).scoped()
+            };
+            keep(&e, error)
+        })
+    }
+
+    #[track_caller]
+    fn loc(self) -> Result<T> {
+        let caller = panic::Location::caller();
+        self.map_err(|e| {
+            let e = e.into();
+            keep(&e, located(caller, format_args!("{}", e)))
+        })
+    }
+
+    fn with_kind(self, kind: ErrorKind) -> Result<T> {
+        self.map_err(|e| e.into().with_kind(kind))
+    }
+}
+
+/// Methods converting an `Option` into a `Result`, with an error for `None` that discloses the
+/// location of the call, as [`option_err!`](crate::option_err) does with `ok_or_else`.
+///
+/// # Examples
+/// ```
+/// use tyg_errors::{Location, OptionExt, Result};
+///
+/// fn first_word(text: &str) -> Result<&str> {
+///     text.split_whitespace().next().or_err("No words found")
+/// }
+///
+/// assert_eq!(first_word("Hello world").unwrap(), "Hello");
+///
+/// let message = first_word("").unwrap_err().to_string();
+/// let (_, rest) = Location::split(&message).unwrap();
+/// assert_eq!(rest, "No words found");
+/// ```
+pub trait OptionExt<T> {
+    /// Convert `None` into an error with the given message.
+    fn or_err(self, message: impl fmt::Display) -> Result<T>;
+
+    /// Convert `None` into an error with the message returned by the closure, which is only called
+    /// if the value is `None`.
+    fn or_err_with<M: fmt::Display>(self, message: impl FnOnce() -> M) -> Result<T>;
+}
+
+impl<T> OptionExt<T> for Option<T> {
+    #[track_caller]
+    fn or_err(self, message: impl fmt::Display) -> Result<T> {
+        let caller = panic::Location::caller();
+        self.ok_or_else(|| located(caller, format_args!("{}", message)))
+    }
+
+    #[track_caller]
+    fn or_err_with<M: fmt::Display>(self, message: impl FnOnce() -> M) -> Result<T> {
+        let caller = panic::Location::caller();
+        self.ok_or_else(|| located(caller, format_args!("{}", message())))
+    }
+}
+
+/// Adapters for iterators over results, so that the values can be processed until, or in spite of,
+/// an error without a hand written loop.
+///
+/// # Examples
+/// ```
+/// use tyg_errors::{result_err, Result, ResultIteratorExt};
+///
+/// fn cycles() -> impl Iterator<Item = Result<u32>> {
+///     (1..=10).map(|n| if n % 5 == 0 { result_err!("Failed at cycle {}", n) } else { Ok(n) })
+/// }
+///
+/// // Stop at the first error
+/// let values: Vec<Result<u32>> = cycles().take_until_err().collect();
+/// assert_eq!(values.len(), 5);
+/// assert!(values[4].is_err());
+///
+/// // Keep going in spite of the errors
+/// let (values, errors) = cycles().map_ok(|n| n * 10).filter_ok(|n| *n > 20).collect_partitioned();
+/// assert_eq!(values, [30, 40, 60, 70, 80, 90]);
+/// assert_eq!(errors.len(), 2);
+///
+/// // Sum the values up to the first error
+/// assert!(cycles().try_fold_all(0, |sum, n| Ok(sum + n)).is_err());
+/// assert_eq!(cycles().take(4).try_fold_all(0, |sum, n| Ok(sum + n)).unwrap(), 10);
+/// ```
+pub trait ResultIteratorExt<T>: Iterator<Item = Result<T>> + Sized {
+    /// Yield the results up to and including the first error.
+    fn take_until_err(self) -> TakeUntilErr<Self> {
+        TakeUntilErr {
+            iter: self,
+            done: false,
+        }
+    }
+
+    /// Yield the errors and the values for which the predicate returns true.
+    fn filter_ok<P: FnMut(&T) -> bool>(self, predicate: P) -> FilterOk<Self, P> {
+        FilterOk {
+            iter: self,
+            predicate,
+        }
+    }
+
+    /// Yield the errors and the values mapped by the function.
+    fn map_ok<U, F: FnMut(T) -> U>(self, f: F) -> MapOk<Self, F> {
+        MapOk { iter: self, f }
+    }
+
+    /// Fold the values with a fallible function, returning the first error from either the
+    /// iterator or the function.
+    fn try_fold_all<B, F: FnMut(B, T) -> Result<B>>(mut self, init: B, mut f: F) -> Result<B> {
+        self.try_fold(init, |accumulator, item| f(accumulator, item?))
+    }
+
+    /// Consume every result, returning the values and the errors separately.
+    fn collect_partitioned(self) -> (Vec<T>, Vec<Error>) {
+        let mut values = Vec::new();
+        let mut errors = Vec::new();
+        for item in self {
+            match item {
+                Ok(value) => values.push(value),
+                Err(error) => errors.push(error),
+            }
+        }
+        (values, errors)
+    }
+}
+
+impl<T, I: Iterator<Item = Result<T>>> ResultIteratorExt<T> for I {}
+
+/// The iterator returned by [`ResultIteratorExt::take_until_err`].
+pub struct TakeUntilErr<I> {
+    iter: I,
+    done: bool,
+}
+
+impl<T, I: Iterator<Item = Result<T>>> Iterator for TakeUntilErr<I> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self.iter.next()?;
+        self.done = item.is_err();
+        Some(item)
+    }
+}
+
+/// The iterator returned by [`ResultIteratorExt::filter_ok`].
+pub struct FilterOk<I, P> {
+    iter: I,
+    predicate: P,
+}
+
+impl<T, I: Iterator<Item = Result<T>>, P: FnMut(&T) -> bool> Iterator for FilterOk<I, P> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.find(|item| match item {
+            Ok(value) => (self.predicate)(value),
+            Err(_) => true,
+        })
+    }
+}
+
+/// The iterator returned by [`ResultIteratorExt::map_ok`].
+pub struct MapOk<I, F> {
+    iter: I,
+    f: F,
+}
+
+impl<T, U, I: Iterator<Item = Result<T>>, F: FnMut(T) -> U> Iterator for MapOk<I, F> {
+    type Item = Result<U>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|item| item.map(&mut self.f))
+    }
+}
+
+// Give the error replacing another the kind of the original, unless it is general, and the scopes
+// of the original, unless it has none
+fn keep(original: &Error, error: Error) -> Error {
+    let error = match original.kind() {
+        ErrorKind::General => error,
+        kind => error.with_kind(kind),
+    };
+    match original.scopes() {
+        [] => error,
+        scopes => error.in_scopes(scopes.to_vec()),
+    }
+}
+
+// Create an error disclosing the given location, within the scopes open on the current thread
+fn located(caller: &panic::Location, message: fmt::Arguments) -> Error {
+    Error::Error(format!(
+        "{}:{}:{}: {}",
+        caller.file(),
+        caller.line(),
+        caller.column(),
+        message
+    ))
+    .scoped()
+}
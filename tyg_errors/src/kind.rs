@@ -0,0 +1,178 @@
+//! The classification of errors, which decides the exit code of a failed run and is reported with
+//! the error in machine readable output.
+
+use std::fmt;
+use std::io;
+
+use crate::Error;
+
+/// The kind of an error.
+///
+/// An error is given a kind explicitly when it is created, e.g.
+/// `result_err!(kind = ErrorKind::Config, "Invalid value for {}", key)`, or with
+/// [`Error::with_kind`], and otherwise the kind follows from the variant of the error, see
+/// [`Error::kind`]. Each kind has an exit code, following the conventions of `sysexits.h` where
+/// they apply.
+///
+/// # Examples
+/// ```
+/// use tyg_errors::ErrorKind;
+///
+/// assert_eq!(ErrorKind::Config.name(), "config");
+/// assert_eq!(ErrorKind::Config.exit_code(), 78);
+/// assert_eq!(ErrorKind::General.exit_code(), 1);
+/// assert_eq!(ErrorKind::from_name("not_found"), Some(ErrorKind::NotFound));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ErrorKind {
+    /// An error that fits no other kind.
+    #[default]
+    General,
+    /// The command line was used incorrectly.
+    Usage,
+    /// The configuration is missing or invalid.
+    Config,
+    /// The data given to the application is invalid.
+    Input,
+    /// A file or other resource does not exist.
+    NotFound,
+    /// The application is not allowed to do what was asked.
+    Permission,
+    /// Reading or writing a file or stream failed.
+    Io,
+    /// A host or service could not be reached.
+    Network,
+    /// Data arrived intact but failed a check, such as a checksum.
+    Verification,
+    /// A limit set on the resources of the run, such as its memory or runtime, was exceeded.
+    ResourceLimit,
+    /// A bug in the application.
+    Internal,
+}
+
+impl ErrorKind {
+    /// Every kind, in the order they are declared.
+    pub const ALL: [ErrorKind; 11] = [
+        ErrorKind::General,
+        ErrorKind::Usage,
+        ErrorKind::Config,
+        ErrorKind::Input,
+        ErrorKind::NotFound,
+        ErrorKind::Permission,
+        ErrorKind::Io,
+        ErrorKind::Network,
+        ErrorKind::Verification,
+        ErrorKind::ResourceLimit,
+        ErrorKind::Internal,
+    ];
+
+    /// The name of the kind, as reported in machine readable output.
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::General => "general",
+            ErrorKind::Usage => "usage",
+            ErrorKind::Config => "config",
+            ErrorKind::Input => "input",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Permission => "permission",
+            ErrorKind::Io => "io",
+            ErrorKind::Network => "network",
+            ErrorKind::Verification => "verification",
+            ErrorKind::ResourceLimit => "resource_limit",
+            ErrorKind::Internal => "internal",
+        }
+    }
+
+    /// The kind with the given name, if there is one.
+    pub fn from_name(name: &str) -> Option<ErrorKind> {
+        ErrorKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// The exit code of a run that fails with an error of this kind.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::General => 1,
+            ErrorKind::Usage => 64,
+            ErrorKind::Input | ErrorKind::Verification => 65,
+            ErrorKind::NotFound => 66,
+            ErrorKind::Network => 69,
+            ErrorKind::Internal => 70,
+            ErrorKind::Io => 74,
+            ErrorKind::ResourceLimit => 75,
+            ErrorKind::Permission => 77,
+            ErrorKind::Config => 78,
+        }
+    }
+
+    // The kind of an io::Error
+    fn of_io(error: &io::Error) -> ErrorKind {
+        match error.kind() {
+            io::ErrorKind::NotFound => ErrorKind::NotFound,
+            io::ErrorKind::PermissionDenied => ErrorKind::Permission,
+            io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => ErrorKind::Input,
+            _ => ErrorKind::Io,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Error {
+    /// The kind of the error: the kind it was given, or otherwise the kind that follows from its
+    /// variant. The kind of a File error follows from the kind of the `io::Error`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io;
+    /// use tyg_errors::{result_err_bare, Error, ErrorKind, Result};
+    ///
+    /// let missing = Error::File(io::Error::from(io::ErrorKind::NotFound));
+    /// let invalid: Result<()> = result_err_bare!(kind = ErrorKind::Config, "Invalid value for {}", "port");
+    ///
+    /// assert_eq!(Error::Error("Failed".to_string()).kind(), ErrorKind::General);
+    /// assert_eq!(missing.kind(), ErrorKind::NotFound);
+    /// assert_eq!(invalid.unwrap_err().kind(), ErrorKind::Config);
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            Error::Error(_) | Error::Deferred(_) => ErrorKind::General,
+            Error::File(ref e) => ErrorKind::of_io(e),
+            Error::Cli(_) => ErrorKind::Usage,
+            Error::Network(_) => ErrorKind::Network,
+            Error::Verification(_) => ErrorKind::Verification,
+            Error::ResourceLimit(_) => ErrorKind::ResourceLimit,
+            Error::SandboxViolation { .. } => ErrorKind::Permission,
+            Error::Classified(kind, _) => kind,
+            Error::Scoped(ref e) => e.error.kind(),
+            #[cfg(feature = "http")]
+            Error::Http(_) => ErrorKind::Network,
+            // The variants added with `generate error-variant`
+            #[allow(unreachable_patterns)]
+            _ => ErrorKind::General,
+        }
+    }
+
+    /// Give the error a kind, replacing the kind it had. The message and the source of the error
+    /// are kept.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io;
+    /// use tyg_errors::{Error, ErrorKind};
+    ///
+    /// let error = Error::File(io::Error::other("settings.toml: Bad syntax")).with_kind(ErrorKind::Config);
+    ///
+    /// assert_eq!(error.kind(), ErrorKind::Config);
+    /// assert_eq!(error.to_string(), "settings.toml: Bad syntax");
+    /// ```
+    pub fn with_kind(self, kind: ErrorKind) -> Error {
+        match self {
+            Error::Classified(_, error) => Error::Classified(kind, error),
+            error => Error::Classified(kind, Box::new(error)),
+        }
+    }
+}
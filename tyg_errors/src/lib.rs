@@ -0,0 +1,649 @@
+//! The error type and error macros used by command line applications built from the
+//! `tyg_template` project.
+//!
+//! Errors are created with the [`option_err!`], [`option_err_bare!`], [`result_err!`] and
+//! [`result_err_bare!`] macros. Disclosed errors show the name of the source file and the location
+//! in the source where the error occured, whereas bare errors only show the message unless the
+//! `disclose` feature is enabled.
+//!
+//! The [`ResultExt`] trait gives the error of any `Result` that converts into an `Error` a message
+//! and a location in the same way, e.g. `File::open(path).msg(|| path.display())?`, and the
+//! [`OptionExt`] trait converts `None` into an error, e.g. `args.next().or_err("No path given")?`.
+//!
+//! Every error has an [`ErrorKind`], which decides the exit code of a failed run. The kind follows
+//! from the variant of the error unless one is given as the first argument of a macro, e.g.
+//! `result_err!(kind = ErrorKind::Config, "Invalid value for {}", key)`, or with the `with_kind`
+//! methods of [`Error`] and [`ResultExt`].
+//!
+//! The macros build the whole message, location included, in a single allocation, as the arguments
+//! are formatted straight into it rather than into an intermediate string. The message is formatted
+//! when the error is created, so an error that is created and then discarded, e.g. by a retry loop,
+//! still costs that allocation, unless `lazy` is given as the first argument of a macro, e.g.
+//! `option_err!(lazy, "Gave up after {} attempts", attempts)`. The arguments are then moved into
+//! the error, so they must be owned, and the message is only formatted when the error is shown.
+//!
+//! # Examples
+//! ```
+//! use tyg_errors::{Result, result_err_bare};
+//!
+//! fn parse(text: &str) -> Result<u32> {
+//!     text.parse::<u32>().or_else(|e| result_err_bare!("{}: {}", text, e))
+//! }
+//!
+//! assert_eq!(parse("42").unwrap(), 42);
+//! assert!(parse("NaN").is_err());
+//! ```
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+mod collection;
+pub use collection::{ErrorCollection, Recovery};
+mod kind;
+pub use kind::ErrorKind;
+mod scope;
+pub use scope::{current_scopes, scope, ScopeGuard, Scoped};
+mod ext;
+pub use ext::{FilterOk, MapOk, OptionExt, ResultExt, ResultIteratorExt, TakeUntilErr};
+
+/// A specialized [`Result`] type for use with the command line template.
+///
+/// This typedef is generally used to simplify [`Result`] usage when using the template
+///
+/// # Examples
+/// ```
+/// use tyg_errors::{Result, Error, option_err};
+///
+/// fn generate_error() -> Result<()> {
+///     let value = None;
+///     let message = "This is a test error messaage";
+///     value.ok_or_else(|| option_err!("{}", message))?;
+///     Ok(())
+/// }
+///
+/// let result = generate_error();
+///
+/// assert!(result.is_err());
+/// println!("{:?}", result);
+/// ```
+pub type Result<T> = std::result::Result<T, Error>;
+
+// Whether bare errors disclose their location. This is exposed for use by the macros, so that the
+// feature is taken from this crate rather than from the crate using the macros.
+#[doc(hidden)]
+pub const DISCLOSE: bool = cfg!(feature = "disclose");
+
+// The core of the error macros, which creates the error with or without its location. Every macro
+// expands to this so that they cannot drift apart. The location is that of the outermost macro
+// invocation, i.e. the call to the public macro.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __tyg_err {
+    ( $mode:ident, lazy, kind = $kind:expr, $( $arg:expr),+ ) => {
+        $crate::__tyg_err!($mode, lazy, $($arg),+).with_kind($kind)
+    };
+    ( $mode:ident, kind = $kind:expr, $( $arg:expr),+ ) => {
+        $crate::__tyg_err!($mode, $($arg),+).with_kind($kind)
+    };
+    ( disclosed, lazy, $( $arg:expr),+ ) => {
+        $crate::Error::Deferred(::std::boxed::Box::new(move |f: &mut ::std::fmt::Formatter<'_>| {
+            ::std::write!(f, "{}:{}:{}: {}", file!(), line!(), column!(), format_args!( $($arg,)+ ))
+        })).scoped()
+    };
+    // The arguments are moved into a single closure, which decides whether to disclose the location
+    ( bare, lazy, $( $arg:expr),+ ) => {
+        $crate::Error::Deferred(::std::boxed::Box::new(move |f: &mut ::std::fmt::Formatter<'_>| {
+            if $crate::DISCLOSE {
+                ::std::write!(f, "{}:{}:{}: ", file!(), line!(), column!())?;
+            }
+            ::std::write!(f, $($arg,)+)
+        })).scoped()
+    };
+    ( disclosed, $( $arg:expr),+ ) => {
+        $crate::Error::Error(format!("{}:{}:{}: {}", file!(), line!(), column!(), format_args!( $($arg,)+ ))).scoped()
+    };
+    ( bare, $( $arg:expr),+ ) => {
+        if $crate::DISCLOSE {
+            $crate::__tyg_err!(disclosed, $($arg),+)
+        } else {
+            $crate::Error::Error(format!( $($arg,)+ )).scoped()
+        }
+    };
+}
+
+//  option_err macro
+/// Macro to prepare a disclosed error when transforming an `Option<T>` into a `Result<T, E>`, that
+/// can be handled by the calling context using the '?' operator or by simply returning it.
+///
+/// The macro creates an error message containing the name of the source file and the
+/// location in the source where the error occured. This is ideal for debugging purposes.
+///
+/// This macro is particularly useful when using
+/// [`ok_or_else()`](https://doc.rust-lang.org/std/option/enum.Option.html#method.ok_or_else)
+///
+/// # Examples
+/// ```
+/// use tyg_errors::{Result, Error, option_err};
+///
+/// fn generate_error() -> Result<()> {
+///     let value = None;
+///     let message = "This is a test error messaage";
+///     value.ok_or_else(|| option_err!("{}", message))?;
+///     Ok(())
+/// }
+///
+/// let result = generate_error();
+///
+/// assert!(result.is_err());
+/// println!("{:?}", result);
+/// ```
+#[macro_export]
+macro_rules! option_err {
+    ( lazy, kind = $kind:expr, $( $arg:expr),+ ) => {
+        $crate::__tyg_err!(disclosed, lazy, kind = $kind, $($arg),+)
+    };
+    ( lazy, $( $arg:expr),+ ) => {
+        $crate::__tyg_err!(disclosed, lazy, $($arg),+)
+    };
+    ( kind = $kind:expr, $( $arg:expr),+ ) => {
+        $crate::__tyg_err!(disclosed, kind = $kind, $($arg),+)
+    };
+    ( $( $arg:expr),+ ) => {
+        $crate::__tyg_err!(disclosed, $($arg),+)
+    };
+}
+
+//  option_err_bare macro
+/// Macro to prepare a non-disclosed error when transforming an `Option<T>` into a `Result<T, E>`, that
+/// can be handled by the calling context using the '?' operator or by simply returning it.
+///
+/// The macro creates an error message, but does not disclose the location where the
+/// error occured. This is ideal for for presenting the end user with the cause of the error.
+///
+/// This macro is particularly useful when using
+/// [`ok_or_else()`](https://doc.rust-lang.org/std/option/enum.Option.html#method.ok_or_else)
+///
+/// If the code is compiled with the 'disclose' feature enabled, the macro creates an error message
+/// containing the name of the source file and the location in the source where the error occured.
+/// It is equivalent to the macro called option_err
+///
+/// Given `lazy` as its first argument, as every error macro can be, the macro moves the arguments
+/// into the error and only formats the message when the error is shown, so that an error which is
+/// discarded, e.g. by a retry loop, is never formatted.
+///
+/// # Examples
+/// ```
+/// use tyg_errors::{Result, Error, option_err_bare};
+///
+/// fn generate_error() -> Result<()> {
+///     let value = None;
+///     let message = "This is a test error messaage";
+///     value.ok_or_else(|| option_err_bare!("{}", message))?;
+///     Ok(())
+/// }
+///
+/// let result = generate_error();
+///
+/// assert!(result.is_err());
+/// println!("{:?}", result);
+/// ```
+///
+/// ```
+/// use std::fmt;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use tyg_errors::{option_err_bare, Error, ErrorKind};
+///
+/// static FORMATTED: AtomicUsize = AtomicUsize::new(0);
+///
+/// struct Attempts(u32);
+///
+/// impl fmt::Display for Attempts {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         FORMATTED.fetch_add(1, Ordering::SeqCst);
+///         write!(f, "{} attempts", self.0)
+///     }
+/// }
+///
+/// let error = None::<u32>.ok_or_else(|| option_err_bare!(lazy, "Gave up after {}", Attempts(3)))
+///     .unwrap_err();
+///
+/// assert!(matches!(error.inner(), Error::Deferred(_)));
+/// assert_eq!(FORMATTED.load(Ordering::SeqCst), 0);
+/// assert!(error.to_string().ends_with("Gave up after 3 attempts"));
+/// assert_eq!(FORMATTED.load(Ordering::SeqCst), 1);
+/// assert_eq!(option_err_bare!(lazy, kind = ErrorKind::Config, "No {}", "port").kind(),
+///            ErrorKind::Config);
+/// ```
+#[macro_export]
+macro_rules! option_err_bare {
+    ( lazy, kind = $kind:expr, $( $arg:expr),+ ) => {
+        $crate::__tyg_err!(bare, lazy, kind = $kind, $($arg),+)
+    };
+    ( lazy, $( $arg:expr),+ ) => {
+        $crate::__tyg_err!(bare, lazy, $($arg),+)
+    };
+    ( kind = $kind:expr, $( $arg:expr),+ ) => {
+        $crate::__tyg_err!(bare, kind = $kind, $($arg),+)
+    };
+    ( $( $arg:expr),+ ) => {
+        $crate::__tyg_err!(bare, $($arg),+)
+    };
+}
+
+//  result_err macro
+/// Macro to prepare a disclosed error of type `Result<T, E>` that can be handled by the calling
+/// context either by using the '?' operator or by simply returning it.
+///
+/// The macro creates an error message containing the name of the source file and the location in
+/// the source where the error occured. This is ideal for debugging purposes.
+///
+/// This macro is particularly useful when using
+/// [`or_else()`](https://doc.rust-lang.org/stable/std/result/enum.Result.html#method.or_else)
+///
+/// # Examples
+/// ```
+/// use tyg_errors::{Result, Error, result_err};
+///
+/// fn generate_error() -> Result<()> {
+///     "NaN".parse::<u32>().or_else(|e| result_err!("Oh dear - {}", e))?;
+///     Ok(())
+/// }
+///
+/// let result = generate_error();
+///
+/// assert!(result.is_err());
+/// println!("{:?}", result);
+/// ```
+///
+/// An optional first argument gives the error a [kind](ErrorKind), which is otherwise
+/// [`ErrorKind::General`]. The same argument is taken by the other error macros.
+///
+/// ```
+/// use tyg_errors::{Result, ErrorKind, result_err};
+///
+/// fn port(value: &str) -> Result<u16> {
+///     value.parse().or_else(|e| result_err!(kind = ErrorKind::Config, "Invalid port {}: {}", value, e))
+/// }
+///
+/// let error = port("http").unwrap_err();
+///
+/// assert_eq!(error.kind(), ErrorKind::Config);
+/// assert_eq!(error.kind().exit_code(), 78);
+/// assert!(error.to_string().ends_with("Invalid port http: invalid digit found in string"));
+/// ```
+#[macro_export]
+macro_rules! result_err {
+    ( lazy, kind = $kind:expr, $( $arg:expr),+ ) => {{
+        let error = $crate::__tyg_err!(disclosed, lazy, kind = $kind, $($arg),+);
+        Err(error)
+    }};
+    ( lazy, $( $arg:expr),+ ) => {{
+        let error = $crate::__tyg_err!(disclosed, lazy, $($arg),+);
+        Err(error)
+    }};
+    ( kind = $kind:expr, $( $arg:expr),+ ) => {{
+        let error = $crate::__tyg_err!(disclosed, kind = $kind, $($arg),+);
+        Err(error)
+    }};
+    ( $( $arg:expr),+ ) => {{
+        // Bound first so that clippy does not suggest map_err for the documented or_else usage
+        let error = $crate::__tyg_err!(disclosed, $($arg),+);
+        Err(error)
+    }};
+}
+
+//  result_err_bare macro
+/// Macro to prepare a non-disclosed error of type `Result<T, E>` that can be handled by the calling
+/// context either by using the '?' operator or by simply returning it.
+///
+/// The macro creates an error message, but does not disclose the location where the error occured.
+/// This is ideal for for presenting the end user with the cause of the error.
+///
+/// This macro is particularly useful when using
+/// [`or_else()`](https://doc.rust-lang.org/stable/std/result/enum.Result.html#method.or_else)
+///
+/// If the code is compiled with the 'disclose' feature enabled, the macro creates an error message
+/// containing the name of the source file and the location in the source where the error occured.
+/// It is equivalent to the macro called result_err
+///
+/// # Examples
+/// ```
+/// use tyg_errors::{Result, Error, result_err_bare};
+///
+/// fn generate_error() -> Result<()> {
+///     "NaN".parse::<u32>().or_else(|e| result_err_bare!("Oh dear - {}", e))?;
+///     Ok(())
+/// }
+///
+/// let result = generate_error();
+///
+/// assert!(result.is_err());
+/// println!("{:?}", result);
+/// ```
+#[macro_export]
+macro_rules! result_err_bare {
+    ( lazy, kind = $kind:expr, $( $arg:expr),+ ) => {{
+        let error = $crate::__tyg_err!(bare, lazy, kind = $kind, $($arg),+);
+        Err(error)
+    }};
+    ( lazy, $( $arg:expr),+ ) => {{
+        let error = $crate::__tyg_err!(bare, lazy, $($arg),+);
+        Err(error)
+    }};
+    ( kind = $kind:expr, $( $arg:expr),+ ) => {{
+        let error = $crate::__tyg_err!(bare, kind = $kind, $($arg),+);
+        Err(error)
+    }};
+    ( $( $arg:expr),+ ) => {{
+        // Bound first so that clippy does not suggest map_err for the documented or_else usage
+        let error = $crate::__tyg_err!(bare, $($arg),+);
+        Err(error)
+    }};
+}
+
+macro_rules! formatter {
+    ( $self:expr, $f:expr ) => {
+        match *$self {
+            Error::Error(ref e) => write!($f, "{}", e),
+            Error::Deferred(ref message) => message($f),
+            Error::File(ref e) => fmt::Display::fmt(e, $f),
+            Error::Cli(ref e) => fmt::Display::fmt(e, $f),
+            Error::Network(ref e) => fmt::Display::fmt(e, $f),
+            Error::Verification(ref e) => write!($f, "{}", e),
+            Error::ResourceLimit(ref e) => write!($f, "{}", e),
+            Error::SandboxViolation { ref path } => write!(
+                $f,
+                "{}: Outside the sandbox, the change was not made",
+                path.display()
+            ),
+            Error::Classified(_, ref e) => fmt::Display::fmt(e, $f),
+            Error::Scoped(ref e) => fmt::Display::fmt(&e.error, $f),
+            #[cfg(feature = "http")]
+            Error::Http(ref e) => fmt::Display::fmt(e, $f),
+        }
+    };
+}
+
+// pub enum error
+/// The template error enumeration is used to define the various error types that can be handled by
+/// the template crate. The only variant that is required for basic operation is the Error variant,
+/// along with the Deferred variant created by the error macros when the message is formatted
+/// lazily. The File variant has been included for demonstration purposes, the Cli variant carries
+/// command line parsing errors, the Network variant carries the errors of network operations, the
+/// Verification variant carries data that failed a check such as a checksum, the ResourceLimit
+/// variant stops a run that exceeded a limit on its resources, the SandboxViolation variant refuses
+/// a change to a file outside the directories the run is confined to, the Classified variant
+/// gives another error an explicit [kind](ErrorKind), the Scoped variant records the
+/// [scopes](with_scope) in which another error happened, and the Http variant,
+/// which is enabled by the `http` feature, carries the errors of HTTP requests. As the Classified
+/// and Scoped variants wrap an error of another variant, match on [`Error::inner`] to find it.
+///
+/// Remember the following when adding new variants:
+/// - Add the variant to the formatter macro.
+/// - Add the variant to the source function.
+/// - Implement From for the new variant.
+///
+/// In a project built from the template, the `generate error-variant` subcommand does all of this
+/// for a variant wrapping another error type, e.g.
+/// `cargo run -- generate error-variant Json --wraps serde_json::Error`.
+///
+/// For an idea on how to implement error variants have a look at the source code here.
+///
+/// # Size
+///
+/// Every function returning a [`Result`] carries an `Error`, so it is kept no larger than a
+/// `String`, which is the size of the Error variant. A variant wrapping a larger error type should
+/// wrap it in a `Box`, as the Http variant does, and its `From` implementation should box the
+/// error. The enum itself is not boxed down to the size of a pointer. Only the Classified and
+/// Scoped variants, which wrap another error, are matched through [`Error::inner`], while the rest
+/// are matched and created directly; a boxed enum would put every variant behind a constructor and
+/// an accessor, change the code the `generate error-variant` subcommand writes, and cost another
+/// allocation for each error. The example below keeps it from growing past a `String`.
+///
+/// ```
+/// use std::mem::size_of;
+/// use tyg_errors::{Error, Result};
+///
+/// assert!(size_of::<Error>() <= size_of::<String>());
+/// assert!(size_of::<Result<()>>() <= size_of::<String>());
+/// ```
+pub enum Error {
+    /// Custom Error of type `String`.
+    Error(String),
+    /// Custom Error whose message is only formatted when it is shown, as created by the error
+    /// macros given `lazy` as their first argument. The closure is boxed to keep the Error no
+    /// larger than a `String`.
+    Deferred(Box<dyn Fn(&mut fmt::Formatter) -> fmt::Result + Send + Sync>),
+    /// Error of type `io::Error`.
+    File(io::Error),
+    /// Error of type `clap::Error`.
+    Cli(clap::Error),
+    /// Error of type `io::Error` from a network operation, with a message that describes the
+    /// failure to the user. Its kind tells whether the failure is [transient](Error::is_transient).
+    Network(io::Error),
+    /// Custom Error of type `Box<str>` for data that was received intact but is not what was
+    /// expected, e.g. a download whose checksum does not match. The message is boxed to keep the
+    /// Error no larger than a `String`.
+    Verification(Box<str>),
+    /// Custom Error of type `Box<str>` for a run that exceeded a limit set on its resources, such
+    /// as its memory or runtime, with a message naming the limit exceeded.
+    ResourceLimit(Box<str>),
+    /// A change to the filing system that was refused because its path is outside the directories
+    /// to which the run is confined. The path is boxed to keep the Error no larger than a `String`.
+    SandboxViolation {
+        /// The path of the file or directory that would have been changed.
+        path: Box<Path>,
+    },
+    /// An error of another variant given an explicit [kind](ErrorKind), as the error macros do
+    /// when a kind is given and [`Error::with_kind`] does. It is shown as the error it holds, which
+    /// is boxed to keep the Error no larger than a `String`.
+    Classified(ErrorKind, Box<Error>),
+    /// An error of another variant created within [scopes](with_scope), as the error macros and
+    /// conversions do when a scope is open. It is shown as the error it holds, which is boxed
+    /// with the labels of the scopes to keep the Error no larger than a `String`.
+    Scoped(Box<Scoped>),
+    /// Error of type `ureq::Error`, covering DNS, connection, TLS, status and body failures of an
+    /// HTTP request. Only available with the `http` feature.
+    #[cfg(feature = "http")]
+    Http(Box<ureq::Error>),
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        formatter!(self, f)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        formatter!(self, f)
+    }
+}
+
+impl Error {
+    /// The error without the kind given to it or the scopes it was created in, for matching on its
+    /// variant. An error given a kind when it is created, e.g. by
+    /// `result_err!(kind = ErrorKind::Config, ...)`, or by [`Error::with_kind`], is held in the
+    /// Classified variant, and an error created within a scope, e.g. by `?` on an `io::Error`
+    /// inside [`with_scope!`], in the Scoped variant, so both are matched through this.
+    ///
+    /// # Examples
+    /// ```
+    /// use tyg_errors::{result_err_bare, Error, ErrorKind, Result};
+    ///
+    /// let result: Result<()> = result_err_bare!(kind = ErrorKind::Config, "Invalid port");
+    /// let error = result.unwrap_err();
+    ///
+    /// assert!(matches!(error, Error::Classified(ErrorKind::Config, _)));
+    /// assert!(matches!(error.inner(), Error::Error(_)));
+    /// assert!(matches!(Error::Error("Failed".to_string()).inner(), Error::Error(_)));
+    /// ```
+    ///
+    /// ```
+    /// use tyg_errors::{with_scope, Error, Result};
+    ///
+    /// fn read() -> Result<String> {
+    ///     with_scope!("reading settings", {
+    ///         Ok(std::fs::read_to_string("/nonexistent/settings.toml")?)
+    ///     })
+    /// }
+    ///
+    /// let error = read().unwrap_err();
+    ///
+    /// assert_eq!(error.scopes(), ["reading settings"]);
+    /// assert!(matches!(error.inner(), Error::File(_)));
+    /// ```
+    pub fn inner(&self) -> &Error {
+        match *self {
+            Error::Classified(_, ref e) => e.inner(),
+            Error::Scoped(ref e) => e.error.inner(),
+            ref error => error,
+        }
+    }
+
+    /// Whether the error is a network failure that may not happen again if the operation is
+    /// retried, such as a timeout, a refused connection or, for HTTP requests, a response status of
+    /// 429 or 5xx.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io;
+    /// use tyg_errors::Error;
+    ///
+    /// let timeout = io::Error::new(io::ErrorKind::TimedOut, "example.com:443: No response");
+    /// let denied = io::Error::new(io::ErrorKind::PermissionDenied, "example.com:443: Denied");
+    ///
+    /// assert!(Error::Network(timeout).is_transient());
+    /// assert!(!Error::Network(denied).is_transient());
+    /// assert!(!Error::Error("Something failed".to_string()).is_transient());
+    /// ```
+    pub fn is_transient(&self) -> bool {
+        match *self {
+            Error::Classified(_, ref e) => e.is_transient(),
+            Error::Scoped(ref e) => e.error.is_transient(),
+            Error::Network(ref e) => matches!(
+                e.kind(),
+                io::ErrorKind::TimedOut
+                    | io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::NotConnected
+                    | io::ErrorKind::HostUnreachable
+                    | io::ErrorKind::NetworkUnreachable
+                    | io::ErrorKind::NetworkDown
+                    | io::ErrorKind::Interrupted
+            ),
+            #[cfg(feature = "http")]
+            Error::Http(ref e) => match **e {
+                ureq::Error::StatusCode(status) => status == 429 || status >= 500,
+                ureq::Error::Timeout(_)
+                | ureq::Error::HostNotFound
+                | ureq::Error::ConnectionFailed
+                | ureq::Error::Io(_) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Error(_) => None,
+            Error::Deferred(_) => None,
+            Error::File(ref e) => Some(e),
+            Error::Cli(ref e) => Some(e),
+            Error::Network(ref e) => Some(e),
+            Error::Verification(_) => None,
+            Error::ResourceLimit(_) => None,
+            Error::SandboxViolation { .. } => None,
+            Error::Classified(_, ref e) => e.source(),
+            Error::Scoped(ref e) => e.error.source(),
+            #[cfg(feature = "http")]
+            Error::Http(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::File(err).scoped()
+    }
+}
+
+impl From<clap::Error> for Error {
+    fn from(err: clap::Error) -> Error {
+        Error::Cli(err).scoped()
+    }
+}
+
+#[cfg(feature = "http")]
+impl From<ureq::Error> for Error {
+    fn from(err: ureq::Error) -> Error {
+        Error::Http(Box::new(err)).scoped()
+    }
+}
+
+/// The location of the source of an error, as disclosed at the start of its message by the error
+/// macros in the form `file:line:column: message`.
+///
+/// # Examples
+/// ```
+/// use tyg_errors::{result_err, Location, Result};
+///
+/// let result: Result<()> = result_err!("Something failed");
+/// let message = result.unwrap_err().to_string();
+/// let (location, rest) = Location::split(&message).unwrap();
+///
+/// assert!(location.file.ends_with(".rs"));
+/// assert!(location.line > 0 && location.column > 0);
+/// assert_eq!(rest, "Something failed");
+/// assert!(Location::split("Ratio 1:2: too small").is_none());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location<'a> {
+    /// The path of the source file.
+    pub file: &'a str,
+    /// The line number, starting at 1.
+    pub line: u32,
+    /// The column number, starting at 1.
+    pub column: u32,
+}
+
+impl<'a> Location<'a> {
+    /// Split the disclosed location from the start of an error message, returning the location and
+    /// the rest of the message, or `None` if the message does not start with a location.
+    pub fn split(message: &'a str) -> Option<(Location<'a>, &'a str)> {
+        let mut fields = message.splitn(4, ':');
+        let (file, line, column) = (fields.next()?, fields.next()?, fields.next()?);
+        let rest = fields.next()?.strip_prefix(' ')?;
+        if file.is_empty()
+            || file.contains(char::is_whitespace)
+            || !is_number(line)
+            || !is_number(column)
+        {
+            return None;
+        }
+        let location = Location {
+            file,
+            line: line.parse().ok()?,
+            column: column.parse().ok()?,
+        };
+        Some((location, rest))
+    }
+}
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+fn is_number(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(feature = "testing")]
+pub mod strategy;
@@ -0,0 +1,175 @@
+//! Scopes naming the phase of work in which errors happen, e.g. `loading configuration`, so that an
+//! error carries the trail of phases that led to it without every error message repeating them.
+//!
+//! A scope is opened with [`with_scope!`](crate::with_scope) around a block, or with a
+//! [`ScopeGuard`] returned by [`scope`] that closes it when dropped. The open scopes form a stack
+//! kept for each thread, so work handed to another thread starts outside any scope. The error
+//! macros, the [`ResultExt`](crate::ResultExt) and [`OptionExt`](crate::OptionExt) methods and
+//! the `From` conversions record the scopes open when they create an error in the
+//! [`Scoped`](Error::Scoped) variant, and an error that replaces another keeps the scopes of the
+//! original. The error is shown as before, [`Error::scopes`] gives the trail to the renderer, and
+//! [`Error::inner`] gives the error of the original variant for matching.
+//!
+//! # Examples
+//! ```
+//! use tyg_errors::{result_err_bare, with_scope, Result};
+//!
+//! fn parse_port(text: &str) -> Result<u16> {
+//!     text.parse().or_else(|_| result_err_bare!("Invalid port {}", text))
+//! }
+//!
+//! fn load() -> Result<u16> {
+//!     with_scope!("loading configuration", {
+//!         let _settings = tyg_errors::scope("reading settings.toml");
+//!         parse_port("http")
+//!     })
+//! }
+//!
+//! let error = load().unwrap_err();
+//!
+//! assert!(error.to_string().ends_with("Invalid port http"));
+//! assert_eq!(error.scopes(), ["loading configuration", "reading settings.toml"]);
+//! assert!(parse_port("http").unwrap_err().scopes().is_empty());
+//! ```
+
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+use crate::Error;
+
+thread_local! {
+    // The labels of the scopes open on this thread, outermost first
+    static SCOPES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// An error of another variant together with the scopes that were open when it was created.
+#[derive(Debug)]
+pub struct Scoped {
+    /// The labels of the scopes, outermost first.
+    pub scopes: Vec<String>,
+    /// The error.
+    pub error: Error,
+}
+
+/// A scope open on the current thread, which is closed when the guard is dropped. Closing a scope
+/// also closes any scope opened within it that is still open.
+#[must_use = "the scope is closed as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct ScopeGuard {
+    depth: usize,
+    // The scope belongs to the stack of the thread that opened it
+    _thread: PhantomData<*const ()>,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        SCOPES.with_borrow_mut(|scopes| scopes.truncate(self.depth));
+    }
+}
+
+/// Open a scope with the given label on the current thread, until the returned guard is dropped.
+///
+/// # Examples
+/// ```
+/// use tyg_errors::{current_scopes, scope};
+///
+/// {
+///     let _configuration = scope("loading configuration");
+///     let _file = scope("reading settings.toml");
+///     assert_eq!(current_scopes(), ["loading configuration", "reading settings.toml"]);
+/// }
+/// assert!(current_scopes().is_empty());
+/// ```
+pub fn scope(label: impl Into<String>) -> ScopeGuard {
+    let depth = SCOPES.with_borrow_mut(|scopes| {
+        scopes.push(label.into());
+        scopes.len() - 1
+    });
+    ScopeGuard {
+        depth,
+        _thread: PhantomData,
+    }
+}
+
+/// The labels of the scopes open on the current thread, outermost first.
+pub fn current_scopes() -> Vec<String> {
+    SCOPES.with_borrow(|scopes| scopes.clone())
+}
+
+//  with_scope macro
+/// Macro to run a block within a [scope](scope) with the given label, so that the errors created
+/// in the block, including those of the functions it calls, carry the label. The value of the
+/// block is the value of the macro, and `?` and `return` in the block leave the enclosing function
+/// as usual.
+///
+/// # Examples
+/// ```
+/// use std::fs;
+/// use tyg_errors::{with_scope, Result};
+///
+/// fn load() -> Result<String> {
+///     let text = with_scope!("loading configuration", { fs::read_to_string("no_such_settings.toml")? });
+///     Ok(text)
+/// }
+///
+/// assert_eq!(load().unwrap_err().scopes(), ["loading configuration"]);
+/// ```
+#[macro_export]
+macro_rules! with_scope {
+    ( $label:expr, $body:block ) => {{
+        let _scope = $crate::scope($label);
+        $body
+    }};
+}
+
+impl Error {
+    /// The labels of the scopes that were open when the error was created, outermost first, or an
+    /// empty slice if there were none.
+    pub fn scopes(&self) -> &[String] {
+        match *self {
+            Error::Scoped(ref e) => &e.scopes,
+            Error::Classified(_, ref e) => e.scopes(),
+            _ => &[],
+        }
+    }
+
+    /// Give the error the scopes with the given labels, replacing the scopes it had. The message,
+    /// kind and source of the error are kept.
+    ///
+    /// # Examples
+    /// ```
+    /// use tyg_errors::{Error, ErrorKind};
+    ///
+    /// let error = Error::Error("Invalid port".to_string()).with_kind(ErrorKind::Config);
+    /// let error = error.in_scopes(vec!["loading configuration".to_string()]);
+    ///
+    /// assert_eq!(error.scopes(), ["loading configuration"]);
+    /// assert_eq!(error.kind(), ErrorKind::Config);
+    /// assert_eq!(error.to_string(), "Invalid port");
+    /// ```
+    pub fn in_scopes(self, scopes: Vec<String>) -> Error {
+        match self {
+            Error::Classified(kind, error) => {
+                Error::Classified(kind, Box::new(error.in_scopes(scopes)))
+            }
+            Error::Scoped(scoped) if scopes.is_empty() => scoped.error,
+            Error::Scoped(mut scoped) => {
+                scoped.scopes = scopes;
+                Error::Scoped(scoped)
+            }
+            error if scopes.is_empty() => error,
+            error => Error::Scoped(Box::new(Scoped { scopes, error })),
+        }
+    }
+
+    /// Give the error the scopes open on the current thread, unless it already has scopes. The
+    /// error macros and conversions do this, so it is only needed for an error created otherwise,
+    /// e.g. as an `Error::Error` from a message.
+    pub fn scoped(self) -> Error {
+        if !self.scopes().is_empty() {
+            return self;
+        }
+        let scopes = current_scopes();
+        self.in_scopes(scopes)
+    }
+}
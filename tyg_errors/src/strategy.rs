@@ -0,0 +1,103 @@
+//! Proptest strategies for generating errors.
+//!
+//! These strategies, along with the [`Arbitrary`] implementation for [`Error`], are available when
+//! the `testing` feature is enabled. They deliberately favour awkward input such as control
+//! characters, newlines and colons that resemble error locations.
+//!
+//! # Examples
+//! ```
+//! use proptest::prelude::*;
+//! use proptest::test_runner::TestRunner;
+//! use tyg_errors::{strategy, Error};
+//!
+//! let mut runner = TestRunner::default();
+//!
+//! runner.run(&any::<Error>(), |error| {
+//!     prop_assert_eq!(format!("{}", error), format!("{:?}", error));
+//!     Ok(())
+//! }).unwrap();
+//!
+//! runner.run(&strategy::message(), |message| {
+//!     prop_assert_eq!(Error::Error(message.clone()).to_string(), message);
+//!     Ok(())
+//! }).unwrap();
+//! ```
+
+use std::io;
+use std::path::PathBuf;
+
+use proptest::prelude::*;
+
+use crate::{Error, ErrorKind};
+
+/// A strategy generating error messages.
+///
+/// The messages range from arbitrary Unicode, including control characters and newlines, to text
+/// made up of the characters that appear in a disclosed error location.
+pub fn message() -> impl Strategy<Value = String> {
+    prop_oneof![
+        prop::collection::vec(any::<char>(), 0..64).prop_map(|chars| chars.into_iter().collect()),
+        "\\PC{0,64}",
+        "[a-z0-9/.:\\n\\r\\t ]{0,64}",
+    ]
+}
+
+/// A strategy generating `io::Error` values, both custom and from operating system error codes.
+pub fn io_error() -> impl Strategy<Value = io::Error> {
+    let kinds = [
+        io::ErrorKind::NotFound,
+        io::ErrorKind::PermissionDenied,
+        io::ErrorKind::AlreadyExists,
+        io::ErrorKind::InvalidInput,
+        io::ErrorKind::InvalidData,
+        io::ErrorKind::UnexpectedEof,
+        io::ErrorKind::Other,
+    ];
+    prop_oneof![
+        (prop::sample::select(kinds.to_vec()), message())
+            .prop_map(|(kind, message)| io::Error::new(kind, message)),
+        (1..150i32).prop_map(io::Error::from_raw_os_error),
+    ]
+}
+
+/// A strategy generating error kinds.
+pub fn kind() -> impl Strategy<Value = ErrorKind> {
+    prop::sample::select(ErrorKind::ALL.to_vec())
+}
+
+/// A strategy generating `clap::Error` values.
+pub fn cli_error() -> impl Strategy<Value = clap::Error> {
+    let kinds = [
+        clap::error::ErrorKind::InvalidValue,
+        clap::error::ErrorKind::UnknownArgument,
+        clap::error::ErrorKind::InvalidSubcommand,
+        clap::error::ErrorKind::MissingRequiredArgument,
+        clap::error::ErrorKind::ArgumentConflict,
+        clap::error::ErrorKind::ValueValidation,
+    ];
+    (prop::sample::select(kinds.to_vec()), message())
+        .prop_map(|(kind, message)| clap::Error::raw(kind, message))
+}
+
+impl Arbitrary for Error {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Error>;
+
+    fn arbitrary_with(_args: ()) -> Self::Strategy {
+        prop_oneof![
+            message().prop_map(Error::Error),
+            io_error().prop_map(Error::File),
+            cli_error().prop_map(Error::Cli),
+            io_error().prop_map(Error::Network),
+            message().prop_map(|message| Error::Verification(message.into())),
+            message().prop_map(|message| Error::ResourceLimit(message.into())),
+            message().prop_map(|path| Error::SandboxViolation {
+                path: PathBuf::from(path).into()
+            }),
+            (kind(), message()).prop_map(|(kind, message)| Error::Error(message).with_kind(kind)),
+            (message(), message())
+                .prop_map(|(scope, message)| Error::Error(message).in_scopes(vec![scope])),
+        ]
+        .boxed()
+    }
+}